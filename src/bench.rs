@@ -0,0 +1,87 @@
+use crate::game::GameState;
+use std::time::Instant;
+
+/// Canned positions as `(x, y, field)` stones, field 1 ours and 2 theirs;
+/// we are to move in each.
+const POSITIONS: [&[(usize, usize, usize)]; 3] = [
+    &[(10, 10, 2), (9, 9, 1), (11, 10, 2)],
+    &[
+        (10, 10, 1),
+        (10, 9, 2),
+        (11, 11, 1),
+        (9, 8, 2),
+        (12, 12, 2),
+        (9, 10, 1),
+    ],
+    &[
+        (7, 7, 2),
+        (8, 8, 1),
+        (8, 7, 2),
+        (9, 7, 1),
+        (7, 9, 2),
+        (9, 9, 1),
+        (6, 8, 2),
+        (10, 10, 1),
+    ],
+];
+
+/// Engine set up on canned position `index`, without its opening book.
+fn engine_for(index: usize) -> GameState {
+    let mut game = GameState::new();
+    game.handle_start(20);
+    let _ = game.handle_board_start();
+    for &(x, y, field) in POSITIONS[index] {
+        let _ = game.handle_board_move(x, y, field);
+    }
+    game
+}
+
+/// Nodes a fresh engine visits searching position `index` to `depth`.
+fn position_nodes(index: usize, depth: usize) -> u64 {
+    engine_for(index).search_nodes(depth)
+}
+
+/// Searches every canned position to `depth` and reports nodes, time and
+/// nodes per second for each, then the totals.
+pub fn run(depth: usize) -> String {
+    let mut lines = Vec::new();
+    let mut total_nodes = 0;
+    let started = Instant::now();
+    for index in 0..POSITIONS.len() {
+        let position_started = Instant::now();
+        let nodes = position_nodes(index, depth);
+        total_nodes += nodes;
+        lines.push(report(
+            &format!("position {}", index + 1),
+            nodes,
+            position_started,
+        ));
+    }
+    lines.push(report("total", total_nodes, started));
+    lines.join("\n")
+}
+
+fn report(label: &str, nodes: u64, started: Instant) -> String {
+    let elapsed = started.elapsed();
+    let nps = nodes as f64 / elapsed.as_secs_f64().max(1e-9);
+    format!(
+        "{}: nodes={} time={}ms nps={:.0}",
+        label,
+        nodes,
+        elapsed.as_millis(),
+        nps
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_count_is_deterministic() {
+        let nodes = position_nodes(0, 2);
+        assert!(nodes > 0);
+        assert_eq!(position_nodes(0, 2), nodes);
+        assert!(position_nodes(0, 1) < nodes);
+    }
+}
@@ -1,5 +1,16 @@
+use crate::error::GameError;
+use crate::zobrist::ZobristKeys;
 use std::fmt;
 
+pub const MAX_BOARD_CELLS: usize = 400;
+pub const MIN_BOARD_SIDE: usize = 5;
+/// Widest board accepted, since each row is a single bitboard word.
+pub const MAX_BOARD_WIDTH: usize = u64::BITS as usize;
+
+pub const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+pub const SYMMETRIES: usize = 8;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[repr(u8)]
 pub enum Cell {
@@ -22,38 +33,133 @@ impl fmt::Display for Cell {
     }
 }
 
-#[derive(Clone, Copy)]
+impl Cell {
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            '.' => Some(Cell::Empty),
+            'X' => Some(Cell::MyStone),
+            'O' => Some(Cell::OpStone),
+            'F' => Some(Cell::Forbidden),
+            _ => None,
+        }
+    }
+
+    pub fn from_board_field(field: usize) -> Option<Self> {
+        match field {
+            0 => Some(Cell::Empty),
+            1 => Some(Cell::MyStone),
+            2 => Some(Cell::OpStone),
+            3 => Some(Cell::Forbidden),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn opponent(self) -> Cell {
+        match self {
+            Cell::MyStone => Cell::OpStone,
+            Cell::OpStone => Cell::MyStone,
+            other => other,
+        }
+    }
+
+    #[inline]
+    pub fn is_stone(self) -> bool {
+        matches!(self, Cell::MyStone | Cell::OpStone)
+    }
+}
+
+#[derive(Clone)]
 pub struct Board {
-    cells: [Cell; 400],
-    size: usize,
+    cells: Vec<Cell>,
+    width: usize,
+    height: usize,
     hash: u64,
+    occupied: Vec<u16>,
+    occupied_slot: Vec<u16>,
+    last_move: Option<(usize, usize)>,
+    /// One bit per cell for each player, a `u64` per row with bit x set when
+    /// the stone at (x, y) is theirs. Mirrors `cells`, which stays the truth.
+    rows: [Vec<u64>; 2],
 }
 
 impl Default for Board {
     fn default() -> Self {
-        Self {
-            cells: [Cell::Empty; 400],
-            size: 20,
-            hash: 0,
-        }
+        Self::empty(20, 20)
     }
 }
 
 impl Board {
-    #[allow(dead_code)]
     pub fn new(size: usize) -> Option<Self> {
-        if size != 20 {
-            return None;
+        Self::new_rect(size, size)
+    }
+
+    pub fn new_rect(width: usize, height: usize) -> Option<Self> {
+        let cells = width.checked_mul(height)?;
+        let fits = width >= MIN_BOARD_SIDE
+            && height >= MIN_BOARD_SIDE
+            && width <= MAX_BOARD_WIDTH
+            && cells <= MAX_BOARD_CELLS;
+        fits.then(|| Self::empty(width, height))
+    }
+
+    pub fn from_grid(rows: &[&str]) -> Result<Self, String> {
+        let width = rows
+            .first()
+            .map_or(0, |row| row.chars().filter(|c| !c.is_whitespace()).count());
+        let mut board = Self::new_rect(width, rows.len())
+            .ok_or_else(|| format!("unsupported board {}x{}", width, rows.len()))?;
+        for (y, row) in rows.iter().enumerate() {
+            let cells: Vec<char> = row.chars().filter(|c| !c.is_whitespace()).collect();
+            if cells.len() != width {
+                return Err(format!(
+                    "row {} has {} cells, expected {}",
+                    y,
+                    cells.len(),
+                    width
+                ));
+            }
+            for (x, &c) in cells.iter().enumerate() {
+                let cell = Cell::from_char(c)
+                    .ok_or_else(|| format!("unknown cell '{}' at {},{}", c, x, y))?;
+                if cell != Cell::Empty {
+                    board.set_cell(x, y, cell).map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        Ok(board)
+    }
+
+    fn empty(width: usize, height: usize) -> Self {
+        let cells = width * height;
+        Self {
+            cells: vec![Cell::Empty; cells],
+            width,
+            height,
+            hash: 0,
+            occupied: Vec::with_capacity(cells),
+            occupied_slot: vec![0; cells],
+            last_move: None,
+            rows: [vec![0; height], vec![0; height]],
         }
-        Some(Self::default())
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
     }
 
     #[inline]
     pub fn get_index(&self, x: usize, y: usize) -> Option<usize> {
-        if x >= self.size || y >= self.size {
+        if x >= self.width || y >= self.height {
             None
         } else {
-            Some(y * self.size + x)
+            Some(y * self.width + x)
         }
     }
 
@@ -62,23 +168,80 @@ impl Board {
         self.get_index(x, y).map(|idx| self.cells[idx])
     }
 
-    pub fn set_cell(&mut self, x: usize, y: usize, cell: Cell) -> Result<(), &'static str> {
+    pub fn set_cell(&mut self, x: usize, y: usize, cell: Cell) -> Result<(), GameError> {
         match self.get_index(x, y) {
             Some(idx) => {
+                let old = self.cells[idx];
+                if old.is_stone() && !cell.is_stone() {
+                    self.remove_occupied(idx);
+                    if self.last_move == Some((x, y)) {
+                        self.last_move = None;
+                    }
+                } else if !old.is_stone() && cell.is_stone() {
+                    self.add_occupied(idx);
+                    self.last_move = Some((x, y));
+                }
+                if let Some(side) = bit_side(old) {
+                    self.rows[side][y] &= !(1 << x);
+                }
+                if let Some(side) = bit_side(cell) {
+                    self.rows[side][y] |= 1 << x;
+                }
                 self.cells[idx] = cell;
                 Ok(())
             }
-            None => Err("Coordinates out of bounds"),
+            None => Err(GameError::OutOfBounds),
+        }
+    }
+
+    fn add_occupied(&mut self, idx: usize) {
+        self.occupied_slot[idx] = self.occupied.len() as u16;
+        self.occupied.push(idx as u16);
+    }
+
+    fn remove_occupied(&mut self, idx: usize) {
+        let slot = self.occupied_slot[idx] as usize;
+        self.occupied.swap_remove(slot);
+        if let Some(&moved) = self.occupied.get(slot) {
+            self.occupied_slot[moved as usize] = slot as u16;
         }
     }
 
+    pub fn occupied(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.occupied
+            .iter()
+            .map(move |&idx| (idx as usize % self.width, idx as usize / self.width))
+    }
+
+    #[inline]
+    pub fn stone_count(&self) -> usize {
+        self.occupied.len()
+    }
+
+    pub fn last_move(&self) -> Option<(usize, usize)> {
+        self.last_move
+    }
+
     pub fn is_empty(&self, x: usize, y: usize) -> bool {
         self.get_cell(x, y) == Some(Cell::Empty)
     }
 
     pub fn clear(&mut self) {
-        self.cells = [Cell::Empty; 400];
+        self.cells.fill(Cell::Empty);
         self.hash = 0;
+        self.occupied.clear();
+        self.last_move = None;
+        for side in &mut self.rows {
+            side.fill(0);
+        }
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.cells.capacity() * std::mem::size_of::<Cell>()
+            + (self.occupied.capacity() + self.occupied_slot.capacity())
+                * std::mem::size_of::<u16>()
+            + self.rows.iter().map(Vec::capacity).sum::<usize>() * std::mem::size_of::<u64>()
     }
 
     #[inline]
@@ -86,13 +249,27 @@ impl Board {
         self.hash
     }
 
-    #[inline]
-    pub fn update_hash(&mut self, key: u64) {
-        self.hash ^= key;
+    pub fn set_cell_hashed(
+        &mut self,
+        x: usize,
+        y: usize,
+        cell: Cell,
+        keys: &ZobristKeys,
+    ) -> Result<(), GameError> {
+        let idx = self.get_index(x, y).ok_or(GameError::OutOfBounds)?;
+        let old = self.cells[idx];
+        self.set_cell(x, y, cell)?;
+        if old.is_stone() {
+            self.hash ^= keys.stone_key(idx, old);
+        }
+        if cell.is_stone() {
+            self.hash ^= keys.stone_key(idx, cell);
+        }
+        Ok(())
     }
 
     pub fn iter_indices(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
-        (0..self.size).flat_map(move |y| (0..self.size).map(move |x| (x, y)))
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| (x, y)))
     }
 
     pub fn iter_empty(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
@@ -105,33 +282,156 @@ impl Board {
     }
 
     pub fn check_five_in_a_row(&self, player: Cell) -> bool {
-        let directions = [(1, 0), (0, 1), (1, 1), (1, -1)];
+        self.find_five(player).is_some()
+    }
 
-        for y in 0..self.size {
-            for x in 0..self.size {
-                if self.get_cell(x, y) != Some(player) {
-                    continue;
+    pub fn find_five(&self, player: Cell) -> Option<[(usize, usize); 5]> {
+        let side = bit_side(player)?;
+        let exact_five = cfg!(feature = "standard-rules");
+        for &(dx, dy) in &DIRECTIONS {
+            for y in 0..self.height as isize {
+                let starts = self.five_starts(side, (dx, dy), y, exact_five);
+                if starts != 0 {
+                    let x = starts.trailing_zeros() as isize;
+                    return Some(std::array::from_fn(|k| {
+                        let k = k as isize;
+                        ((x + k * dx) as usize, (y + k * dy) as usize)
+                    }));
                 }
+            }
+        }
+        None
+    }
 
-                for &(dx, dy) in &directions {
-                    let mut count = 1;
-                    for step in 1..5 {
-                        let nx = x as isize + dx * step;
-                        let ny = y as isize + dy * step;
-
-                        if nx < 0 || ny < 0 {
-                            break;
-                        }
-
-                        if self.get_cell(nx as usize, ny as usize) == Some(player) {
-                            count += 1;
-                        } else {
-                            break;
-                        }
-                    }
-                    if count >= 5 {
-                        return true;
+    pub fn symmetric_coords(&self, x: usize, y: usize, symmetry: usize) -> Option<(usize, usize)> {
+        if symmetry == 0 {
+            return Some((x, y));
+        }
+        if self.width != self.height || x >= self.width || y >= self.height {
+            return None;
+        }
+        let last = self.width - 1;
+        let (x, y) = if symmetry & 4 != 0 { (y, x) } else { (x, y) };
+        let x = if symmetry & 1 != 0 { last - x } else { x };
+        let y = if symmetry & 2 != 0 { last - y } else { y };
+        Some((x, y))
+    }
+
+    pub fn rotate90(&self, keys: &ZobristKeys) -> Board {
+        self.transformed(5, keys)
+    }
+
+    pub fn mirror_horizontal(&self, keys: &ZobristKeys) -> Board {
+        self.transformed(1, keys)
+    }
+
+    fn transformed(&self, symmetry: usize, keys: &ZobristKeys) -> Board {
+        let mut image = Board::empty(self.width, self.height);
+        for (x, y) in self.occupied() {
+            if let Some((sx, sy)) = self.symmetric_coords(x, y, symmetry) {
+                let cell = self.get_cell(x, y).unwrap_or(Cell::Empty);
+                let _ = image.set_cell_hashed(sx, sy, cell, keys);
+            }
+        }
+        image.last_move = self
+            .last_move
+            .and_then(|(x, y)| self.symmetric_coords(x, y, symmetry));
+        image
+    }
+
+    pub fn canonical_hash(&self, keys: &ZobristKeys) -> u64 {
+        let symmetries = if self.width == self.height {
+            SYMMETRIES
+        } else {
+            1
+        };
+        (0..symmetries)
+            .map(|s| {
+                self.occupied().fold(0, |hash, (x, y)| {
+                    let cell = self.get_cell(x, y).unwrap_or(Cell::Empty);
+                    match self.symmetric_coords(x, y, s) {
+                        Some((sx, sy)) => hash ^ keys.stone_key(sy * self.width + sx, cell),
+                        None => hash,
                     }
+                })
+            })
+            .min()
+            .unwrap_or(0)
+    }
+
+    pub fn is_symmetric_under(&self, symmetry: usize) -> bool {
+        self.occupied().all(|(x, y)| {
+            self.symmetric_coords(x, y, symmetry)
+                .and_then(|(sx, sy)| self.get_cell(sx, sy))
+                == self.get_cell(x, y)
+        })
+    }
+
+    /// Whether `player` has a winning line. With `exact_five` only a run of
+    /// exactly five counts, as in standard gomoku where six or more does not
+    /// win; otherwise any run of five or more does.
+    ///
+    /// Works a row at a time on the bitboards: ANDing five shifted rows
+    /// leaves bit x set exactly where a run of five starts at column x.
+    pub fn check_win(&self, player: Cell, exact_five: bool) -> bool {
+        let Some(side) = bit_side(player) else {
+            return false;
+        };
+        DIRECTIONS.iter().any(|&dir| {
+            (0..self.height as isize).any(|y| self.five_starts(side, dir, y, exact_five) != 0)
+        })
+    }
+
+    fn five_starts(
+        &self,
+        side: usize,
+        (dx, dy): (isize, isize),
+        y: isize,
+        exact_five: bool,
+    ) -> u64 {
+        let row = |y: isize, shift: isize| -> u64 {
+            if y < 0 || y >= self.height as isize {
+                return 0;
+            }
+            let bits = self.rows[side][y as usize];
+            if shift >= 0 {
+                bits >> shift
+            } else {
+                bits << -shift
+            }
+        };
+        let mut starts = (0..5).fold(u64::MAX, |acc, k| acc & row(y + k * dy, k * dx));
+        if exact_five {
+            starts &= !row(y - dy, -dx) & !row(y + 5 * dy, 5 * dx);
+        }
+        starts
+    }
+
+    #[cfg(test)]
+    pub(crate) fn cells_ptr(&self) -> *const Cell {
+        self.cells.as_ptr()
+    }
+
+    #[cfg(test)]
+    fn check_win_scalar(&self, player: Cell, exact_five: bool) -> bool {
+        for (x, y) in self.occupied() {
+            if self.get_cell(x, y) != Some(player) {
+                continue;
+            }
+
+            for &(dx, dy) in &DIRECTIONS {
+                let at = |k: isize| {
+                    let nx = x as isize + dx * k;
+                    let ny = y as isize + dy * k;
+                    nx >= 0 && ny >= 0 && self.get_cell(nx as usize, ny as usize) == Some(player)
+                };
+                // Only measure each run once, from its first stone.
+                if at(-1) {
+                    continue;
+                }
+                let run = (1..).take_while(|&k| at(k)).count() + 1;
+                if run == 5 || (run > 5 && !exact_five) {
+                    return true;
                 }
             }
         }
@@ -139,11 +439,42 @@ impl Board {
     }
 }
 
+#[inline]
+fn bit_side(cell: Cell) -> Option<usize> {
+    match cell {
+        Cell::MyStone => Some(0),
+        Cell::OpStone => Some(1),
+        _ => None,
+    }
+}
+
+impl Board {
+    pub fn labeled(&self) -> String {
+        let mut out = String::from("   ");
+        for x in 0..self.width {
+            out.push_str(&format!("{:>3}", x));
+        }
+        out.push('\n');
+        for y in 0..self.height {
+            out.push_str(&format!("{:>3}", y));
+            for x in 0..self.width {
+                out.push_str(&format!("{:>3}", self.get_cell(x, y).unwrap()));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
 impl fmt::Debug for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Board {{ size: {} }}", self.size)?;
-        for y in 0..self.size {
-            for x in 0..self.size {
+        writeln!(
+            f,
+            "Board {{ width: {}, height: {} }}",
+            self.width, self.height
+        )?;
+        for y in 0..self.height {
+            for x in 0..self.width {
                 write!(f, "{} ", self.get_cell(x, y).unwrap())?;
             }
             writeln!(f)?;
@@ -156,10 +487,147 @@ impl fmt::Debug for Board {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_from_grid_lays_out_rows_top_down() {
+        let board = Board::from_grid(&["X....", ".O...", "..F..", ".....", "....X"]).unwrap();
+        assert_eq!((board.width(), board.height()), (5, 5));
+        assert_eq!(board.get_cell(0, 0), Some(Cell::MyStone));
+        assert_eq!(board.get_cell(1, 1), Some(Cell::OpStone));
+        assert_eq!(board.get_cell(2, 2), Some(Cell::Forbidden));
+        assert_eq!(board.get_cell(4, 4), Some(Cell::MyStone));
+        assert_eq!(board.stone_count(), 3);
+    }
+
+    #[test]
+    fn test_from_grid_round_trips_debug_output() {
+        let mut board = Board::new_rect(7, 6).unwrap();
+        board.set_cell(3, 2, Cell::MyStone).unwrap();
+        board.set_cell(6, 5, Cell::OpStone).unwrap();
+        let printed = format!("{:?}", board);
+        let rows: Vec<&str> = printed.lines().skip(1).collect();
+        let parsed = Board::from_grid(&rows).unwrap();
+        assert_eq!(format!("{:?}", parsed), printed);
+    }
+
+    #[test]
+    fn test_from_grid_rejects_bad_shapes_and_characters() {
+        assert_eq!(
+            Board::from_grid(&["X....", ".....", "....", ".....", "....."]).unwrap_err(),
+            "row 2 has 4 cells, expected 5"
+        );
+        assert_eq!(
+            Board::from_grid(&["....", "....", "....", "...."]).unwrap_err(),
+            "unsupported board 4x4"
+        );
+        assert_eq!(Board::from_grid(&[]).unwrap_err(), "unsupported board 0x0");
+        assert_eq!(
+            Board::from_grid(&[".....", "..#..", ".....", ".....", "....."]).unwrap_err(),
+            "unknown cell '#' at 2,1"
+        );
+    }
+
     #[test]
     fn test_new_board() {
         assert!(Board::new(20).is_some());
-        assert!(Board::new(19).is_none());
+        assert!(Board::new(15).is_some());
+        assert!(Board::new(4).is_none());
+        assert!(Board::new_rect(65, 5).is_none());
+    }
+
+    #[test]
+    fn test_indexing_follows_each_shape() {
+        for (width, height) in [(5, 5), (15, 15), (19, 7), (64, 6)] {
+            let board = Board::new_rect(width, height).unwrap();
+            assert_eq!((board.width(), board.height()), (width, height));
+            assert_eq!(board.get_index(width - 1, 0), Some(width - 1));
+            assert_eq!(board.get_index(0, 1), Some(width));
+            assert_eq!(
+                board.get_index(width - 1, height - 1),
+                Some(width * height - 1)
+            );
+            assert_eq!(board.get_index(width, 0), None);
+            assert_eq!(board.get_index(0, height), None);
+            assert_eq!(board.iter_empty().count(), width * height);
+        }
+    }
+
+    #[test]
+    fn test_find_five_returns_the_line_in_every_direction() {
+        for &(dx, dy) in &DIRECTIONS {
+            let mut board = Board::default();
+            let (x0, y0) = (7isize, 9isize);
+            let line: Vec<(usize, usize)> = (0..5)
+                .map(|k| ((x0 + k * dx) as usize, (y0 + k * dy) as usize))
+                .collect();
+            board.set_cell(3, 3, Cell::MyStone).unwrap();
+            for &(x, y) in &line[..4] {
+                board.set_cell(x, y, Cell::MyStone).unwrap();
+            }
+            assert_eq!(board.find_five(Cell::MyStone), None);
+            assert!(!board.check_five_in_a_row(Cell::MyStone));
+
+            board.set_cell(line[4].0, line[4].1, Cell::MyStone).unwrap();
+            let found = board.find_five(Cell::MyStone).unwrap();
+            let mut expected = line.clone();
+            let mut got = found.to_vec();
+            expected.sort();
+            got.sort();
+            assert_eq!(got, expected, "direction {:?}", (dx, dy));
+            for pair in found.windows(2) {
+                assert_eq!(
+                    (pair[0].0.abs_diff(pair[1].0), pair[0].1.abs_diff(pair[1].1)),
+                    (dx.unsigned_abs(), dy.unsigned_abs())
+                );
+            }
+            assert!(board.check_five_in_a_row(Cell::MyStone));
+            assert_eq!(board.find_five(Cell::OpStone), None);
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "standard-rules"))]
+    fn test_find_five_in_overline_stays_on_the_line() {
+        let mut board = Board::default();
+        for x in 2..8 {
+            board.set_cell(x, 19, Cell::OpStone).unwrap();
+        }
+        let found = board.find_five(Cell::OpStone).unwrap();
+        assert!(found.iter().all(|&(x, y)| y == 19 && (2..8).contains(&x)));
+        assert_eq!(board.find_five(Cell::Empty), None);
+    }
+
+    #[test]
+    fn test_win_detection_on_other_sizes() {
+        for (width, height) in [(5, 5), (15, 15), (19, 7), (64, 6)] {
+            let mut board = Board::new_rect(width, height).unwrap();
+            for k in 0..4 {
+                board
+                    .set_cell(width - 1 - k, height - 1 - k, Cell::OpStone)
+                    .unwrap();
+            }
+            assert!(!board.check_win(Cell::OpStone, false));
+            board
+                .set_cell(width - 5, height - 5, Cell::OpStone)
+                .unwrap();
+            assert!(board.check_win(Cell::OpStone, true));
+            assert_eq!(
+                board.check_win(Cell::OpStone, false),
+                board.check_win_scalar(Cell::OpStone, false)
+            );
+
+            board.clear();
+            for x in width - 5..width {
+                board.set_cell(x, 0, Cell::MyStone).unwrap();
+            }
+            assert!(board.check_win(Cell::MyStone, true));
+            assert!(!board.check_win(Cell::OpStone, false));
+        }
+    }
+
+    #[test]
+    fn test_new_board_rejects_oversized() {
+        assert!(Board::new(1000).is_none());
+        assert!(Board::new(usize::MAX).is_none());
     }
 
     #[test]
@@ -181,7 +649,10 @@ mod tests {
         assert!(board.set_cell(10, 10, Cell::MyStone).is_ok());
         assert_eq!(board.get_cell(10, 10), Some(Cell::MyStone));
 
-        assert!(board.set_cell(20, 20, Cell::MyStone).is_err());
+        assert_eq!(
+            board.set_cell(20, 20, Cell::MyStone),
+            Err(GameError::OutOfBounds)
+        );
     }
 
     #[test]
@@ -194,6 +665,56 @@ mod tests {
         assert!(!empty_cells.contains(&(0, 0)));
     }
 
+    #[test]
+    fn test_occupied_tracks_set_and_clear() {
+        let mut board = Board::default();
+        board.set_cell(3, 4, Cell::MyStone).unwrap();
+        board.set_cell(10, 10, Cell::OpStone).unwrap();
+        board.set_cell(19, 19, Cell::MyStone).unwrap();
+        board.set_cell(5, 5, Cell::Forbidden).unwrap();
+
+        let mut stones: Vec<_> = board.occupied().collect();
+        stones.sort();
+        assert_eq!(stones, vec![(3, 4), (10, 10), (19, 19)]);
+
+        board.set_cell(10, 10, Cell::MyStone).unwrap();
+        assert_eq!(board.occupied().count(), 3);
+
+        board.set_cell(3, 4, Cell::Empty).unwrap();
+        let mut stones: Vec<_> = board.occupied().collect();
+        stones.sort();
+        assert_eq!(stones, vec![(10, 10), (19, 19)]);
+
+        board.clear();
+        assert_eq!(board.occupied().count(), 0);
+    }
+
+    #[test]
+    fn test_occupied_matches_scan_after_make_unmake() {
+        let mut board = Board::default();
+        let moves = [(0, 0), (7, 3), (12, 18), (19, 0), (9, 9), (10, 9)];
+        for (i, &(x, y)) in moves.iter().enumerate() {
+            let cell = if i % 2 == 0 {
+                Cell::MyStone
+            } else {
+                Cell::OpStone
+            };
+            board.set_cell(x, y, cell).unwrap();
+        }
+        for &(x, y) in moves.iter().rev().step_by(2) {
+            board.set_cell(x, y, Cell::Empty).unwrap();
+        }
+
+        let mut indexed: Vec<_> = board.occupied().collect();
+        indexed.sort();
+        let mut scanned: Vec<_> = board
+            .iter_indices()
+            .filter(|&(x, y)| board.get_cell(x, y).is_some_and(Cell::is_stone))
+            .collect();
+        scanned.sort();
+        assert_eq!(indexed, scanned);
+    }
+
     #[test]
     fn test_check_five_in_a_row_horizontal() {
         let mut board = Board::default();
@@ -477,6 +998,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "standard-rules"))]
     fn test_overline_six_in_a_row() {
         let mut board = Board::default();
         for x in 0..6 {
@@ -539,4 +1061,250 @@ mod tests {
         board.set_cell(10, 10, Cell::MyStone).unwrap();
         assert!(!board.check_five_in_a_row(Cell::MyStone));
     }
+
+    #[test]
+    fn test_set_cell_hashed_round_trip() {
+        let keys = ZobristKeys::new();
+        let mut board = Board::default();
+        board.set_cell_hashed(3, 4, Cell::MyStone, &keys).unwrap();
+        assert_eq!(board.hash(), keys.stone_key(4 * 20 + 3, Cell::MyStone));
+
+        board.set_cell_hashed(3, 4, Cell::OpStone, &keys).unwrap();
+        assert_eq!(board.hash(), keys.stone_key(4 * 20 + 3, Cell::OpStone));
+
+        board.set_cell_hashed(3, 4, Cell::Empty, &keys).unwrap();
+        assert_eq!(board.hash(), 0);
+    }
+
+    #[test]
+    fn test_set_cell_hashed_is_order_independent() {
+        let keys = ZobristKeys::new();
+        let mut a = Board::default();
+        a.set_cell_hashed(1, 1, Cell::MyStone, &keys).unwrap();
+        a.set_cell_hashed(2, 2, Cell::OpStone, &keys).unwrap();
+        let mut b = Board::default();
+        b.set_cell_hashed(2, 2, Cell::OpStone, &keys).unwrap();
+        b.set_cell_hashed(1, 1, Cell::MyStone, &keys).unwrap();
+        assert_eq!(a.hash(), b.hash());
+        assert!(a.set_cell_hashed(20, 0, Cell::MyStone, &keys).is_err());
+    }
+
+    #[test]
+    fn test_stone_count_and_last_move_on_placement() {
+        let mut board = Board::default();
+        assert_eq!(board.stone_count(), 0);
+        assert_eq!(board.last_move(), None);
+
+        board.set_cell(3, 4, Cell::MyStone).unwrap();
+        board.set_cell(5, 6, Cell::OpStone).unwrap();
+        assert_eq!(board.stone_count(), 2);
+        assert_eq!(board.last_move(), Some((5, 6)));
+
+        board.set_cell(7, 7, Cell::Forbidden).unwrap();
+        assert_eq!(board.stone_count(), 2);
+        assert_eq!(board.last_move(), Some((5, 6)));
+    }
+
+    #[test]
+    fn test_stone_count_on_overwrite_and_removal() {
+        let mut board = Board::default();
+        board.set_cell(3, 4, Cell::MyStone).unwrap();
+        board.set_cell(5, 6, Cell::MyStone).unwrap();
+
+        board.set_cell(3, 4, Cell::OpStone).unwrap();
+        assert_eq!(board.stone_count(), 2);
+        assert_eq!(board.last_move(), Some((5, 6)));
+
+        board.set_cell(3, 4, Cell::Empty).unwrap();
+        assert_eq!(board.stone_count(), 1);
+        assert_eq!(board.last_move(), Some((5, 6)));
+
+        board.set_cell(5, 6, Cell::Empty).unwrap();
+        assert_eq!(board.stone_count(), 0);
+        assert_eq!(board.last_move(), None);
+    }
+
+    #[test]
+    fn test_clear_resets_stone_count_and_last_move() {
+        let mut board = Board::default();
+        board.set_cell(1, 1, Cell::MyStone).unwrap();
+        board.clear();
+        assert_eq!(board.stone_count(), 0);
+        assert_eq!(board.last_move(), None);
+    }
+
+    #[test]
+    fn test_symmetric_coords_cover_the_eight_images() {
+        let board = Board::default();
+        let images: std::collections::HashSet<_> = (0..SYMMETRIES)
+            .map(|s| board.symmetric_coords(3, 5, s).unwrap())
+            .collect();
+        assert_eq!(images.len(), SYMMETRIES);
+        assert!(images.contains(&(16, 5)));
+        assert!(images.contains(&(5, 3)));
+        assert!(images.contains(&(14, 16)));
+    }
+
+    fn asymmetric_board(keys: &ZobristKeys) -> Board {
+        let mut board = Board::default();
+        board.set_cell_hashed(0, 0, Cell::MyStone, keys).unwrap();
+        board.set_cell_hashed(3, 1, Cell::OpStone, keys).unwrap();
+        board.set_cell_hashed(19, 12, Cell::MyStone, keys).unwrap();
+        board
+    }
+
+    #[test]
+    fn test_rotating_four_times_returns_original() {
+        let keys = ZobristKeys::new();
+        let board = asymmetric_board(&keys);
+        let once = board.rotate90(&keys);
+        assert_eq!(once.get_cell(19, 0), Some(Cell::MyStone));
+        assert_eq!(once.get_cell(18, 3), Some(Cell::OpStone));
+        assert_ne!(once.hash(), board.hash());
+
+        let back = once.rotate90(&keys).rotate90(&keys).rotate90(&keys);
+        assert_eq!(back.labeled(), board.labeled());
+        assert_eq!(back.hash(), board.hash());
+    }
+
+    #[test]
+    fn test_mirroring_twice_returns_original() {
+        let keys = ZobristKeys::new();
+        let board = asymmetric_board(&keys);
+        let mirrored = board.mirror_horizontal(&keys);
+        assert_eq!(mirrored.get_cell(19, 0), Some(Cell::MyStone));
+        assert_eq!(mirrored.get_cell(0, 12), Some(Cell::MyStone));
+        let back = mirrored.mirror_horizontal(&keys);
+        assert_eq!(back.labeled(), board.labeled());
+        assert_eq!(back.hash(), board.hash());
+    }
+
+    #[test]
+    fn test_canonical_hash_is_shared_by_symmetric_positions() {
+        let keys = ZobristKeys::new();
+        let board = asymmetric_board(&keys);
+        let canonical = board.canonical_hash(&keys);
+        assert_eq!(board.rotate90(&keys).canonical_hash(&keys), canonical);
+        assert_eq!(
+            board.mirror_horizontal(&keys).canonical_hash(&keys),
+            canonical
+        );
+        assert!(canonical <= board.hash());
+
+        let mut other = board;
+        other.set_cell_hashed(5, 5, Cell::OpStone, &keys).unwrap();
+        assert_ne!(other.canonical_hash(&keys), canonical);
+    }
+
+    #[test]
+    fn test_is_symmetric_under_checks_every_stone() {
+        let mut board = Board::default();
+        board.set_cell(10, 10, Cell::MyStone).unwrap();
+        assert!(board.is_symmetric_under(4));
+        assert!(!board.is_symmetric_under(1));
+        board.set_cell(9, 10, Cell::MyStone).unwrap();
+        assert!(board.is_symmetric_under(1));
+        assert!(!board.is_symmetric_under(4));
+    }
+
+    #[test]
+    fn test_bitboard_win_matches_scalar_scan() {
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut wins = 0;
+        for density in [10, 30, 50, 70] {
+            for _ in 0..200 {
+                let mut board = Board::default();
+                for (x, y) in Board::default().iter_indices() {
+                    let roll = next() % 100;
+                    if roll < density / 2 {
+                        board.set_cell(x, y, Cell::MyStone).unwrap();
+                    } else if roll < density {
+                        board.set_cell(x, y, Cell::OpStone).unwrap();
+                    }
+                }
+                for player in [Cell::MyStone, Cell::OpStone] {
+                    for exact in [false, true] {
+                        let expected = board.check_win_scalar(player, exact);
+                        assert_eq!(board.check_win(player, exact), expected);
+                        wins += usize::from(expected);
+                    }
+                }
+            }
+        }
+        assert!(wins > 0);
+    }
+
+    #[test]
+    fn test_bitboards_follow_removed_and_cleared_stones() {
+        let mut board = Board::default();
+        for x in 15..20 {
+            board.set_cell(x, 19, Cell::OpStone).unwrap();
+        }
+        assert!(board.check_win(Cell::OpStone, true));
+        board.set_cell(19, 19, Cell::MyStone).unwrap();
+        assert!(!board.check_win(Cell::OpStone, false));
+        assert!(!board.check_win(Cell::MyStone, false));
+        board.set_cell(19, 19, Cell::OpStone).unwrap();
+        board.clear();
+        assert!(!board.check_win(Cell::OpStone, false));
+    }
+
+    #[test]
+    fn test_overline_wins_only_without_exact_five() {
+        let mut board = Board::default();
+        for x in 3..9 {
+            board.set_cell(x, 7, Cell::MyStone).unwrap();
+        }
+        assert!(board.check_win(Cell::MyStone, false));
+        assert!(!board.check_win(Cell::MyStone, true));
+        assert_eq!(
+            board.check_five_in_a_row(Cell::MyStone),
+            !cfg!(feature = "standard-rules")
+        );
+    }
+
+    #[test]
+    fn test_exact_five_wins_under_both_rules() {
+        let mut board = Board::default();
+        for i in 0..5 {
+            board.set_cell(15 - i, 4 + i, Cell::OpStone).unwrap();
+        }
+        assert!(board.check_win(Cell::OpStone, true));
+        assert!(board.check_win(Cell::OpStone, false));
+        assert!(!board.check_win(Cell::MyStone, false));
+    }
+}
+
+#[cfg(all(test, feature = "standard-rules"))]
+mod standard_rules_tests {
+    use super::*;
+
+    #[test]
+    fn test_overline_is_not_five_in_a_row() {
+        let mut board = Board::default();
+        for x in 2..8 {
+            board.set_cell(x, 19, Cell::OpStone).unwrap();
+        }
+        assert_eq!(board.find_five(Cell::OpStone), None);
+        assert!(!board.check_five_in_a_row(Cell::OpStone));
+    }
+
+    #[test]
+    fn test_exact_five_next_to_an_overline_still_counts() {
+        let mut board = Board::default();
+        for x in 0..6 {
+            board.set_cell(x, 0, Cell::MyStone).unwrap();
+        }
+        for y in 2..7 {
+            board.set_cell(9, y, Cell::MyStone).unwrap();
+        }
+        let found = board.find_five(Cell::MyStone).unwrap();
+        assert!(found.iter().all(|&(x, _)| x == 9));
+    }
 }
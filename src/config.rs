@@ -0,0 +1,454 @@
+use crate::board::MAX_BOARD_CELLS;
+use crate::rules::RuleSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const DEFAULT_BOARD_SIZE: usize = 20;
+pub const DEFAULT_MAX_BOARD_CELLS: usize = MAX_BOARD_CELLS;
+pub const DEFAULT_MAX_DEPTH: usize = 20;
+/// Range a depth given through `GOMOKU_MAX_DEPTH` or `INFO max_depth` must
+/// fall in to be used, up to the default, which is as deep as search goes.
+pub const CONFIGURABLE_DEPTHS: std::ops::RangeInclusive<usize> = 1..=DEFAULT_MAX_DEPTH;
+pub const DEFAULT_TIME_MARGIN: Duration = Duration::from_millis(100);
+/// How far from an existing stone a cell may be to count as a candidate.
+pub const DEFAULT_ADJACENCY_RADIUS: usize = 2;
+/// Radii `GOMOKU_ADJACENCY_RADIUS` or `INFO adjacency_radius` may set.
+pub const CONFIGURABLE_RADII: std::ops::RangeInclusive<usize> = 1..=4;
+/// Rings around the four middle cells searched while the board is nearly
+/// empty, wherever the stones are.
+pub const DEFAULT_CENTER_RADIUS: usize = 1;
+/// Radii `GOMOKU_CENTER_RADIUS` or `INFO center_radius` may set.
+pub const CONFIGURABLE_CENTER_RADII: std::ops::RangeInclusive<usize> = 0..=4;
+/// Thread counts `GOMOKU_THREADS` may set.
+pub const CONFIGURABLE_THREADS: std::ops::RangeInclusive<usize> = 1..=64;
+/// Tournament memory cap assumed until `INFO max_memory` says otherwise.
+pub const DEFAULT_MAX_MEMORY: usize = 70 * 1024 * 1024;
+
+/// Shape of a line through a stone, weakest first: how many stones it holds
+/// toward a five and whether both ends are still open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Pattern {
+    #[default]
+    None,
+    ClosedTwo,
+    OpenTwo,
+    ClosedThree,
+    OpenThree,
+    ClosedFour,
+    OpenFour,
+}
+
+/// Points the evaluation gives a stone for each line pattern it is part
+/// of, summed for each side. Tuning these needs no rebuild: `main` reads
+/// `GOMOKU_W_<NAME>` overrides for every name in `NAMES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalWeights {
+    pub open_four: i32,
+    pub closed_four: i32,
+    pub open_three: i32,
+    pub closed_three: i32,
+    pub open_two: i32,
+    pub closed_two: i32,
+}
+
+impl EvalWeights {
+    pub const DEFAULT: EvalWeights = EvalWeights {
+        open_four: 90000,
+        closed_four: 10000,
+        open_three: 5000,
+        closed_three: 500,
+        open_two: 100,
+        closed_two: 10,
+    };
+
+    /// Names `set` accepts, one per field.
+    pub const NAMES: [&'static str; 6] = [
+        "open_four",
+        "closed_four",
+        "open_three",
+        "closed_three",
+        "open_two",
+        "closed_two",
+    ];
+
+    pub fn score(&self, pattern: Pattern) -> i32 {
+        match pattern {
+            Pattern::None => 0,
+            Pattern::ClosedTwo => self.closed_two,
+            Pattern::OpenTwo => self.open_two,
+            Pattern::ClosedThree => self.closed_three,
+            Pattern::OpenThree => self.open_three,
+            Pattern::ClosedFour => self.closed_four,
+            Pattern::OpenFour => self.open_four,
+        }
+    }
+
+    /// Sets the weight called `name`, returning false for a name not in
+    /// `NAMES`.
+    pub fn set(&mut self, name: &str, value: i32) -> bool {
+        let field = match name {
+            "open_four" => &mut self.open_four,
+            "closed_four" => &mut self.closed_four,
+            "open_three" => &mut self.open_three,
+            "closed_three" => &mut self.closed_three,
+            "open_two" => &mut self.open_two,
+            "closed_two" => &mut self.closed_two,
+            _ => return false,
+        };
+        *field = value;
+        true
+    }
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Every tunable the engine reads, gathered in one place. Built once in
+/// `main` from the environment and then updated by `INFO` commands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameConfig {
+    /// Board size front ends start games with and the engine allocates its
+    /// board at; board commands are still refused until `START`.
+    pub board_size: usize,
+    /// Boards with more cells than this are refused with an error.
+    pub max_board_cells: usize,
+    pub rule: RuleSet,
+    /// Deepest iterative-deepening iteration the search will start.
+    pub max_depth: usize,
+    /// Once stones are down, only empty cells within this Chebyshev distance
+    /// of one are searched.
+    pub adjacency_radius: usize,
+    /// While the board holds at most two stones, empty cells this many rings
+    /// around the four middle cells are searched as well.
+    pub center_radius: usize,
+    /// Reserved for I/O so a reply never lands after the deadline.
+    pub time_margin: Duration,
+    pub timeout_turn: Option<Duration>,
+    pub timeout_match: Option<Duration>,
+    /// Memory budget in bytes; the transposition table is sized from it.
+    pub max_memory: usize,
+    /// Answer the first few moves from the opening book instead of searching.
+    /// Off by default so a bare `GameState` always searches; `main` turns it
+    /// on for play.
+    pub opening_book: bool,
+    pub analysis: bool,
+    /// Search the expected reply on a background thread while the opponent
+    /// thinks.
+    pub ponder: bool,
+    /// Threads searching each move; more than one needs the `parallel`
+    /// feature.
+    pub threads: usize,
+    pub eval_weights: EvalWeights,
+    /// Directory each finished game is saved to as a `.psq` record.
+    pub psq_dir: Option<PathBuf>,
+    pub resign_threshold: Option<i32>,
+    /// How move requests are answered.
+    pub engine: EngineKind,
+    /// Seed for anything picked at random; self-play draws its opening
+    /// stones from it, 0 meaning an empty board.
+    pub seed: u64,
+    /// Log search summaries and decisions.
+    pub debug: bool,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            board_size: DEFAULT_BOARD_SIZE,
+            max_board_cells: DEFAULT_MAX_BOARD_CELLS,
+            rule: RuleSet::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            adjacency_radius: DEFAULT_ADJACENCY_RADIUS,
+            center_radius: DEFAULT_CENTER_RADIUS,
+            time_margin: DEFAULT_TIME_MARGIN,
+            timeout_turn: None,
+            timeout_match: None,
+            max_memory: DEFAULT_MAX_MEMORY,
+            opening_book: false,
+            analysis: false,
+            ponder: false,
+            threads: 1,
+            eval_weights: EvalWeights::DEFAULT,
+            psq_dir: None,
+            resign_threshold: None,
+            engine: EngineKind::default(),
+            seed: 0,
+            debug: false,
+        }
+    }
+}
+
+/// The move chooser behind `GameState`, picked with `GOMOKU_ENGINE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EngineKind {
+    /// Threat solvers and the full alpha-beta search.
+    #[default]
+    Search,
+    /// A win, a forced block, or else the best-ranked candidate, with no
+    /// search at all; a quick sparring partner.
+    Greedy,
+}
+
+impl EngineKind {
+    /// `search` or `greedy`, in any case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "search" => Some(EngineKind::Search),
+            "greedy" => Some(EngineKind::Greedy),
+            _ => None,
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn builder() -> GameConfigBuilder {
+        GameConfigBuilder::default()
+    }
+}
+
+/// A search depth written by a user, if it is a number in
+/// `CONFIGURABLE_DEPTHS`.
+pub fn parse_max_depth(value: &str) -> Option<usize> {
+    parse_within(value, &CONFIGURABLE_DEPTHS)
+}
+
+/// An adjacency radius written by a user, if it is in `CONFIGURABLE_RADII`.
+pub fn parse_adjacency_radius(value: &str) -> Option<usize> {
+    parse_within(value, &CONFIGURABLE_RADII)
+}
+
+/// A centre radius written by a user, if it is in
+/// `CONFIGURABLE_CENTER_RADII`.
+pub fn parse_center_radius(value: &str) -> Option<usize> {
+    parse_within(value, &CONFIGURABLE_CENTER_RADII)
+}
+
+/// A search thread count written by a user, if it is in
+/// `CONFIGURABLE_THREADS`.
+pub fn parse_threads(value: &str) -> Option<usize> {
+    parse_within(value, &CONFIGURABLE_THREADS)
+}
+
+fn parse_within(value: &str, range: &std::ops::RangeInclusive<usize>) -> Option<usize> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|number| range.contains(number))
+}
+
+#[derive(Debug, Default)]
+pub struct GameConfigBuilder {
+    config: GameConfig,
+}
+
+impl GameConfigBuilder {
+    pub fn board_size(mut self, size: usize) -> Self {
+        self.config.board_size = size;
+        self
+    }
+
+    pub fn max_board_cells(mut self, cells: usize) -> Self {
+        self.config.max_board_cells = cells;
+        self
+    }
+
+    pub fn rule(mut self, rule: RuleSet) -> Self {
+        self.config.rule = rule;
+        self
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.config.max_depth = depth;
+        self
+    }
+
+    pub fn adjacency_radius(mut self, radius: usize) -> Self {
+        self.config.adjacency_radius = radius;
+        self
+    }
+
+    pub fn center_radius(mut self, rings: usize) -> Self {
+        self.config.center_radius = rings;
+        self
+    }
+
+    pub fn time_margin(mut self, margin: Duration) -> Self {
+        self.config.time_margin = margin;
+        self
+    }
+
+    pub fn timeout_turn(mut self, timeout: Duration) -> Self {
+        self.config.timeout_turn = Some(timeout);
+        self
+    }
+
+    pub fn timeout_match(mut self, timeout: Duration) -> Self {
+        self.config.timeout_match = Some(timeout);
+        self
+    }
+
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.config.max_memory = bytes;
+        self
+    }
+
+    pub fn opening_book(mut self, enabled: bool) -> Self {
+        self.config.opening_book = enabled;
+        self
+    }
+
+    pub fn analysis(mut self, enabled: bool) -> Self {
+        self.config.analysis = enabled;
+        self
+    }
+
+    pub fn ponder(mut self, enabled: bool) -> Self {
+        self.config.ponder = enabled;
+        self
+    }
+
+    pub fn threads(mut self, count: usize) -> Self {
+        self.config.threads = count;
+        self
+    }
+
+    pub fn eval_weights(mut self, weights: EvalWeights) -> Self {
+        self.config.eval_weights = weights;
+        self
+    }
+
+    pub fn psq_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.config.psq_dir = dir;
+        self
+    }
+
+    pub fn resign_threshold(mut self, threshold: Option<i32>) -> Self {
+        self.config.resign_threshold = threshold;
+        self
+    }
+
+    pub fn engine(mut self, engine: EngineKind) -> Self {
+        self.config.engine = engine;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = seed;
+        self
+    }
+
+    pub fn debug(mut self, enabled: bool) -> Self {
+        self.config.debug = enabled;
+        self
+    }
+
+    pub fn build(self) -> GameConfig {
+        self.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_defaults() {
+        assert_eq!(GameConfig::builder().build(), GameConfig::default());
+    }
+
+    #[test]
+    fn test_parse_max_depth_accepts_only_sane_depths() {
+        assert_eq!(parse_max_depth("1"), Some(1));
+        assert_eq!(parse_max_depth(" 16 "), Some(16));
+        assert_eq!(
+            parse_max_depth(&DEFAULT_MAX_DEPTH.to_string()),
+            Some(DEFAULT_MAX_DEPTH)
+        );
+        assert_eq!(parse_max_depth("0"), None);
+        assert_eq!(parse_max_depth("21"), None);
+        assert_eq!(parse_max_depth("-3"), None);
+        assert_eq!(parse_max_depth("deep"), None);
+    }
+
+    #[test]
+    fn test_parse_candidate_settings_rejects_out_of_range_values() {
+        assert_eq!(parse_adjacency_radius("3"), Some(3));
+        assert_eq!(parse_adjacency_radius("0"), None);
+        assert_eq!(parse_adjacency_radius("5"), None);
+        assert_eq!(parse_center_radius("0"), Some(0));
+        assert_eq!(parse_center_radius("4"), Some(4));
+        assert_eq!(parse_center_radius("5"), None);
+        assert_eq!(parse_center_radius("wide"), None);
+        assert_eq!(parse_threads("8"), Some(8));
+        assert_eq!(parse_threads("0"), None);
+        assert_eq!(parse_threads("65"), None);
+    }
+
+    #[test]
+    fn test_eval_weights_set_by_name() {
+        let mut weights = EvalWeights::default();
+        for (value, name) in EvalWeights::NAMES.iter().enumerate() {
+            assert!(weights.set(name, value as i32));
+        }
+        assert!(!weights.set("open_five", 1));
+        let patterns = [
+            Pattern::OpenFour,
+            Pattern::ClosedFour,
+            Pattern::OpenThree,
+            Pattern::ClosedThree,
+            Pattern::OpenTwo,
+            Pattern::ClosedTwo,
+        ];
+        for (value, pattern) in patterns.into_iter().enumerate() {
+            assert_eq!(weights.score(pattern), value as i32);
+        }
+        assert_eq!(weights.score(Pattern::None), 0);
+    }
+
+    #[test]
+    fn test_builder_applies_overrides() {
+        let config = GameConfig::builder()
+            .board_size(15)
+            .max_board_cells(225)
+            .rule(RuleSet::Renju)
+            .max_depth(4)
+            .time_margin(Duration::from_millis(250))
+            .timeout_turn(Duration::from_millis(900))
+            .max_memory(1 << 20)
+            .analysis(true)
+            .ponder(true)
+            .threads(4)
+            .resign_threshold(Some(40000))
+            .engine(EngineKind::Greedy)
+            .seed(42)
+            .debug(true)
+            .build();
+
+        assert_eq!(config.board_size, 15);
+        assert_eq!(config.max_board_cells, 225);
+        assert_eq!(config.rule, RuleSet::Renju);
+        assert_eq!(config.max_depth, 4);
+        assert_eq!(config.time_margin, Duration::from_millis(250));
+        assert_eq!(config.timeout_turn, Some(Duration::from_millis(900)));
+        assert_eq!(config.timeout_match, None);
+        assert_eq!(config.max_memory, 1 << 20);
+        assert!(config.analysis);
+        assert!(config.ponder);
+        assert_eq!(config.threads, 4);
+        assert_eq!(config.resign_threshold, Some(40000));
+        assert_eq!(config.engine, EngineKind::Greedy);
+        assert_eq!(config.seed, 42);
+        assert!(config.debug);
+    }
+
+    #[test]
+    fn test_engine_kind_from_name() {
+        assert_eq!(EngineKind::from_name(" Greedy "), Some(EngineKind::Greedy));
+        assert_eq!(EngineKind::from_name("search"), Some(EngineKind::Search));
+        assert_eq!(EngineKind::from_name("mcts"), None);
+    }
+}
@@ -0,0 +1,103 @@
+use std::env;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Whether the whole process logs, off until `set_enabled` turns it on.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns logging on or off, normally from `GameConfig::debug`.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_debug_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// The `GOMOKU_LOG_FILE` sink, opened on first use. `None` when the variable
+/// is unset or the file cannot be opened, in which case logs go to stderr.
+fn log_file() -> Option<&'static Mutex<File>> {
+    static FILE: OnceLock<Option<Mutex<File>>> = OnceLock::new();
+    FILE.get_or_init(|| {
+        env::var("GOMOKU_LOG_FILE")
+            .ok()
+            .and_then(|path| open_log(&path))
+            .map(Mutex::new)
+    })
+    .as_ref()
+}
+
+fn open_log(path: &str) -> Option<File> {
+    OpenOptions::new().create(true).append(true).open(path).ok()
+}
+
+/// Writes one diagnostic line to the log file, or to stderr without one;
+/// stdout belongs to the protocol. Write failures are ignored.
+pub fn write_line(args: fmt::Arguments<'_>) {
+    if let Some(file) = log_file() {
+        if let Ok(mut file) = file.lock() {
+            let _ = writeln!(file, "{}", timestamped(SystemTime::now(), args));
+            return;
+        }
+    }
+    let _ = writeln!(io::stderr().lock(), "DEBUG {}", args);
+}
+
+/// Pushes out anything still buffered for the log, if debugging is on.
+pub fn flush() {
+    if !is_debug_enabled() {
+        return;
+    }
+    if let Some(file) = log_file() {
+        if let Ok(mut file) = file.lock() {
+            let _ = file.flush();
+        }
+    }
+    let _ = io::stderr().flush();
+}
+
+/// `args` prefixed with seconds and milliseconds since the Unix epoch.
+fn timestamped(now: SystemTime, args: fmt::Arguments<'_>) -> String {
+    let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!(
+        "[{}.{:03}] {}",
+        since_epoch.as_secs(),
+        since_epoch.subsec_millis(),
+        args
+    )
+}
+
+/// `format!`-style logging that costs nothing unless debugging is enabled.
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::debug::is_debug_enabled() {
+            $crate::debug::write_line(format_args!($($arg)*));
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_timestamped_prefixes_epoch_millis() {
+        let now = UNIX_EPOCH + Duration::from_millis(1_700_000_000_042);
+        assert_eq!(
+            timestamped(now, format_args!("depth={}", 3)),
+            "[1700000000.042] depth=3"
+        );
+    }
+
+    #[test]
+    fn test_unopenable_log_path_is_skipped() {
+        let dir = env::temp_dir();
+        assert!(open_log(dir.to_str().unwrap()).is_none());
+    }
+}
@@ -0,0 +1,61 @@
+use crate::board::{Board, Cell};
+use crate::config::GameConfig;
+use crate::game::GameState;
+
+/// Stateless front end to the search: each call analyses the given position
+/// from scratch under the engine's configuration.
+#[derive(Debug, Clone, Default)]
+pub struct Engine {
+    config: GameConfig,
+}
+
+impl Engine {
+    pub fn new(config: GameConfig) -> Self {
+        Self { config }
+    }
+
+    /// The move `player` should play on `board`, or `None` when `player` is
+    /// not a stone colour, the board size is unsupported or no cell is free.
+    pub fn best_move(&self, board: &Board, player: Cell) -> Option<(usize, usize)> {
+        let mut game = GameState::with_config(self.config.clone());
+        game.load_position(board, player).ok()?;
+        game.best_move()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn engine() -> Engine {
+        Engine::new(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_millis(500))
+                .max_depth(2)
+                .opening_book(true)
+                .build(),
+        )
+    }
+
+    #[test]
+    fn test_completes_five_for_either_colour() {
+        let mut board = Board::default();
+        for x in 3..7 {
+            board.set_cell(x, 4, Cell::OpStone).unwrap();
+        }
+        board.set_cell(2, 4, Cell::MyStone).unwrap();
+        board.set_cell(10, 10, Cell::MyStone).unwrap();
+        board.set_cell(11, 10, Cell::MyStone).unwrap();
+
+        assert_eq!(engine().best_move(&board, Cell::OpStone), Some((7, 4)));
+        assert_eq!(engine().best_move(&board, Cell::MyStone), Some((7, 4)));
+    }
+
+    #[test]
+    fn test_empty_board_and_bad_side() {
+        let board = Board::default();
+        assert_eq!(engine().best_move(&board, Cell::MyStone), Some((10, 10)));
+        assert_eq!(engine().best_move(&board, Cell::Empty), None);
+    }
+}
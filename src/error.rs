@@ -0,0 +1,69 @@
+//! Errors from the board and game layer. Their text is the protocol's
+//! wording without the `ERROR` prefix, which `protocol::error_response`
+//! adds when a reply goes out.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameError {
+    /// The cell lies outside the board.
+    OutOfBounds,
+    /// The cell already holds a stone.
+    Occupied,
+    /// The cell is marked forbidden, or the move breaks a Renju rule.
+    Forbidden,
+    /// A board command arrived before `START`.
+    NotInitialized,
+    /// No board of this shape can be set up.
+    UnsupportedSize { width: usize, height: usize },
+    /// A `BOARD` line named a field that is not a cell value.
+    InvalidField(usize),
+    /// The side to move was given as something other than a stone.
+    NotAStone,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::OutOfBounds => write!(f, "coordinates out of range"),
+            GameError::Occupied => write!(f, "cell already occupied"),
+            GameError::Forbidden => write!(f, "move forbidden"),
+            GameError::NotInitialized => write!(f, "game not initialized"),
+            GameError::UnsupportedSize { width, height } => {
+                write!(f, "unsupported board {}x{}", width, height)
+            }
+            GameError::InvalidField(_) => write!(f, "invalid board field"),
+            GameError::NotAStone => write!(f, "side to move must be a stone"),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::error_response;
+
+    #[test]
+    fn test_responses_keep_the_protocol_wording() {
+        let cases = [
+            (GameError::OutOfBounds, "ERROR coordinates out of range"),
+            (GameError::Occupied, "ERROR cell already occupied"),
+            (GameError::Forbidden, "ERROR move forbidden"),
+            (GameError::NotInitialized, "ERROR game not initialized"),
+            (
+                GameError::UnsupportedSize {
+                    width: 30,
+                    height: 20,
+                },
+                "ERROR unsupported board 30x20",
+            ),
+            (GameError::InvalidField(7), "ERROR invalid board field"),
+            (GameError::NotAStone, "ERROR side to move must be a stone"),
+        ];
+        for (error, response) in cases {
+            assert_eq!(error_response(error), response);
+        }
+    }
+}
@@ -1,35 +1,86 @@
-use crate::board::{Board, Cell};
+use crate::board::{Board, Cell, DIRECTIONS, MAX_BOARD_CELLS, SYMMETRIES};
+use crate::config::{self, EngineKind, EvalWeights, GameConfig, Pattern, DEFAULT_MAX_MEMORY};
+use crate::debug;
+use crate::error::GameError;
+use crate::opening;
+use crate::protocol::{self, error_response};
+use crate::psq;
+use crate::rules::{self, RuleSet};
+use crate::time_manager::TimeManager;
 use crate::zobrist::{TTFlag, TranspositionTable, ZobristKeys};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
 
-const CANDIDATE_RADIUS: isize = 2;
 const CANDIDATE_CAP: usize = 80;
-const CENTER_CELLS: [(usize, usize); 4] = [(10, 10), (9, 9), (9, 10), (10, 9)];
-
-const SCORE_OPEN_FOUR: i32 = 50000;
-const SCORE_CLOSED_FOUR: i32 = 10000;
-const SCORE_OPEN_THREE: i32 = 5000;
-const SCORE_CLOSED_THREE: i32 = 500;
-const SCORE_OPEN_TWO: i32 = 100;
-const SCORE_CLOSED_TWO: i32 = 10;
+const MIN_CANDIDATE_CAP: usize = 30;
+const STONES_PER_NARROWER_CAP: usize = 2;
+const FULL_WIDTH_MOVE_TIME: Duration = Duration::from_millis(1000);
+const OPENING_DEPTH: usize = 4;
+const STONES_PER_EXTRA_PLY: usize = 4;
+
+const SCORE_OPEN_FOUR: i32 = EvalWeights::DEFAULT.open_four;
+const SCORE_CLOSED_FOUR: i32 = EvalWeights::DEFAULT.closed_four;
+const SCORE_OPEN_THREE: i32 = EvalWeights::DEFAULT.open_three;
+const SCORE_SHARP_MOVE: i32 = SCORE_OPEN_THREE / 2;
+const SCORE_FORK_THREE: i32 = 8000;
+
+const CENTER_BONUS_PER_RING: i32 = 3;
+const CENTER_BONUS_RINGS: usize = 5;
+const CENTER_BONUS_STONES: usize = 20;
 
 const SCORE_DOUBLE_THREAT: i32 = 80000;
 const SCORE_WIN: i32 = 100000;
+const MATE_THRESHOLD: i32 = SCORE_WIN - 1000;
 const MIN_EVAL_SCORE: i32 = -200000;
 const MAX_EVAL_SCORE: i32 = 200000;
 
-const TIME_BUDGET: Duration = Duration::from_secs(5);
+pub const BOARD_FULL: &str = "ERROR board full";
+const FAST_PLAY_BUDGET: Duration = Duration::from_millis(200);
+const UNLIMITED_SEARCH: Duration = Duration::from_secs(24 * 60 * 60);
+const RESIGN_PATIENCE: usize = 3;
+const MAX_PV_LENGTH: usize = 12;
 const MAX_SEARCH_DEPTH: usize = 20;
 const MAX_QUIESCENCE_DEPTH: usize = 4;
+/// Hard cap on plies from the root, extensions included, so a position full
+/// of mutual threats cannot keep extending past the nominal depth.
+const MAX_SEARCH_PLY: usize = MAX_SEARCH_DEPTH + 8;
+const LMR_MIN_DEPTH: usize = 3;
+const LMR_FULL_DEPTH_MOVES: usize = 3;
+const ASPIRATION_DELTA: i32 = 50;
+const VCF_MAX_DEPTH: usize = 8;
+const VCF_NODE_LIMIT: usize = 5000;
+const VCT_MAX_DEPTH: usize = 4;
+const VCT_NODE_LIMIT: usize = 3000;
+const VCT_TIME_DIVISOR: u32 = 10;
+const VCT_MIN_TIME: Duration = Duration::from_millis(200);
+const ENDGAME_EMPTY_CELLS: usize = 12;
+const ENDGAME_TIME_DIVISOR: u32 = 2;
+/// Depth recorded for solved positions: deeper than any search reaches, so
+/// the search trusts them and the solver can tell them apart.
+const SOLVED_DEPTH: u8 = u8::MAX;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct WinningMoves {
+    fives: [bool; 2],
+    open_fours: [bool; 2],
+}
 
-const BOARD_SIZE: usize = 20;
-const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+impl WinningMoves {
+    fn note(&mut self, player: Cell, threats: &ThreatInfo) {
+        let side = IncrementalScores::player_index(player);
+        self.fives[side] |= threats.fives > 0;
+        self.open_fours[side] |= threats.open_fours > threats.fives;
+    }
+}
 
 #[derive(Default, Clone, Copy)]
 pub struct ThreatInfo {
     pub open_fours: u8,
     pub closed_fours: u8,
     pub open_threes: u8,
+    pub fives: u8,
+    pub fork_threes: u8,
 }
 
 impl ThreatInfo {
@@ -40,22 +91,37 @@ impl ThreatInfo {
             || (self.closed_fours >= 1 && self.open_threes >= 1)
     }
 
+    pub fn threat_count(&self) -> u8 {
+        self.open_fours + self.closed_fours + self.open_threes
+    }
+
+    pub fn makes_four(&self) -> bool {
+        self.open_fours + self.closed_fours > 0
+    }
+
     pub fn score(&self) -> i32 {
+        if self.fives > 0 {
+            return SCORE_WIN;
+        }
+        if self.open_fours > 0 {
+            return SCORE_OPEN_FOUR;
+        }
         if self.is_winning() {
             return SCORE_DOUBLE_THREAT;
         }
         let mut s = 0i32;
-        s += self.open_fours as i32 * SCORE_OPEN_FOUR;
         s += self.closed_fours as i32 * SCORE_CLOSED_FOUR;
-        s += self.open_threes as i32 * SCORE_OPEN_THREE;
+        s += (self.open_threes - self.fork_threes) as i32 * SCORE_OPEN_THREE;
+        s += self.fork_threes as i32 * SCORE_FORK_THREE;
         s
     }
 }
 
 #[derive(Clone)]
 pub struct IncrementalScores {
-    scores: [[[i32; 4]; 400]; 2],
+    scores: [[[i32; 4]; MAX_BOARD_CELLS]; 2],
     totals: [i32; 2],
+    weights: EvalWeights,
 }
 
 impl Default for IncrementalScores {
@@ -66,14 +132,19 @@ impl Default for IncrementalScores {
 
 impl IncrementalScores {
     pub fn new() -> Self {
+        Self::with_weights(EvalWeights::DEFAULT)
+    }
+
+    pub fn with_weights(weights: EvalWeights) -> Self {
         Self {
-            scores: [[[0; 4]; 400]; 2],
+            scores: [[[0; 4]; MAX_BOARD_CELLS]; 2],
             totals: [0; 2],
+            weights,
         }
     }
 
     pub fn clear(&mut self) {
-        self.scores = [[[0; 4]; 400]; 2];
+        self.scores = [[[0; 4]; MAX_BOARD_CELLS]; 2];
         self.totals = [0; 2];
     }
 
@@ -87,8 +158,8 @@ impl IncrementalScores {
     }
 
     #[inline]
-    fn cell_index(x: usize, y: usize) -> usize {
-        y * BOARD_SIZE + x
+    fn cell_index(board: &Board, x: usize, y: usize) -> usize {
+        y * board.width() + x
     }
 
     pub fn evaluate_position(&self) -> i32 {
@@ -96,77 +167,15 @@ impl IncrementalScores {
     }
 
     fn evaluate_sequence_for_cell(
+        &self,
         board: &Board,
         x: usize,
         y: usize,
         dir_idx: usize,
         player: Cell,
     ) -> i32 {
-        let (dx, dy) = DIRECTIONS[dir_idx];
-        let size = BOARD_SIZE as isize;
-
-        let mut forward_count = 0;
-        let mut nx = x as isize + dx;
-        let mut ny = y as isize + dy;
-        while nx >= 0 && ny >= 0 && nx < size && ny < size {
-            if board.get_cell(nx as usize, ny as usize) == Some(player) {
-                forward_count += 1;
-                nx += dx;
-                ny += dy;
-            } else {
-                break;
-            }
-        }
-
-        let forward_open = nx >= 0
-            && ny >= 0
-            && nx < size
-            && ny < size
-            && board.get_cell(nx as usize, ny as usize) == Some(Cell::Empty);
-
-        let mut backward_count = 0;
-        nx = x as isize - dx;
-        ny = y as isize - dy;
-        while nx >= 0 && ny >= 0 && nx < size && ny < size {
-            if board.get_cell(nx as usize, ny as usize) == Some(player) {
-                backward_count += 1;
-                nx -= dx;
-                ny -= dy;
-            } else {
-                break;
-            }
-        }
-
-        let backward_open = nx >= 0
-            && ny >= 0
-            && nx < size
-            && ny < size
-            && board.get_cell(nx as usize, ny as usize) == Some(Cell::Empty);
-
-        let total_count = forward_count + backward_count + 1;
-        let open_sides = i32::from(forward_open) + i32::from(backward_open);
-
-        if total_count >= 4 {
-            if open_sides == 2 {
-                SCORE_OPEN_FOUR
-            } else {
-                SCORE_CLOSED_FOUR
-            }
-        } else if total_count == 3 {
-            if open_sides == 2 {
-                SCORE_OPEN_THREE
-            } else {
-                SCORE_CLOSED_THREE
-            }
-        } else if total_count == 2 {
-            if open_sides == 2 {
-                SCORE_OPEN_TWO
-            } else {
-                SCORE_CLOSED_TWO
-            }
-        } else {
-            0
-        }
+        self.weights
+            .score(line_pattern(board, x, y, DIRECTIONS[dir_idx], player))
     }
 
     fn collect_affected_stones(
@@ -176,42 +185,22 @@ impl IncrementalScores {
         dir_idx: usize,
     ) -> Vec<(usize, usize, Cell)> {
         let (dx, dy) = DIRECTIONS[dir_idx];
-        let size = BOARD_SIZE as isize;
         let mut affected = Vec::new();
 
-        let mut nx = x as isize + dx;
-        let mut ny = y as isize + dy;
-        while nx >= 0 && ny >= 0 && nx < size && ny < size {
-            match board.get_cell(nx as usize, ny as usize) {
-                Some(Cell::MyStone) => {
-                    affected.push((nx as usize, ny as usize, Cell::MyStone));
-                    nx += dx;
-                    ny += dy;
-                }
-                Some(Cell::OpStone) => {
-                    affected.push((nx as usize, ny as usize, Cell::OpStone));
-                    nx += dx;
-                    ny += dy;
-                }
-                _ => break,
-            }
-        }
-
-        nx = x as isize - dx;
-        ny = y as isize - dy;
-        while nx >= 0 && ny >= 0 && nx < size && ny < size {
-            match board.get_cell(nx as usize, ny as usize) {
-                Some(Cell::MyStone) => {
-                    affected.push((nx as usize, ny as usize, Cell::MyStone));
-                    nx -= dx;
-                    ny -= dy;
-                }
-                Some(Cell::OpStone) => {
-                    affected.push((nx as usize, ny as usize, Cell::OpStone));
-                    nx -= dx;
-                    ny -= dy;
+        for sign in [1, -1] {
+            let mut gap_used = false;
+            let mut k = 1;
+            loop {
+                let nx = x as isize + sign * dx * k;
+                let ny = y as isize + sign * dy * k;
+                match cell_at(board, nx, ny) {
+                    Some(cell) if cell.is_stone() => {
+                        affected.push((nx as usize, ny as usize, cell));
+                    }
+                    Some(Cell::Empty) if !gap_used => gap_used = true,
+                    _ => break,
                 }
-                _ => break,
+                k += 1;
             }
         }
 
@@ -226,19 +215,19 @@ impl IncrementalScores {
         dir_idx: usize,
         player: Cell,
     ) {
-        let idx = Self::cell_index(x, y);
+        let idx = Self::cell_index(board, x, y);
         let p_idx = Self::player_index(player);
 
         let old_score = self.scores[p_idx][idx][dir_idx];
         self.totals[p_idx] -= old_score;
 
-        let new_score = Self::evaluate_sequence_for_cell(board, x, y, dir_idx, player);
+        let new_score = self.evaluate_sequence_for_cell(board, x, y, dir_idx, player);
         self.scores[p_idx][idx][dir_idx] = new_score;
         self.totals[p_idx] += new_score;
     }
 
-    fn clear_cell_scores(&mut self, x: usize, y: usize, player: Cell) {
-        let idx = Self::cell_index(x, y);
+    fn clear_cell_scores(&mut self, board: &Board, x: usize, y: usize, player: Cell) {
+        let idx = Self::cell_index(board, x, y);
         let p_idx = Self::player_index(player);
 
         for dir_idx in 0..4 {
@@ -248,7 +237,8 @@ impl IncrementalScores {
         }
     }
 
-    pub fn on_stone_placed(&mut self, board: &Board, x: usize, y: usize, player: Cell) {
+    pub fn on_stone_placed(&mut self, board: &Board, x: usize, y: usize, player: Cell) -> i32 {
+        let before = self.evaluate_position();
         for dir_idx in 0..4 {
             let affected = Self::collect_affected_stones(board, x, y, dir_idx);
             for (ax, ay, ap) in &affected {
@@ -256,10 +246,12 @@ impl IncrementalScores {
             }
             self.update_cell_score(board, x, y, dir_idx, player);
         }
+        self.evaluate_position() - before
     }
 
-    pub fn on_stone_removed(&mut self, board: &Board, x: usize, y: usize, old_player: Cell) {
-        self.clear_cell_scores(x, y, old_player);
+    pub fn on_stone_removed(&mut self, board: &Board, x: usize, y: usize, old_player: Cell) -> i32 {
+        let before = self.evaluate_position();
+        self.clear_cell_scores(board, x, y, old_player);
 
         for dir_idx in 0..4 {
             let affected = Self::collect_affected_stones(board, x, y, dir_idx);
@@ -267,21 +259,22 @@ impl IncrementalScores {
                 self.update_cell_score(board, *ax, *ay, dir_idx, *ap);
             }
         }
+        self.evaluate_position() - before
     }
 
     pub fn rebuild_from_board(&mut self, board: &Board) {
         self.clear();
 
-        for y in 0..BOARD_SIZE {
-            for x in 0..BOARD_SIZE {
+        for y in 0..board.height() {
+            for x in 0..board.width() {
                 let cell = board.get_cell(x, y);
                 if cell == Some(Cell::MyStone) || cell == Some(Cell::OpStone) {
                     let player = cell.unwrap();
                     let p_idx = Self::player_index(player);
-                    let idx = Self::cell_index(x, y);
+                    let idx = Self::cell_index(board, x, y);
 
                     for dir_idx in 0..4 {
-                        let score = Self::evaluate_sequence_for_cell(board, x, y, dir_idx, player);
+                        let score = self.evaluate_sequence_for_cell(board, x, y, dir_idx, player);
                         self.scores[p_idx][idx][dir_idx] = score;
                         self.totals[p_idx] += score;
                     }
@@ -291,285 +284,1179 @@ impl IncrementalScores {
     }
 }
 
+fn cell_at(board: &Board, x: isize, y: isize) -> Option<Cell> {
+    if x < 0 || y < 0 {
+        None
+    } else {
+        board.get_cell(x as usize, y as usize)
+    }
+}
+
+fn line_pattern(
+    board: &Board,
+    x: usize,
+    y: usize,
+    (dx, dy): (isize, isize),
+    player: Cell,
+) -> Pattern {
+    let at = |k: isize| cell_at(board, x as isize + dx * k, y as isize + dy * k);
+    // A stone of ours on the window's edge may extend a run past it, which
+    // the table cannot see; such lines are scanned directly.
+    if at(WINDOW_RADIUS) == Some(player) || at(-WINDOW_RADIUS) == Some(player) {
+        return window_pattern(at, player);
+    }
+    pattern_table()[window_code(at, player)]
+}
+
+const WINDOW_RADIUS: isize = 5;
+const WINDOW_CODES: usize = 3usize.pow(2 * WINDOW_RADIUS as u32);
+
+fn window_code(at: impl Fn(isize) -> Option<Cell>, player: Cell) -> usize {
+    (-WINDOW_RADIUS..=WINDOW_RADIUS)
+        .filter(|&k| k != 0)
+        .fold(0, |code, k| {
+            let digit = match at(k) {
+                Some(Cell::Empty) => 0,
+                Some(cell) if cell == player => 1,
+                _ => 2,
+            };
+            code * 3 + digit
+        })
+}
+
+fn pattern_table() -> &'static [Pattern] {
+    static TABLE: OnceLock<Vec<Pattern>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        (0..WINDOW_CODES)
+            .map(|code| {
+                let at = |k: isize| {
+                    if k == 0 || k.abs() > WINDOW_RADIUS {
+                        return None;
+                    }
+                    let slot = if k < 0 {
+                        k + WINDOW_RADIUS
+                    } else {
+                        k + WINDOW_RADIUS - 1
+                    };
+                    let shift = 2 * WINDOW_RADIUS - 1 - slot;
+                    match code / 3usize.pow(shift as u32) % 3 {
+                        0 => Some(Cell::Empty),
+                        1 => Some(Cell::MyStone),
+                        _ => None,
+                    }
+                };
+                window_pattern(at, Cell::MyStone)
+            })
+            .collect()
+    })
+}
+
+fn window_pattern(at: impl Fn(isize) -> Option<Cell>, player: Cell) -> Pattern {
+    let open = |k: isize| at(k) == Some(Cell::Empty);
+    let run_end = |from: isize, step: isize| {
+        let mut k = from;
+        while at(k + step) == Some(player) {
+            k += step;
+        }
+        k
+    };
+
+    let hi = run_end(0, 1);
+    let lo = run_end(0, -1);
+    let mut best = run_pattern(hi - lo + 1, open(lo - 1), open(hi + 1));
+
+    if open(hi + 1) && at(hi + 2) == Some(player) {
+        let far = run_end(hi + 2, 1);
+        best = best.max(split_pattern(far - lo, open(lo - 1), open(far + 1)));
+    }
+    if open(lo - 1) && at(lo - 2) == Some(player) {
+        let far = run_end(lo - 2, -1);
+        best = best.max(split_pattern(hi - far, open(far - 1), open(hi + 1)));
+    }
+    best
+}
+
+fn run_pattern(stones: isize, open_back: bool, open_front: bool) -> Pattern {
+    let both_open = open_back && open_front;
+    match stones {
+        n if n >= 4 && both_open => Pattern::OpenFour,
+        4 if !open_back && !open_front => Pattern::None,
+        n if n >= 4 => Pattern::ClosedFour,
+        3 if both_open => Pattern::OpenThree,
+        3 => Pattern::ClosedThree,
+        2 if both_open => Pattern::OpenTwo,
+        2 => Pattern::ClosedTwo,
+        _ => Pattern::None,
+    }
+}
+
+fn split_pattern(stones: isize, open_back: bool, open_front: bool) -> Pattern {
+    if stones >= 4 {
+        Pattern::ClosedFour
+    } else {
+        run_pattern(stones, open_back, open_front)
+    }
+}
+
+/// Mate scores count plies from the root, which means nothing once the
+/// entry is reached through another path; the table stores them relative to
+/// the node instead.
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score + ply as i32
+    } else if score <= -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score - ply as i32
+    } else if score <= -MATE_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Cuts sorted candidates down to `cap`, except that moves making or
+/// stopping a four are kept wherever they sort, so the cap can never drop
+/// the one move that saves or wins the game.
+fn cap_candidates<T>(sorted: &mut Vec<T>, cap: usize, tactical: impl Fn(&T) -> bool) {
+    let mut seen = 0;
+    sorted.retain(|candidate| {
+        seen += 1;
+        seen <= cap || tactical(candidate)
+    });
+}
+
+fn canonical_moves(board: &Board, candidates: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    let symmetries: Vec<usize> = (1..SYMMETRIES)
+        .filter(|&s| board.is_symmetric_under(s))
+        .collect();
+    if symmetries.is_empty() {
+        return candidates;
+    }
+
+    let index = |(x, y): (usize, usize)| y * board.width() + x;
+    let mut listed = [false; MAX_BOARD_CELLS];
+    for &mv in &candidates {
+        listed[index(mv)] = true;
+    }
+    candidates
+        .into_iter()
+        .filter(|&mv| {
+            symmetries.iter().all(|&s| {
+                board.symmetric_coords(mv.0, mv.1, s).map_or(true, |image| {
+                    index(image) >= index(mv) || !listed[index(image)]
+                })
+            })
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct ForcingMoves {
+    moves: Vec<(usize, usize)>,
+    can_win: bool,
+    opponent_fives: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchReport {
+    pub best_move: (usize, usize),
+    pub score: i32,
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchPlan {
+    pub max_depth: usize,
+    pub candidate_cap: usize,
+}
+
+pub fn search_plan(stones: usize) -> SearchPlan {
+    SearchPlan {
+        max_depth: (OPENING_DEPTH + stones / STONES_PER_EXTRA_PLY).min(MAX_SEARCH_DEPTH),
+        candidate_cap: CANDIDATE_CAP
+            .saturating_sub(stones / STONES_PER_NARROWER_CAP)
+            .max(MIN_CANDIDATE_CAP),
+    }
+}
+
+fn adaptive_candidate_cap(
+    plan_cap: usize,
+    budget: Duration,
+    sorted_scores: impl IntoIterator<Item = i32>,
+) -> usize {
+    let full = FULL_WIDTH_MOVE_TIME.as_millis();
+    let timed = plan_cap as u128 * budget.as_millis().min(full) / full;
+    let mut scores = sorted_scores.into_iter();
+    let contested = match scores.next() {
+        Some(best) if best >= SCORE_SHARP_MOVE => {
+            1 + scores
+                .take_while(|&score| score >= SCORE_SHARP_MOVE && score >= best / 2)
+                .count()
+        }
+        _ => 0,
+    };
+    (timed as usize)
+        .max(contested)
+        .clamp(MIN_CANDIDATE_CAP, CANDIDATE_CAP)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Win(Cell),
+    Draw,
+}
+
+pub fn format_score(score: i32) -> String {
+    if score >= MATE_THRESHOLD {
+        format!("mate in {}", (SCORE_WIN - score + 1) / 2)
+    } else if score <= -MATE_THRESHOLD {
+        format!("mated in {}", (SCORE_WIN + score + 1) / 2)
+    } else {
+        score.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub tt: usize,
+    pub eval: usize,
+    pub board: usize,
+    pub move_cache: usize,
+    pub heuristics: usize,
+    pub moves: usize,
+    pub pending: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.tt
+            + self.eval
+            + self.board
+            + self.move_cache
+            + self.heuristics
+            + self.moves
+            + self.pending
+    }
+}
+
+const MOVE_CACHE_SLOTS: usize = 1 << 12;
+
+type CachedMoves = (u64, Vec<(usize, usize)>, WinningMoves);
+
+/// Only valid for one move, as the lists also depend on the configuration.
+/// Lists longer than `CANDIDATE_CAP` are not kept, which bounds the cache by
+/// `MoveCache::MAX_BYTES`.
+struct MoveCache {
+    slots: Vec<Option<CachedMoves>>,
+    hits: u64,
+}
+
+impl MoveCache {
+    const MAX_BYTES: usize = MOVE_CACHE_SLOTS
+        * (std::mem::size_of::<Option<CachedMoves>>()
+            + CANDIDATE_CAP * std::mem::size_of::<(usize, usize)>());
+
+    fn new() -> Self {
+        Self {
+            slots: vec![None; MOVE_CACHE_SLOTS],
+            hits: 0,
+        }
+    }
+
+    fn get(&mut self, hash: u64) -> Option<(Vec<(usize, usize)>, WinningMoves)> {
+        match &self.slots[hash as usize & (MOVE_CACHE_SLOTS - 1)] {
+            Some((key, moves, wins)) if *key == hash => {
+                self.hits += 1;
+                Some((moves.clone(), *wins))
+            }
+            _ => None,
+        }
+    }
+
+    fn insert(&mut self, hash: u64, moves: &[(usize, usize)], wins: WinningMoves) {
+        if moves.len() <= CANDIDATE_CAP {
+            self.slots[hash as usize & (MOVE_CACHE_SLOTS - 1)] = Some((hash, moves.to_vec(), wins));
+        }
+    }
+
+    fn memory_bytes(&self) -> usize {
+        let lists: usize = self
+            .slots
+            .iter()
+            .flatten()
+            .map(|(_, moves, _)| moves.capacity())
+            .sum();
+        self.slots.capacity() * std::mem::size_of::<Option<CachedMoves>>()
+            + lists * std::mem::size_of::<(usize, usize)>()
+    }
+
+    fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+        self.hits = 0;
+    }
+}
+
+fn transposition_table(max_memory: usize) -> TranspositionTable {
+    TranspositionTable::with_capacity(max_memory.saturating_sub(MoveCache::MAX_BYTES))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BoardBlock {
+    name: &'static str,
+    earlier_moves: usize,
+}
+
+#[derive(Clone)]
+struct PendingMove {
+    board: Board,
+    inc_scores: IncrementalScores,
+    game_in_progress: bool,
+    history_len: usize,
+    opponent_move: Option<(usize, usize)>,
+}
+
+impl PendingMove {
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() - std::mem::size_of::<Board>() + self.board.memory_bytes()
+    }
+}
+
 pub struct GameState {
-    size: usize,
+    config: GameConfig,
+    width: usize,
+    height: usize,
     is_initialized: bool,
     game_in_progress: bool,
     board: Board,
     zobrist: ZobristKeys,
-    tt: TranspositionTable,
+    tt: Arc<TranspositionTable>,
     inc_scores: IncrementalScores,
     killer_moves: [[Option<(usize, usize)>; 2]; MAX_SEARCH_DEPTH],
-    history: [[i32; 400]; 2],
+    history: [[i32; MAX_BOARD_CELLS]; 2],
+    search_path: Vec<u64>,
+    root_hint: Option<(usize, usize)>,
+    move_cache: MoveCache,
+    time_left: Option<Duration>,
+    game_type: Option<u32>,
+    folder: Option<String>,
+    move_history: Vec<(usize, usize, Cell)>,
+    move_times: Vec<Instant>,
+    first_player: Option<Cell>,
+    last_search: Option<SearchReport>,
+    last_evaluation: Option<((usize, usize), Option<i32>)>,
+    nodes: u64,
+    losing_streak: usize,
+    fast_play: bool,
+    pending_move: Option<PendingMove>,
+    board_block: Option<BoardBlock>,
+    stop: Option<Arc<AtomicBool>>,
+    #[cfg(test)]
+    before_search: Option<fn(&mut GameState)>,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GameState {
     pub fn new() -> Self {
+        Self::with_config(GameConfig::default())
+    }
+
+    pub fn with_config(config: GameConfig) -> Self {
+        let tt = Arc::new(transposition_table(config.max_memory));
+        let inc_scores = IncrementalScores::with_weights(config.eval_weights);
+        let board = Board::new(config.board_size).unwrap_or_default();
+        pattern_table();
         GameState {
-            size: 0,
+            config,
+            width: 0,
+            height: 0,
             is_initialized: false,
             game_in_progress: false,
-            board: Board::default(),
+            board,
             zobrist: ZobristKeys::new(),
-            tt: TranspositionTable::new(),
-            inc_scores: IncrementalScores::new(),
+            tt,
+            inc_scores,
             killer_moves: [[None; 2]; MAX_SEARCH_DEPTH],
-            history: [[0; 400]; 2],
+            history: [[0; MAX_BOARD_CELLS]; 2],
+            search_path: Vec::with_capacity(MAX_SEARCH_PLY),
+            root_hint: None,
+            move_cache: MoveCache::new(),
+            time_left: None,
+            game_type: None,
+            folder: None,
+            move_history: Vec::new(),
+            move_times: Vec::new(),
+            first_player: None,
+            last_search: None,
+            last_evaluation: None,
+            nodes: 0,
+            losing_streak: 0,
+            fast_play: false,
+            pending_move: None,
+            board_block: None,
+            stop: None,
+            #[cfg(test)]
+            before_search: None,
         }
     }
 
     pub fn handle_start(&mut self, size: usize) -> String {
-        if size != 20 {
-            return format!("ERROR unsupported size {}", size);
+        match self.sized_board(size, size) {
+            Some(board) => self.start_with(board),
+            None => format!("ERROR unsupported size {}", size),
+        }
+    }
+
+    pub fn handle_rect_start(&mut self, width: usize, height: usize) -> String {
+        match self.sized_board(width, height) {
+            Some(board) => self.start_with(board),
+            None => error_response(GameError::UnsupportedSize { width, height }),
         }
-        self.size = size;
+    }
+
+    fn sized_board(&self, width: usize, height: usize) -> Option<Board> {
+        let within_cap = width
+            .checked_mul(height)
+            .is_some_and(|cells| cells <= self.config.max_board_cells);
+        Board::new_rect(width, height).filter(|_| within_cap)
+    }
+
+    fn start_with(&mut self, board: Board) -> String {
+        self.save_psq();
+        self.width = board.width();
+        self.height = board.height();
         self.is_initialized = true;
         self.game_in_progress = false;
-        self.board.clear();
+        self.board = board;
+        self.forget_game();
+        "OK".to_string()
+    }
+
+    fn forget_game(&mut self) {
+        self.board_block = None;
         self.tt.clear();
         self.inc_scores.clear();
-        self.killer_moves = [[None; 2]; MAX_SEARCH_DEPTH];
-        self.history = [[0; 400]; 2];
-        "OK".to_string()
+        self.clear_history();
+        self.first_player = None;
+        self.losing_streak = 0;
+        self.fast_play = false;
+        self.clear_heuristics();
     }
 
-    #[inline]
-    fn place_stone(&mut self, x: usize, y: usize, cell: Cell) {
-        if let Some(idx) = self.board.get_index(x, y) {
-            let old_cell = self.board.get_cell(x, y).unwrap_or(Cell::Empty);
-            if old_cell != Cell::Empty {
-                self.board
-                    .update_hash(self.zobrist.stone_key(idx, old_cell));
+    pub fn analysis_enabled(&self) -> bool {
+        self.config.analysis
+    }
+
+    pub fn ponder_enabled(&self) -> bool {
+        self.config.ponder
+    }
+
+    fn search_copy(&self) -> GameState {
+        let mut position = GameState::with_config(GameConfig {
+            max_memory: 0,
+            ..self.config.clone()
+        });
+        position.config.max_memory = self.config.max_memory;
+        position.tt = Arc::clone(&self.tt);
+        position.width = self.width;
+        position.height = self.height;
+        position.is_initialized = true;
+        position.game_in_progress = self.game_in_progress;
+        position.board = self.board.clone();
+        position.inc_scores = self.inc_scores.clone();
+        position.history = self.history;
+        position.first_player = Some(self.black_player());
+        position.root_hint = self.root_hint;
+        position
+    }
+
+    pub fn ponder_position(&mut self) -> Option<(GameState, (usize, usize))> {
+        let &(x, y, cell) = self.move_history.last()?;
+        if cell != Cell::MyStone || !self.game_in_progress {
+            return None;
+        }
+        let guess = *self.principal_variation((x, y)).get(1)?;
+        let mut position = self.search_copy();
+        position.push_move(guess.0, guess.1, Cell::OpStone);
+        Some((position, guess))
+    }
+
+    pub fn ponder(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = Some(stop);
+        if self.game_over().is_none() {
+            self.find_best_move(Instant::now() + UNLIMITED_SEARCH);
+        }
+        self.stop = None;
+    }
+
+    fn out_of_time(&self, deadline: Instant) -> bool {
+        Instant::now() >= deadline
+            || self
+                .stop
+                .as_ref()
+                .is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
+    pub fn handle_info(&mut self, key: &str, value: &str) {
+        let millis = value.trim().parse::<u64>().ok().map(Duration::from_millis);
+        match key.to_ascii_lowercase().as_str() {
+            "timeout_turn" => self.config.timeout_turn = millis,
+            "timeout_match" => self.config.timeout_match = millis,
+            "time_left" => self.time_left = millis,
+            "max_memory" => {
+                if let Ok(bytes) = value.trim().parse::<usize>() {
+                    self.set_max_memory(bytes);
+                }
+            }
+            "rule" => {
+                if let Some(rule) = RuleSet::from_info(value) {
+                    self.config.rule = rule;
+                }
+            }
+            "max_depth" => {
+                if let Some(depth) = config::parse_max_depth(value) {
+                    self.config.max_depth = depth;
+                }
+            }
+            "adjacency_radius" => {
+                if let Some(radius) = config::parse_adjacency_radius(value) {
+                    self.config.adjacency_radius = radius;
+                }
             }
-            self.board.set_cell(x, y, cell).unwrap();
-            if cell != Cell::Empty {
-                self.board.update_hash(self.zobrist.stone_key(idx, cell));
-                self.inc_scores.on_stone_placed(&self.board, x, y, cell);
+            "center_radius" => {
+                if let Some(rings) = config::parse_center_radius(value) {
+                    self.config.center_radius = rings;
+                }
             }
+            "game_type" => self.game_type = value.trim().parse().ok(),
+            "folder" => self.folder = Some(value.trim().to_string()),
+            // The manager's cursor: answered on the debug log only, as
+            // INFO gets no reply.
+            "evaluate" => match protocol::parse_coordinates(value) {
+                Ok((x, y)) => self.handle_evaluate(x, y),
+                Err(reason) => debug!("INFO evaluate {}: {}", value.trim(), reason),
+            },
+            _ => {}
+        }
+    }
+
+    fn set_max_memory(&mut self, bytes: usize) {
+        let bytes = if bytes == 0 {
+            DEFAULT_MAX_MEMORY
+        } else {
+            bytes
+        };
+        if bytes != self.config.max_memory {
+            self.config.max_memory = bytes;
+            self.tt = Arc::new(transposition_table(bytes));
+        }
+    }
+
+    fn move_time_budget(&self) -> Duration {
+        let budget =
+            TimeManager::new(&self.config, self.time_left).move_budget(self.board.stone_count());
+        if self.fast_play {
+            budget.min(FAST_PLAY_BUDGET)
+        } else {
+            budget
         }
     }
 
     #[inline]
-    fn remove_stone(&mut self, x: usize, y: usize) {
-        if let Some(idx) = self.board.get_index(x, y) {
-            let old_cell = self.board.get_cell(x, y).unwrap_or(Cell::Empty);
-            if old_cell != Cell::Empty {
-                self.board
-                    .update_hash(self.zobrist.stone_key(idx, old_cell));
-            }
-            self.board.set_cell(x, y, Cell::Empty).unwrap();
-            if old_cell == Cell::MyStone || old_cell == Cell::OpStone {
-                self.inc_scores
-                    .on_stone_removed(&self.board, x, y, old_cell);
-            }
+    fn place_stone(&mut self, x: usize, y: usize, cell: Cell) -> i32 {
+        if self
+            .board
+            .set_cell_hashed(x, y, cell, &self.zobrist)
+            .is_ok()
+            && cell != Cell::Empty
+        {
+            self.inc_scores.on_stone_placed(&self.board, x, y, cell)
+        } else {
+            0
         }
     }
 
-    pub fn validate_move(&self, x: usize, y: usize) -> Result<(), &'static str> {
-        if !self.is_initialized {
-            return Err("ERROR game not initialized");
+    #[inline]
+    fn remove_stone(&mut self, x: usize, y: usize) -> i32 {
+        let old_cell = self.board.get_cell(x, y).unwrap_or(Cell::Empty);
+        if self
+            .board
+            .set_cell_hashed(x, y, Cell::Empty, &self.zobrist)
+            .is_ok()
+            && (old_cell == Cell::MyStone || old_cell == Cell::OpStone)
+        {
+            self.inc_scores
+                .on_stone_removed(&self.board, x, y, old_cell)
+        } else {
+            0
+        }
+    }
+
+    fn ensure_initialized(&self) -> Result<(), GameError> {
+        if self.is_initialized {
+            Ok(())
+        } else {
+            Err(GameError::NotInitialized)
         }
-        if x >= self.size || y >= self.size {
-            return Err("ERROR coordinates out of range");
+    }
+
+    pub fn validate_move(&self, x: usize, y: usize) -> Result<(), GameError> {
+        self.ensure_initialized()?;
+        if x >= self.width || y >= self.height {
+            return Err(GameError::OutOfBounds);
         }
         if self.board.get_cell(x, y) == Some(Cell::Forbidden) {
-            return Err("ERROR move forbidden");
+            return Err(GameError::Forbidden);
         }
         if !self.board.is_empty(x, y) {
-            return Err("ERROR cell already occupied");
+            return Err(GameError::Occupied);
+        }
+        Ok(())
+    }
+
+    pub fn validate_move_for(&self, x: usize, y: usize, player: Cell) -> Result<(), GameError> {
+        self.validate_move(x, y)?;
+        if self.config.rule == RuleSet::Renju
+            && player == self.black_player()
+            && rules::is_forbidden_for_black(&self.board, x, y, player)
+        {
+            return Err(GameError::Forbidden);
         }
         Ok(())
     }
 
+    fn exact_five_for(&self, player: Cell) -> bool {
+        match self.config.rule {
+            RuleSet::Freestyle => false,
+            RuleSet::Standard => true,
+            RuleSet::Renju => player == self.black_player(),
+        }
+    }
+
+    pub fn first_player(&self) -> Option<Cell> {
+        self.first_player
+    }
+
+    fn black_player(&self) -> Cell {
+        self.first_player.unwrap_or_else(|| {
+            self.move_history
+                .first()
+                .map_or(Cell::MyStone, |&(_, _, cell)| cell)
+        })
+    }
+
     pub fn handle_turn(&mut self, x: usize, y: usize) -> String {
-        if !self.is_initialized {
-            self.handle_start(20);
+        if let Err(e) = self.ensure_initialized() {
+            return error_response(e);
         }
 
         if let Err(e) = self.validate_move(x, y) {
-            return e.to_string();
+            return error_response(e);
         }
 
-        self.place_stone(x, y, Cell::OpStone);
+        if self.first_player.is_none() && self.move_history.is_empty() {
+            self.first_player = Some(Cell::OpStone);
+        }
+        self.begin_pending_move(Some((x, y)));
+        self.push_move(x, y, Cell::OpStone);
         self.game_in_progress = true;
 
-        if self.game_over().is_some() {
-            self.game_in_progress = false;
+        if self.settle_if_over() {
+            self.pending_move = None;
+            return self.emergency_move();
         }
 
         self.generate_move()
     }
 
-    pub fn game_over(&self) -> Option<Cell> {
-        if self.board.check_five_in_a_row(Cell::MyStone) {
-            return Some(Cell::MyStone);
+    pub fn handle_play(&mut self, x: usize, y: usize) -> String {
+        if let Err(e) = self.ensure_initialized() {
+            return error_response(e);
+        }
+        if let Err(e) = self.validate_move_for(x, y, Cell::MyStone) {
+            return error_response(e);
+        }
+
+        self.pending_move = None;
+        self.push_move(x, y, Cell::MyStone);
+        self.game_in_progress = !self.settle_if_over();
+        format!("{},{}", x, y)
+    }
+
+    fn begin_pending_move(&mut self, opponent_move: Option<(usize, usize)>) {
+        self.last_search = None;
+        self.pending_move = Some(PendingMove {
+            board: self.board.clone(),
+            inc_scores: self.inc_scores.clone(),
+            game_in_progress: self.game_in_progress,
+            history_len: self.move_history.len(),
+            opponent_move,
+        });
+    }
+
+    pub fn recover_move(&mut self) -> String {
+        if let Some(pending) = self.pending_move.take() {
+            self.board = pending.board;
+            self.inc_scores = pending.inc_scores;
+            self.game_in_progress = pending.game_in_progress;
+            self.move_history.truncate(pending.history_len);
+            self.move_times.truncate(pending.history_len);
+            if let Some((x, y)) = pending.opponent_move {
+                self.push_move(x, y, Cell::OpStone);
+                self.game_in_progress = true;
+            }
+        }
+
+        let searched = self
+            .last_search
+            .map(|report| report.best_move)
+            .filter(|&(x, y)| self.validate_move_for(x, y, Cell::MyStone).is_ok());
+        match searched.or_else(|| self.any_empty_cell()) {
+            Some((x, y)) => {
+                self.push_move(x, y, Cell::MyStone);
+                self.settle_if_over();
+                format!("{},{}", x, y)
+            }
+            None => self.emergency_move(),
         }
-        if self.board.check_five_in_a_row(Cell::OpStone) {
-            return Some(Cell::OpStone);
+    }
+
+    pub fn game_over(&self) -> Option<GameResult> {
+        for player in [Cell::MyStone, Cell::OpStone] {
+            if self.board.check_win(player, self.exact_five_for(player)) {
+                return Some(GameResult::Win(player));
+            }
         }
         if self.board.is_full() {
-            return Some(Cell::Empty);
+            return Some(GameResult::Draw);
         }
         None
     }
 
+    fn settle_if_over(&mut self) -> bool {
+        let Some(result) = self.game_over() else {
+            return false;
+        };
+        debug!(
+            "game over: {:?} after {} stones",
+            result,
+            self.count_stones()
+        );
+        self.game_in_progress = false;
+        true
+    }
+
     pub fn handle_begin(&mut self) -> String {
-        if !self.is_initialized {
-            self.handle_start(20);
+        if let Err(e) = self.ensure_initialized() {
+            return error_response(e);
         }
+        if self.first_player.is_none() && self.move_history.is_empty() {
+            self.first_player = Some(Cell::MyStone);
+        }
+        self.begin_pending_move(None);
         self.game_in_progress = true;
         self.generate_move()
     }
 
-    pub fn handle_board_start(&mut self) -> Result<(), &'static str> {
-        if !self.is_initialized {
-            self.handle_start(20);
-        }
+    pub fn handle_board_start(&mut self) -> Result<(), GameError> {
+        self.ensure_initialized()?;
         self.game_in_progress = true;
         self.board.clear();
         self.inc_scores.clear();
+        self.clear_history();
+        self.first_player = None;
+        self.board_block = Some(BoardBlock {
+            name: "BOARD",
+            earlier_moves: 0,
+        });
         Ok(())
     }
 
-    pub fn handle_board_move(
-        &mut self,
-        x: usize,
-        y: usize,
-        field: usize,
-    ) -> Result<(), &'static str> {
-        if !self.is_initialized {
-            self.handle_start(20);
-        }
-        if x >= self.size || y >= self.size {
-            return Err("ERROR coordinates out of range");
+    pub fn handle_yxboard_start(&mut self) -> Result<(), GameError> {
+        self.ensure_initialized()?;
+        self.game_in_progress = true;
+        self.board_block = Some(BoardBlock {
+            name: "YXBOARD",
+            earlier_moves: self.move_history.len(),
+        });
+        Ok(())
+    }
+
+    pub fn handle_board_move(&mut self, x: usize, y: usize, field: usize) -> Result<(), GameError> {
+        self.ensure_initialized()?;
+        if x >= self.width || y >= self.height {
+            return Err(GameError::OutOfBounds);
         }
 
-        let cell = match field {
-            0 => Cell::Empty,
-            1 => Cell::MyStone,
-            2 => Cell::OpStone,
-            3 => Cell::Forbidden,
-            _ => return Err("ERROR invalid board field"),
+        let cell = match Cell::from_board_field(field) {
+            // Under Renju the engine computes forbidden points itself, so the
+            // manager's markers are dropped rather than marked a second time.
+            Some(Cell::Forbidden) if self.config.rule == RuleSet::Renju => Cell::Empty,
+            Some(cell) => cell,
+            None => return Err(GameError::InvalidField(field)),
         };
 
-        self.place_stone(x, y, cell);
+        if let Some(previous) = self.board.get_cell(x, y).filter(|&c| c != Cell::Empty) {
+            let block = self.board_block.unwrap_or(BoardBlock {
+                name: "BOARD",
+                earlier_moves: 0,
+            });
+            let entry = self.history_index(x, y);
+            if entry.is_some_and(|index| index < block.earlier_moves) {
+                debug!(
+                    "{} overwrites {},{}, played before the block",
+                    block.name, x, y
+                );
+                return Err(GameError::Occupied);
+            }
+            debug!(
+                "{} lists {},{} again: {:?} replaces {:?}",
+                block.name, x, y, cell, previous
+            );
+            self.forget_board_entry(x, y);
+        }
+        if cell.is_stone() {
+            self.push_move(x, y, cell);
+        } else {
+            self.place_stone(x, y, cell);
+        }
         Ok(())
     }
 
+    fn history_index(&self, x: usize, y: usize) -> Option<usize> {
+        self.move_history
+            .iter()
+            .position(|&(hx, hy, _)| (hx, hy) == (x, y))
+    }
+
+    fn forget_board_entry(&mut self, x: usize, y: usize) {
+        if let Some(index) = self.history_index(x, y) {
+            self.move_history.remove(index);
+            self.move_times.remove(index);
+        }
+        self.remove_stone(x, y);
+    }
+
     pub fn handle_board_done(&mut self) -> String {
-        if !self.is_initialized {
-            self.handle_start(20);
+        self.board_block = None;
+        if let Err(e) = self.ensure_initialized() {
+            return error_response(e);
         }
         self.inc_scores.rebuild_from_board(&self.board);
+        self.begin_pending_move(None);
         self.generate_move()
     }
 
     pub fn handle_restart(&mut self) -> String {
-        if !self.is_initialized {
-            return "ERROR game not initialized".to_string();
+        if let Err(e) = self.ensure_initialized() {
+            return error_response(e);
         }
+        self.save_psq();
         self.game_in_progress = false;
         self.board.clear();
+        self.forget_game();
+        "OK".to_string()
+    }
+
+    pub fn handle_swap(&mut self) -> String {
+        if let Err(e) = self.ensure_initialized() {
+            return error_response(e);
+        }
+        self.game_in_progress = true;
+        if self.evaluation() < 0 {
+            self.swap_colors();
+            return "SWAP".to_string();
+        }
+        self.begin_pending_move(None);
+        self.generate_move()
+    }
+
+    fn swap_colors(&mut self) {
+        let stones: Vec<(usize, usize)> = self.board.occupied().collect();
+        for (x, y) in stones {
+            let cell = self.board.get_cell(x, y).unwrap_or(Cell::Empty);
+            self.remove_stone(x, y);
+            self.place_stone(x, y, cell.opponent());
+        }
+        for entry in &mut self.move_history {
+            entry.2 = entry.2.opponent();
+        }
+        self.first_player = self.first_player.map(Cell::opponent);
         self.tt.clear();
+        self.clear_heuristics();
+    }
+
+    fn push_move(&mut self, x: usize, y: usize, cell: Cell) {
+        self.place_stone(x, y, cell);
+        self.move_history.push((x, y, cell));
+        self.move_times.push(Instant::now());
+    }
+
+    fn clear_history(&mut self) {
+        self.move_history.clear();
+        self.move_times.clear();
+    }
+
+    pub fn psq_record(&self) -> String {
+        let mut previous = self.move_times.first().copied();
+        let moves: Vec<(usize, usize, u128)> = self
+            .move_history
+            .iter()
+            .zip(&self.move_times)
+            .map(|(&(x, y, _), &at)| {
+                let ms = previous.map_or(0, |p| at.duration_since(p).as_millis());
+                previous = Some(at);
+                (x, y, ms)
+            })
+            .collect();
+        psq::format_psq(self.width, self.height, &moves)
+    }
+
+    pub fn save_psq(&self) {
+        let Some(dir) = &self.config.psq_dir else {
+            return;
+        };
+        if self.move_history.is_empty() {
+            return;
+        }
+        if let Err(err) = psq::write_psq(dir, &self.psq_record()) {
+            debug!("could not write PSQ record: {}", err);
+        }
+    }
+
+    pub fn handle_end(&mut self) {
+        self.save_psq();
+        debug::flush();
+        self.board.clear();
         self.inc_scores.clear();
+        self.clear_history();
+        self.pending_move = None;
+        self.is_initialized = false;
+        self.game_in_progress = false;
+    }
+
+    fn pop_move(&mut self) -> Option<(usize, usize, Cell)> {
+        let (x, y, cell) = self.move_history.pop()?;
+        self.move_times.pop();
+        self.remove_stone(x, y);
+        Some((x, y, cell))
+    }
+
+    pub fn undo_move(&mut self) -> Option<(usize, usize)> {
+        let (x, y, _) = self.pop_move()?;
+        self.game_in_progress = self.game_over().is_none();
+        Some((x, y))
+    }
+
+    pub fn handle_takeback(&mut self, x: usize, y: usize) -> String {
+        if let Err(e) = self.ensure_initialized() {
+            return error_response(e);
+        }
+        if x >= self.width || y >= self.height {
+            return error_response(GameError::OutOfBounds);
+        }
+        if !self.board.get_cell(x, y).is_some_and(Cell::is_stone) {
+            return "ERROR no stone to take back".to_string();
+        }
+
+        self.remove_stone(x, y);
+        if let Some(pos) = self
+            .move_history
+            .iter()
+            .rposition(|&(mx, my, _)| (mx, my) == (x, y))
+        {
+            self.move_history.remove(pos);
+            self.move_times.remove(pos);
+        }
+        self.pending_move = None;
+        self.game_in_progress = self.game_over().is_none();
         "OK".to_string()
     }
 
-    fn count_stones(&self) -> usize {
-        let mut count = 0;
-        for y in 0..self.size {
-            for x in 0..self.size {
-                match self.board.get_cell(x, y) {
-                    Some(Cell::MyStone) | Some(Cell::OpStone) => count += 1,
-                    _ => {}
-                }
-            }
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn rule(&self) -> RuleSet {
+        self.config.rule
+    }
+
+    pub fn game_type(&self) -> Option<u32> {
+        self.game_type
+    }
+
+    pub fn folder(&self) -> Option<&str> {
+        self.folder.as_deref()
+    }
+
+    pub fn evaluation(&self) -> i32 {
+        self.evaluate_position()
+    }
+
+    pub fn evaluate_move(&mut self, x: usize, y: usize) -> Option<i32> {
+        self.validate_move_for(x, y, Cell::MyStone).ok()?;
+        self.place_stone(x, y, Cell::MyStone);
+        let score = self.evaluate_position();
+        self.remove_stone(x, y);
+        Some(score)
+    }
+
+    fn handle_evaluate(&mut self, x: usize, y: usize) {
+        if !self.is_initialized {
+            return;
+        }
+        let score = self.evaluate_move(x, y);
+        match score {
+            Some(score) => debug!("evaluate {},{} score={}", x, y, format_score(score)),
+            None => debug!("evaluate {},{}: not a legal move", x, y),
+        }
+        self.last_evaluation = Some(((x, y), score));
+    }
+
+    pub fn last_evaluation(&self) -> Option<((usize, usize), Option<i32>)> {
+        self.last_evaluation
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            tt: self.tt.memory_bytes(),
+            eval: std::mem::size_of::<IncrementalScores>(),
+            board: self.board.memory_bytes(),
+            move_cache: self.move_cache.memory_bytes(),
+            heuristics: std::mem::size_of_val(&self.killer_moves)
+                + std::mem::size_of_val(&self.history)
+                + self.search_path.capacity() * std::mem::size_of::<u64>(),
+            moves: self.move_history.capacity() * std::mem::size_of::<(usize, usize, Cell)>()
+                + self.move_times.capacity() * std::mem::size_of::<Instant>(),
+            pending: self
+                .pending_move
+                .as_ref()
+                .map_or(0, PendingMove::memory_bytes),
+        }
+    }
+
+    pub fn search_nodes(&mut self, depth: usize) -> u64 {
+        self.move_cache.clear();
+        self.search_to_depth(Instant::now() + UNLIMITED_SEARCH, depth);
+        self.nodes
+    }
+
+    fn cached_candidates(&mut self) -> Vec<(usize, usize)> {
+        self.cached_scan().0
+    }
+
+    fn cached_scan(&mut self) -> (Vec<(usize, usize)>, WinningMoves) {
+        let hash = self.board.hash();
+        if let Some(scan) = self.move_cache.get(hash) {
+            return scan;
         }
-        count
+        let (moves, wins) = self.scan_candidates();
+        self.move_cache.insert(hash, &moves, wins);
+        (moves, wins)
+    }
+
+    pub fn candidate_moves(&self) -> Vec<(usize, usize)> {
+        self.generate_candidates()
+    }
+
+    fn count_stones(&self) -> usize {
+        self.board.stone_count()
     }
 
     fn center_distance(&self, x: usize, y: usize) -> usize {
-        let center = self.size / 2;
-        let dx = if x > center { x - center } else { center - x };
-        let dy = if y > center { y - center } else { center - y };
-        dx.max(dy)
+        x.abs_diff(self.width / 2).max(y.abs_diff(self.height / 2))
+    }
+
+    fn center_cells(&self) -> [(usize, usize); 4] {
+        let (cx, cy) = (self.width / 2, self.height / 2);
+        [(cx, cy), (cx - 1, cy - 1), (cx - 1, cy), (cx, cy - 1)]
     }
 
-    fn add_candidate(mask: &mut [bool; 400], size: usize, x: usize, y: usize) {
-        let idx = y * size + x;
+    fn add_candidate(mask: &mut [bool; MAX_BOARD_CELLS], width: usize, x: usize, y: usize) {
+        let idx = y * width + x;
         mask[idx] = true;
     }
 
     fn generate_candidates(&self) -> Vec<(usize, usize)> {
+        self.scan_candidates().0
+    }
+
+    fn scan_candidates(&self) -> (Vec<(usize, usize)>, WinningMoves) {
+        let mut wins = WinningMoves::default();
         let total_stones = self.count_stones();
         if total_stones == 0 {
             let mut centers = Vec::new();
-            for &(x, y) in &CENTER_CELLS {
+            for (x, y) in self.center_cells() {
                 if self.board.is_empty(x, y) {
                     centers.push((x, y));
                 }
             }
             if !centers.is_empty() {
-                return centers;
+                return (centers, wins);
             }
-            return self.board.iter_empty().collect();
+            return (self.board.iter_empty().collect(), wins);
         }
 
         let early_game = total_stones <= 2;
-        let mut mask = [false; 400];
+        let mut mask = [false; MAX_BOARD_CELLS];
 
         if early_game {
-            for &(cx, cy) in &CENTER_CELLS {
-                for dy in -1..=1 {
-                    for dx in -1..=1 {
+            let reach = self.config.center_radius as isize;
+            for (cx, cy) in self.center_cells() {
+                for dy in -reach..=reach {
+                    for dx in -reach..=reach {
                         let nx = cx as isize + dx;
                         let ny = cy as isize + dy;
-                        if nx < 0 || ny < 0 || nx >= self.size as isize || ny >= self.size as isize
+                        if nx < 0
+                            || ny < 0
+                            || nx >= self.width as isize
+                            || ny >= self.height as isize
                         {
                             continue;
                         }
                         let ux = nx as usize;
                         let uy = ny as usize;
                         if self.board.is_empty(ux, uy) {
-                            GameState::add_candidate(&mut mask, self.size, ux, uy);
+                            GameState::add_candidate(&mut mask, self.width, ux, uy);
                         }
                     }
                 }
             }
         }
 
-        for y in 0..self.size {
-            for x in 0..self.size {
-                match self.board.get_cell(x, y) {
-                    Some(Cell::MyStone) | Some(Cell::OpStone) => {
-                        for dy in -CANDIDATE_RADIUS..=CANDIDATE_RADIUS {
-                            for dx in -CANDIDATE_RADIUS..=CANDIDATE_RADIUS {
-                                let nx = x as isize + dx;
-                                let ny = y as isize + dy;
-                                if nx < 0
-                                    || ny < 0
-                                    || nx >= self.size as isize
-                                    || ny >= self.size as isize
-                                {
-                                    continue;
-                                }
-                                let ux = nx as usize;
-                                let uy = ny as usize;
-                                if self.board.is_empty(ux, uy) {
-                                    GameState::add_candidate(&mut mask, self.size, ux, uy);
-                                }
-                            }
-                        }
+        let radius = self.config.adjacency_radius as isize;
+        for (x, y) in self.board.occupied() {
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let nx = x as isize + dx;
+                    let ny = y as isize + dy;
+                    if nx < 0 || ny < 0 || nx >= self.width as isize || ny >= self.height as isize {
+                        continue;
+                    }
+                    let ux = nx as usize;
+                    let uy = ny as usize;
+                    if self.board.is_empty(ux, uy) {
+                        GameState::add_candidate(&mut mask, self.width, ux, uy);
                     }
-                    _ => {}
                 }
             }
         }
 
         let mut candidates = Vec::new();
-        for y in 0..self.size {
-            for x in 0..self.size {
-                if mask[y * self.size + x] {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if mask[y * self.width + x] {
                     candidates.push((x, y));
                 }
             }
@@ -579,11 +1466,13 @@ impl GameState {
             candidates = self.board.iter_empty().collect();
         }
 
-        let mut scored: Vec<(usize, usize, i32, usize)> = candidates
+        let mut scored: Vec<(usize, usize, i32, usize, bool)> = candidates
             .into_iter()
             .map(|(x, y)| {
                 let my_threats = self.detect_threats(x, y, Cell::MyStone);
                 let opp_threats = self.detect_threats(x, y, Cell::OpStone);
+                wins.note(Cell::MyStone, &my_threats);
+                wins.note(Cell::OpStone, &opp_threats);
 
                 let mut score = my_threats.score();
                 if opp_threats.is_winning() {
@@ -597,7 +1486,8 @@ impl GameState {
                     let bonus = (4_i32 - center_dist as i32).max(0);
                     score += bonus;
                 }
-                (x, y, score, center_dist)
+                let tactical = my_threats.makes_four() || opp_threats.makes_four();
+                (x, y, score, center_dist, tactical)
             })
             .collect();
 
@@ -608,11 +1498,13 @@ impl GameState {
                 .then_with(|| a.0.cmp(&b.0))
         });
 
-        if scored.len() > CANDIDATE_CAP {
-            scored.truncate(CANDIDATE_CAP);
-        }
-
-        scored.into_iter().map(|(x, y, _, _)| (x, y)).collect()
+        let cap = adaptive_candidate_cap(
+            search_plan(total_stones).candidate_cap,
+            self.move_time_budget(),
+            scored.iter().map(|c| c.2),
+        );
+        cap_candidates(&mut scored, cap, |c| c.4);
+        (scored.into_iter().map(|(x, y, ..)| (x, y)).collect(), wins)
     }
 
     fn find_immediate_win(&mut self, player: Cell) -> Option<(usize, usize)> {
@@ -620,7 +1512,7 @@ impl GameState {
 
         for (x, y) in candidates {
             self.place_stone(x, y, player);
-            let is_win = self.board.check_five_in_a_row(player);
+            let is_win = self.board.check_win(player, self.exact_five_for(player));
             self.remove_stone(x, y);
 
             if is_win {
@@ -631,270 +1523,825 @@ impl GameState {
         None
     }
 
-    fn generate_move(&mut self) -> String {
-        let move_coords = self
-            .find_immediate_win(Cell::MyStone)
-            .or_else(|| self.find_immediate_win(Cell::OpStone))
-            .or_else(|| self.find_best_move())
-            .or_else(|| self.fallback_move())
-            .or_else(|| self.any_empty_cell());
-
-        if let Some((x, y)) = move_coords {
-            self.place_stone(x, y, Cell::MyStone);
-
-            if self.game_over().is_some() {
-                self.game_in_progress = false;
+    fn find_vcf(
+        &mut self,
+        player: Cell,
+        max_depth: usize,
+        deadline: Instant,
+    ) -> Option<(usize, usize)> {
+        let candidates = self.generate_candidates();
+        let five = candidates
+            .iter()
+            .copied()
+            .find(|&(x, y)| self.detect_threats(x, y, player).fives > 0);
+        let opponent = player.opponent();
+        let found = match five {
+            Some(win) => Some((win, 1)),
+            None if candidates
+                .iter()
+                .any(|&(x, y)| self.detect_threats(x, y, opponent).fives > 0) =>
+            {
+                None
             }
+            None => {
+                let mut nodes = 0;
+                (1..=max_depth)
+                    .find_map(|depth| self.vcf_search(player, depth, deadline, &mut nodes))
+            }
+        };
 
-            return format!("{},{}", x, y);
-        }
-
-        self.emergency_move()
+        let (best_move, moves) = found?;
+        let plies = 2 * moves - 1;
+        self.last_search = Some(SearchReport {
+            best_move,
+            score: SCORE_WIN - plies as i32,
+            depth: plies,
+        });
+        Some(best_move)
     }
 
-    fn any_empty_cell(&self) -> Option<(usize, usize)> {
-        self.board.iter_empty().next()
-    }
+    fn vcf_search(
+        &mut self,
+        player: Cell,
+        depth: usize,
+        deadline: Instant,
+        nodes: &mut usize,
+    ) -> Option<((usize, usize), usize)> {
+        if depth == 0 || *nodes >= VCF_NODE_LIMIT || self.out_of_time(deadline) {
+            return None;
+        }
 
-    fn fallback_move(&self) -> Option<(usize, usize)> {
+        let opponent = player.opponent();
         for (x, y) in self.generate_candidates() {
-            if self.validate_move(x, y).is_ok() {
-                return Some((x, y));
+            if self.validate_move_for(x, y, player).is_err() {
+                continue;
             }
-        }
+            let threats = self.detect_threats(x, y, player);
+            if threats.open_fours + threats.closed_fours == 0 {
+                continue;
+            }
+            *nodes += 1;
 
-        self.board
-            .iter_empty()
-            .find(|&(x, y)| self.validate_move(x, y).is_ok())
-    }
+            self.place_stone(x, y, player);
+            let moves = match self.fives_through(x, y, player)[..] {
+                [] => None,
+                [(bx, by)] => {
+                    self.place_stone(bx, by, opponent);
+                    let moves = if self.fives_through(bx, by, opponent).is_empty() {
+                        self.vcf_search(player, depth - 1, deadline, nodes)
+                            .map(|(_, moves)| moves + 1)
+                    } else {
+                        None
+                    };
+                    self.remove_stone(bx, by);
+                    moves
+                }
+                _ => Some(2),
+            };
+            self.remove_stone(x, y);
 
-    pub fn emergency_move(&self) -> String {
-        if let Some((x, y)) = self.board.iter_empty().next() {
-            return format!("{},{}", x, y);
+            if let Some(moves) = moves {
+                return Some(((x, y), moves));
+            }
         }
-        for y in 0..20 {
-            for x in 0..20 {
-                if self.board.get_cell(x, y) == Some(Cell::Empty) {
-                    return format!("{},{}", x, y);
+        None
+    }
+
+    fn fives_through(&self, x: usize, y: usize, player: Cell) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        for &(dx, dy) in &DIRECTIONS {
+            for k in (-4..=4).filter(|&k| k != 0) {
+                let (nx, ny) = (x as isize + dx * k, y as isize + dy * k);
+                if !self.is_empty_at((nx, ny)) {
+                    continue;
+                }
+                let cell = (nx as usize, ny as usize);
+                if !cells.contains(&cell) && self.detect_threats(cell.0, cell.1, player).fives > 0 {
+                    cells.push(cell);
                 }
             }
         }
-        "10,10".to_string()
+        cells
     }
 
-    fn generate_forcing_moves(&self, player: Cell) -> Vec<(usize, usize)> {
-        let candidates = self.generate_candidates();
-        let mut forcing = Vec::new();
-
-        for (x, y) in candidates {
-            let my_threats = self.detect_threats(x, y, player);
-            let opp = if player == Cell::MyStone {
-                Cell::OpStone
-            } else {
-                Cell::MyStone
-            };
-            let opp_threats = self.detect_threats(x, y, opp);
+    fn solve_endgame(&mut self, deadline: Instant) -> Option<(usize, usize)> {
+        let empty: Vec<(usize, usize)> = self.board.iter_empty().collect();
+        if empty.len() > ENDGAME_EMPTY_CELLS {
+            return None;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let deadline = Instant::now() + remaining / ENDGAME_TIME_DIVISOR;
 
-            if my_threats.open_fours >= 1
-                || my_threats.closed_fours >= 1
-                || my_threats.open_threes >= 2
-                || opp_threats.open_fours >= 1
-                || opp_threats.closed_fours >= 1
-            {
-                forcing.push((x, y));
+        let mut best = None;
+        let mut alpha = MIN_EVAL_SCORE;
+        for (x, y) in empty.iter().copied() {
+            if self.validate_move_for(x, y, Cell::MyStone).is_err() {
+                continue;
+            }
+            self.place_stone(x, y, Cell::MyStone);
+            let result = self.solve(-MAX_EVAL_SCORE, -alpha, Cell::OpStone, deadline, 1);
+            self.remove_stone(x, y);
+            let value = -result?;
+            if value > alpha {
+                alpha = value;
+                best = Some((x, y));
             }
         }
-        forcing
+        let best_move = best?;
+        self.last_search = Some(SearchReport {
+            best_move,
+            score: alpha,
+            depth: empty.len(),
+        });
+        Some(best_move)
     }
 
-    fn quiescence(
+    fn solve(
         &mut self,
         mut alpha: i32,
         beta: i32,
         player: Cell,
         deadline: Instant,
-        qdepth: usize,
+        ply: usize,
     ) -> Option<i32> {
-        if Instant::now() >= deadline {
+        if self.out_of_time(deadline) {
             return None;
         }
+        self.nodes += 1;
+        if let Some(score) = self.terminal_score(player, ply) {
+            return Some(score);
+        }
 
-        if let Some(winner) = self.game_over() {
-            match winner {
-                Cell::MyStone => {
-                    return Some(if player == Cell::MyStone {
-                        SCORE_WIN
-                    } else {
-                        -SCORE_WIN
-                    });
-                }
-                Cell::OpStone => {
-                    return Some(if player == Cell::OpStone {
-                        SCORE_WIN
-                    } else {
-                        -SCORE_WIN
-                    });
-                }
-                Cell::Empty => return Some(0),
+        let hash = self.compute_hash_with_turn(player);
+        if let Some(entry) = self.tt.probe(hash).filter(|e| e.depth == SOLVED_DEPTH) {
+            let score = score_from_tt(entry.score, ply);
+            match entry.flag {
+                TTFlag::Exact => return Some(score),
+                TTFlag::LowerBound if score >= beta => return Some(score),
+                TTFlag::UpperBound if score <= alpha => return Some(score),
                 _ => {}
             }
         }
 
-        let stand_pat = self.evaluate_position();
-        let stand_pat = if player == Cell::MyStone {
-            stand_pat
+        let original_alpha = alpha;
+        let mut best_value = MIN_EVAL_SCORE;
+        let mut best_move = None;
+        let empty: Vec<(usize, usize)> = self.board.iter_empty().collect();
+        for (x, y) in empty {
+            if self.validate_move_for(x, y, player).is_err() {
+                continue;
+            }
+            self.place_stone(x, y, player);
+            let result = self.solve(-beta, -alpha, player.opponent(), deadline, ply + 1);
+            self.remove_stone(x, y);
+            let value = -result?;
+            if value > best_value {
+                best_value = value;
+                best_move = Some((x, y));
+            }
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        if best_move.is_none() {
+            return Some(0);
+        }
+
+        let flag = if best_value <= original_alpha {
+            TTFlag::UpperBound
+        } else if best_value >= beta {
+            TTFlag::LowerBound
         } else {
-            -stand_pat
+            TTFlag::Exact
         };
+        self.tt.store(
+            hash,
+            SOLVED_DEPTH,
+            score_to_tt(best_value, ply),
+            flag,
+            best_move,
+        );
+        Some(best_value)
+    }
 
-        if stand_pat >= beta {
-            return Some(beta);
-        }
-        if stand_pat > alpha {
-            alpha = stand_pat;
+    fn find_vct(
+        &mut self,
+        player: Cell,
+        max_depth: usize,
+        deadline: Instant,
+    ) -> Option<(usize, usize)> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining < VCT_MIN_TIME {
+            return None;
         }
+        let deadline = Instant::now() + remaining / VCT_TIME_DIVISOR;
+        let mut nodes = 0;
+        (1..=max_depth).find_map(|depth| self.vct_attack(player, depth, deadline, &mut nodes))
+    }
 
-        if qdepth == 0 {
-            return Some(alpha);
+    fn vct_attack(
+        &mut self,
+        player: Cell,
+        depth: usize,
+        deadline: Instant,
+        nodes: &mut usize,
+    ) -> Option<(usize, usize)> {
+        if depth == 0 || *nodes >= VCT_NODE_LIMIT || self.out_of_time(deadline) {
+            return None;
         }
 
-        let forcing = self.generate_forcing_moves(player);
-        if forcing.is_empty() {
-            return Some(alpha);
+        let candidates = self.generate_candidates();
+        if let Some(&win) = candidates
+            .iter()
+            .find(|&&(x, y)| self.detect_threats(x, y, player).fives > 0)
+        {
+            return Some(win);
+        }
+        let opponent = player.opponent();
+        let forced: Vec<(usize, usize)> = candidates
+            .iter()
+            .copied()
+            .filter(|&(x, y)| self.detect_threats(x, y, opponent).fives > 0)
+            .collect();
+        if forced.len() > 1 {
+            return None;
         }
+        let attacks = if forced.is_empty() {
+            candidates
+        } else {
+            forced
+        };
 
-        for (x, y) in forcing {
-            if self.validate_move(x, y).is_err() {
+        for (x, y) in attacks {
+            let threats = self.detect_threats(x, y, player);
+            if !threats.makes_four() && threats.open_threes == 0 {
+                continue;
+            }
+            if self.validate_move_for(x, y, player).is_err() {
                 continue;
             }
+            *nodes += 1;
 
             self.place_stone(x, y, player);
-            let next_player = if player == Cell::MyStone {
-                Cell::OpStone
-            } else {
-                Cell::MyStone
-            };
-            let score = -self.quiescence(-beta, -alpha, next_player, deadline, qdepth - 1)?;
+            let wins = self.vct_defend(player, depth - 1, deadline, nodes);
             self.remove_stone(x, y);
-
-            if score >= beta {
-                return Some(beta);
-            }
-            if score > alpha {
-                alpha = score;
+            if wins {
+                return Some((x, y));
             }
         }
-
-        Some(alpha)
+        None
     }
 
-    fn negamax(
+    fn vct_defend(
         &mut self,
+        attacker: Cell,
         depth: usize,
+        deadline: Instant,
+        nodes: &mut usize,
+    ) -> bool {
+        let defender = attacker.opponent();
+        let candidates = self.generate_candidates();
+        if candidates
+            .iter()
+            .any(|&(x, y)| self.detect_threats(x, y, defender).fives > 0)
+        {
+            return false;
+        }
+        let fives: Vec<(usize, usize)> = candidates
+            .iter()
+            .copied()
+            .filter(|&(x, y)| self.detect_threats(x, y, attacker).fives > 0)
+            .collect();
+        if fives.len() > 1 {
+            return true;
+        }
+
+        let replies = if fives.is_empty() {
+            let open_fours: Vec<(usize, usize)> = candidates
+                .iter()
+                .copied()
+                .filter(|&(x, y)| self.detect_threats(x, y, attacker).open_fours > 0)
+                .collect();
+            if open_fours.is_empty() {
+                return false;
+            }
+            candidates
+                .into_iter()
+                .filter(|&(x, y)| {
+                    self.detect_threats(x, y, defender).makes_four()
+                        || self.stops_open_fours(x, y, defender, &open_fours)
+                })
+                .collect()
+        } else {
+            fives
+        };
+
+        for (x, y) in replies {
+            if self.validate_move_for(x, y, defender).is_err() {
+                continue;
+            }
+            self.place_stone(x, y, defender);
+            let wins = self.vct_attack(attacker, depth, deadline, nodes).is_some();
+            self.remove_stone(x, y);
+            if !wins {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn stops_open_fours(
+        &mut self,
+        x: usize,
+        y: usize,
+        defender: Cell,
+        open_fours: &[(usize, usize)],
+    ) -> bool {
+        let attacker = defender.opponent();
+        self.place_stone(x, y, defender);
+        let stopped = open_fours
+            .iter()
+            .filter(|&&cell| cell != (x, y))
+            .all(|&(fx, fy)| self.detect_threats(fx, fy, attacker).open_fours == 0);
+        self.remove_stone(x, y);
+        stopped
+    }
+
+    fn find_fork_block(&self) -> Option<(usize, usize)> {
+        let candidates = self.generate_candidates();
+        if candidates
+            .iter()
+            .any(|&(x, y)| self.detect_threats(x, y, Cell::MyStone).is_winning())
+        {
+            return None;
+        }
+
+        let mut best: Option<((usize, usize), i32, i32)> = None;
+        for (x, y) in candidates {
+            let threats = self.detect_threats(x, y, Cell::OpStone);
+            if threats.threat_count() < 2 {
+                continue;
+            }
+            let severity = threats.score();
+            let own = self.detect_threats(x, y, Cell::MyStone).score();
+            if best.map_or(true, |(_, s, o)| (severity, own) > (s, o)) {
+                best = Some(((x, y), severity, own));
+            }
+        }
+        best.map(|(pos, _, _)| pos)
+    }
+
+    fn search_against_fork(&mut self, deadline: Instant) -> Option<(usize, usize)> {
+        let fork = self.find_fork_block();
+        self.root_hint = fork;
+        let searched = self.find_best_move(deadline);
+        self.root_hint = None;
+        match (fork, searched) {
+            (Some(fork), Some(mv)) if !self.search_proved_win() && !self.meets_fork(mv, fork) => {
+                debug!("search move {:?} leaves the fork at {:?}", mv, fork);
+                self.last_search = None;
+                Some(fork)
+            }
+            _ => searched.or(fork),
+        }
+    }
+
+    fn search_proved_win(&self) -> bool {
+        self.last_search
+            .is_some_and(|r| r.score >= SCORE_WIN - MAX_SEARCH_PLY as i32)
+    }
+
+    fn meets_fork(&mut self, mv: (usize, usize), fork: (usize, usize)) -> bool {
+        if mv == fork || self.detect_threats(mv.0, mv.1, Cell::MyStone).makes_four() {
+            return true;
+        }
+        self.place_stone(mv.0, mv.1, Cell::MyStone);
+        let fork_stands = self
+            .detect_threats(fork.0, fork.1, Cell::OpStone)
+            .is_winning();
+        self.remove_stone(mv.0, mv.1);
+        !fork_stands
+    }
+
+    pub fn handle_suggest(&mut self) -> String {
+        if let Err(e) = self.ensure_initialized() {
+            return error_response(e);
+        }
+        match self.choose_move() {
+            Some((x, y)) => format!("SUGGEST {},{}", x, y),
+            None => "ERROR no move available".to_string(),
+        }
+    }
+
+    pub fn load_position(&mut self, board: &Board, player: Cell) -> Result<(), GameError> {
+        if !player.is_stone() {
+            return Err(GameError::NotAStone);
+        }
+        let fresh =
+            self.sized_board(board.width(), board.height())
+                .ok_or(GameError::UnsupportedSize {
+                    width: board.width(),
+                    height: board.height(),
+                })?;
+        self.start_with(fresh);
+        self.game_in_progress = true;
+        for (x, y) in board.iter_indices() {
+            let cell = match board.get_cell(x, y) {
+                Some(cell) if player == Cell::OpStone => cell.opponent(),
+                Some(cell) => cell,
+                None => continue,
+            };
+            if cell.is_stone() {
+                self.push_move(x, y, cell);
+            } else if cell == Cell::Forbidden {
+                self.place_stone(x, y, cell);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn best_move(&mut self) -> Option<(usize, usize)> {
+        self.choose_move()
+    }
+
+    fn generate_move(&mut self) -> String {
+        if self.board.is_full() {
+            debug!("asked to move on a full board");
+            self.pending_move = None;
+            self.game_in_progress = false;
+            return BOARD_FULL.to_string();
+        }
+        let move_coords = self.choose_move();
+        if let Some((x, y)) = move_coords {
+            self.push_move(x, y, Cell::MyStone);
+            self.settle_if_over();
+
+            self.pending_move = None;
+            let mut reply = format!("{},{}", x, y);
+            if self.config.analysis {
+                let pv = self
+                    .principal_variation((x, y))
+                    .iter()
+                    .map(|(px, py)| format!("{},{}", px, py))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                reply = format!(
+                    "{}\nMESSAGE pv {}\n{}",
+                    self.analysis_message(x, y),
+                    pv,
+                    reply
+                );
+            }
+            if self.update_resign_state(x, y) {
+                reply = format!("MESSAGE resign-eval\n{}", reply);
+            }
+            return reply;
+        }
+
+        self.pending_move = None;
+        self.emergency_move()
+    }
+
+    fn choose_move(&mut self) -> Option<(usize, usize)> {
+        let deadline = Instant::now() + self.move_time_budget();
+        self.last_search = None;
+        self.move_cache.clear();
+        let move_coords = match self.find_immediate_win(Cell::MyStone) {
+            Some(win) => {
+                self.last_search = Some(SearchReport {
+                    best_move: win,
+                    score: SCORE_WIN - 1,
+                    depth: 1,
+                });
+                Some(win)
+            }
+            None if self.config.engine == EngineKind::Greedy => self
+                .find_immediate_win(Cell::OpStone)
+                .or_else(|| self.fallback_move()),
+            None => self
+                .find_immediate_win(Cell::OpStone)
+                .or_else(|| self.solve_endgame(deadline))
+                .or_else(|| self.book_move())
+                .or_else(|| self.find_vcf(Cell::MyStone, VCF_MAX_DEPTH, deadline))
+                .or_else(|| self.find_vct(Cell::MyStone, VCT_MAX_DEPTH, deadline))
+                .or_else(|| self.search_against_fork(deadline))
+                .or_else(|| self.fallback_move())
+                .or_else(|| self.any_empty_cell()),
+        };
+        move_coords
+            .filter(|&(x, y)| self.validate_move_for(x, y, Cell::MyStone).is_ok())
+            .or_else(|| self.fallback_move())
+    }
+
+    fn book_move(&self) -> Option<(usize, usize)> {
+        if self.config.opening_book {
+            opening::book_move(&self.board)
+        } else {
+            None
+        }
+    }
+
+    fn played_move_score(&self, x: usize, y: usize) -> (i32, usize) {
+        match self.last_search {
+            Some(report) if report.best_move == (x, y) => (report.score, report.depth),
+            _ => (self.static_score(Cell::MyStone), 0),
+        }
+    }
+
+    fn update_resign_state(&mut self, x: usize, y: usize) -> bool {
+        let Some(threshold) = self.config.resign_threshold else {
+            return false;
+        };
+        let (score, _) = self.played_move_score(x, y);
+        if score > -threshold {
+            self.losing_streak = 0;
+            return false;
+        }
+        self.losing_streak += 1;
+        if self.fast_play || self.losing_streak < RESIGN_PATIENCE {
+            return false;
+        }
+        self.fast_play = true;
+        true
+    }
+
+    fn analysis_message(&self, x: usize, y: usize) -> String {
+        let (score, depth) = self.played_move_score(x, y);
+        format!(
+            "MESSAGE move={},{} score={} depth={}",
+            x,
+            y,
+            format_score(score),
+            depth
+        )
+    }
+
+    fn principal_variation(&mut self, played: (usize, usize)) -> Vec<(usize, usize)> {
+        let mut pv = vec![played];
+        let mut visited = Vec::with_capacity(MAX_PV_LENGTH);
+        let mut player = Cell::OpStone;
+
+        while pv.len() < MAX_PV_LENGTH && self.game_over().is_none() {
+            let hash = self.compute_hash_with_turn(player);
+            if visited.contains(&hash) {
+                break;
+            }
+            visited.push(hash);
+            let Some((x, y)) = self
+                .tt
+                .probe(hash)
+                .and_then(|entry| entry.best_move)
+                .map(|(x, y)| (x as usize, y as usize))
+            else {
+                break;
+            };
+            if self.validate_move_for(x, y, player).is_err() {
+                break;
+            }
+            self.place_stone(x, y, player);
+            pv.push((x, y));
+            player = player.opponent();
+        }
+
+        for &(x, y) in pv[1..].iter().rev() {
+            self.remove_stone(x, y);
+        }
+        pv
+    }
+
+    fn any_empty_cell(&self) -> Option<(usize, usize)> {
+        self.board.iter_empty().next()
+    }
+
+    fn fallback_move(&self) -> Option<(usize, usize)> {
+        for (x, y) in self.generate_candidates() {
+            if self.validate_move_for(x, y, Cell::MyStone).is_ok() {
+                return Some((x, y));
+            }
+        }
+
+        self.board
+            .iter_empty()
+            .find(|&(x, y)| self.validate_move_for(x, y, Cell::MyStone).is_ok())
+    }
+
+    pub fn emergency_move(&self) -> String {
+        match self.board.iter_empty().next() {
+            Some((x, y)) => format!("{},{}", x, y),
+            None => BOARD_FULL.to_string(),
+        }
+    }
+
+    fn generate_forcing_moves(&mut self, player: Cell) -> ForcingMoves {
+        let candidates = self.cached_candidates();
+        let opp = player.opponent();
+        let mut forcing = ForcingMoves::default();
+
+        for (x, y) in candidates {
+            let my_threats = self.detect_threats(x, y, player);
+            let opp_threats = self.detect_threats(x, y, opp);
+
+            forcing.can_win |= my_threats.fives > 0;
+            if opp_threats.fives > 0 {
+                forcing.opponent_fives.push((x, y));
+            }
+            if my_threats.open_fours >= 1
+                || my_threats.closed_fours >= 1
+                || my_threats.open_threes >= 2
+                || opp_threats.open_fours >= 1
+                || opp_threats.closed_fours >= 1
+            {
+                forcing.moves.push((x, y));
+            }
+        }
+        forcing
+    }
+
+    fn terminal_score(&self, player: Cell, ply: usize) -> Option<i32> {
+        match self.game_over()? {
+            GameResult::Draw => Some(0),
+            GameResult::Win(winner) if winner == player => Some(SCORE_WIN - ply as i32),
+            GameResult::Win(_) => Some(-(SCORE_WIN - ply as i32)),
+        }
+    }
+
+    /// Static evaluation from `player`'s point of view, kept clear of the
+    /// mate range so it is never mistaken for a proven result.
+    fn static_score(&self, player: Cell) -> i32 {
+        let eval = self.evaluate_position();
+        let eval = if player == Cell::MyStone { eval } else { -eval };
+        eval.clamp(-MATE_THRESHOLD + 1, MATE_THRESHOLD - 1)
+    }
+
+    fn forced_win_score(wins: WinningMoves, player: Cell, ply: usize) -> Option<i32> {
+        let side = IncrementalScores::player_index(player);
+        if wins.fives[side] {
+            return Some(SCORE_WIN - (ply as i32 + 1));
+        }
+        if !wins.open_fours[side] || wins.fives[1 - side] {
+            return None;
+        }
+        Some(SCORE_WIN - (ply as i32 + 3))
+    }
+
+    fn quiescence(
+        &mut self,
         mut alpha: i32,
         beta: i32,
         player: Cell,
         deadline: Instant,
+        qdepth: usize,
+        ply: usize,
     ) -> Option<i32> {
-        if Instant::now() >= deadline {
+        if self.out_of_time(deadline) {
             return None;
         }
+        self.nodes += 1;
 
-        if let Some(winner) = self.game_over() {
-            match winner {
-                Cell::MyStone => {
-                    return Some(if player == Cell::MyStone {
-                        SCORE_WIN
-                    } else {
-                        -SCORE_WIN
-                    });
+        if let Some(score) = self.terminal_score(player, ply) {
+            return Some(score);
+        }
+
+        let forcing = self.generate_forcing_moves(player);
+        if forcing.can_win {
+            return Some(SCORE_WIN - (ply as i32 + 1));
+        }
+        if forcing.opponent_fives.len() >= 2 {
+            return Some(-(SCORE_WIN - (ply as i32 + 2)));
+        }
+
+        let stand_pat = self.static_score(player);
+        if qdepth == 0 || ply >= MAX_SEARCH_PLY {
+            return Some(stand_pat.clamp(alpha, beta));
+        }
+
+        // Facing a four there is no quiet option: the block is the only move.
+        let moves = match forcing.opponent_fives[..] {
+            [block] => vec![block],
+            _ => {
+                if stand_pat >= beta {
+                    return Some(beta);
                 }
-                Cell::OpStone => {
-                    return Some(if player == Cell::OpStone {
-                        SCORE_WIN
-                    } else {
-                        -SCORE_WIN
-                    });
+                if stand_pat > alpha {
+                    alpha = stand_pat;
                 }
-                Cell::Empty => return Some(0),
-                _ => {}
+                forcing.moves
             }
+        };
+
+        for (x, y) in moves {
+            if self.validate_move_for(x, y, player).is_err() {
+                continue;
+            }
+
+            self.place_stone(x, y, player);
+            let result = self.quiescence(
+                -beta,
+                -alpha,
+                player.opponent(),
+                deadline,
+                qdepth - 1,
+                ply + 1,
+            );
+            self.remove_stone(x, y);
+            let score = -result?;
+
+            if score >= beta {
+                return Some(beta);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+
+        Some(alpha)
+    }
+
+    fn negamax(
+        &mut self,
+        depth: usize,
+        mut alpha: i32,
+        beta: i32,
+        player: Cell,
+        deadline: Instant,
+        ply: usize,
+    ) -> Option<i32> {
+        if self.out_of_time(deadline) {
+            return None;
+        }
+        self.nodes += 1;
+
+        if let Some(score) = self.terminal_score(player, ply) {
+            return Some(score);
         }
 
         if depth == 0 {
-            return self.quiescence(alpha, beta, player, deadline, MAX_QUIESCENCE_DEPTH);
+            return self.quiescence(alpha, beta, player, deadline, MAX_QUIESCENCE_DEPTH, ply);
         }
 
         let hash = self.compute_hash_with_turn(player);
+        // Every move adds a stone, so a position already on the current line
+        // can only come back through a hash collision or a corrupt table
+        // move; it scores as neutral rather than being searched again.
+        if self.search_path.contains(&hash) {
+            return Some(0);
+        }
         let original_alpha = alpha;
 
+        let mut tt_move = None;
         if let Some(entry) = self.tt.probe(hash) {
+            tt_move = entry.best_move;
             if entry.depth as usize >= depth {
+                let score = score_from_tt(entry.score, ply);
                 match entry.flag {
-                    TTFlag::Exact => return Some(entry.score),
-                    TTFlag::LowerBound => {
-                        if entry.score > alpha {
-                            alpha = entry.score;
-                        }
-                    }
-                    TTFlag::UpperBound => {
-                        if entry.score < beta {
-                            return Some(entry.score);
-                        }
-                    }
-                }
-                if alpha >= beta {
-                    return Some(entry.score);
+                    TTFlag::Exact => return Some(score),
+                    TTFlag::LowerBound if score >= beta => return Some(score),
+                    TTFlag::UpperBound if score <= alpha => return Some(score),
+                    _ => {}
                 }
             }
         }
 
-        let mut candidates = self.generate_candidates();
+        let (mut candidates, wins) = self.cached_scan();
         if candidates.is_empty() {
-            let eval = self.evaluate_position();
-            return Some(if player == Cell::MyStone { eval } else { -eval });
+            return Some(self.static_score(player));
         }
 
-        let tt_move = self.tt.probe(hash).and_then(|e| e.best_move);
-        let player_idx = if player == Cell::MyStone { 0 } else { 1 };
-
-        candidates.sort_by(|&(ax, ay), &(bx, by)| {
-            let a_is_tt = tt_move == Some((ax as u8, ay as u8));
-            let b_is_tt = tt_move == Some((bx as u8, by as u8));
-            if a_is_tt != b_is_tt {
-                return b_is_tt.cmp(&a_is_tt);
-            }
-
-            let a_is_killer = depth < MAX_SEARCH_DEPTH
-                && (self.killer_moves[depth][0] == Some((ax, ay))
-                    || self.killer_moves[depth][1] == Some((ax, ay)));
-            let b_is_killer = depth < MAX_SEARCH_DEPTH
-                && (self.killer_moves[depth][0] == Some((bx, by))
-                    || self.killer_moves[depth][1] == Some((bx, by)));
-            if a_is_killer != b_is_killer {
-                return b_is_killer.cmp(&a_is_killer);
-            }
+        if let Some(score) = Self::forced_win_score(wins, player, ply) {
+            return Some(score);
+        }
 
-            let a_hist = self.history[player_idx][ay * BOARD_SIZE + ax];
-            let b_hist = self.history[player_idx][by * BOARD_SIZE + bx];
-            b_hist.cmp(&a_hist)
-        });
+        self.order_moves(&mut candidates, tt_move, ply, player);
 
+        let next_player = player.opponent();
         let mut best_value = MIN_EVAL_SCORE;
         let mut best_move = None;
-        for (x, y) in candidates {
-            if self.validate_move(x, y).is_err() {
+        self.search_path.push(hash);
+        for (index, (x, y)) in candidates.into_iter().enumerate() {
+            if self.validate_move_for(x, y, player).is_err() {
                 continue;
             }
 
+            let reduce = depth >= LMR_MIN_DEPTH
+                && index >= LMR_FULL_DEPTH_MOVES
+                && best_move.is_some()
+                && !self.is_forcing_move(x, y, player);
+
             self.place_stone(x, y, player);
-            let next_player = if player == Cell::MyStone {
-                Cell::OpStone
+            let result = if best_move.is_none() {
+                self.negamax(depth - 1, -beta, -alpha, next_player, deadline, ply + 1)
+            } else if reduce {
+                self.reduced_search(depth, alpha, beta, next_player, deadline, ply + 1)
             } else {
-                Cell::MyStone
+                self.null_window_search(depth - 1, alpha, beta, next_player, deadline, ply + 1)
             };
-            let value = -self.negamax(depth - 1, -beta, -alpha, next_player, deadline)?;
             self.remove_stone(x, y);
+            let Some(result) = result else {
+                self.search_path.pop();
+                return None;
+            };
+            let value = -result;
 
             if value > best_value {
                 best_value = value;
@@ -902,20 +2349,14 @@ impl GameState {
             }
             if value > alpha {
                 alpha = value;
+                self.record_history(player, (x, y), depth);
             }
             if alpha >= beta {
-                if depth < MAX_SEARCH_DEPTH {
-                    if self.killer_moves[depth][0] != Some((x, y)) {
-                        self.killer_moves[depth][1] = self.killer_moves[depth][0];
-                        self.killer_moves[depth][0] = Some((x, y));
-                    }
-                    let player_idx = if player == Cell::MyStone { 0 } else { 1 };
-                    let idx = y * BOARD_SIZE + x;
-                    self.history[player_idx][idx] += (depth * depth) as i32;
-                }
+                self.record_killer(ply, (x, y));
                 break;
             }
         }
+        self.search_path.pop();
 
         let flag = if best_value <= original_alpha {
             TTFlag::UpperBound
@@ -925,100 +2366,331 @@ impl GameState {
             TTFlag::Exact
         };
 
-        self.tt
-            .store(hash, depth as u8, best_value, flag, best_move);
+        self.tt.store(
+            hash,
+            depth as u8,
+            score_to_tt(best_value, ply),
+            flag,
+            best_move,
+        );
 
         Some(best_value)
     }
 
-    #[inline]
-    fn compute_hash_with_turn(&self, player: Cell) -> u64 {
-        let mut hash = self.board.hash();
-        if player == Cell::OpStone {
-            hash ^= self.zobrist.turn_key();
-        }
-        hash
-    }
+    fn order_moves(
+        &self,
+        candidates: &mut [(usize, usize)],
+        tt_move: Option<(u8, u8)>,
+        ply: usize,
+        player: Cell,
+    ) {
+        let player_idx = if player == Cell::MyStone { 0 } else { 1 };
+        let killers = self.killer_moves.get(ply).copied().unwrap_or([None; 2]);
 
-    fn find_best_move(&mut self) -> Option<(usize, usize)> {
-        let mut candidates = self.generate_candidates();
-        if candidates.is_empty() {
-            return None;
-        }
+        candidates.sort_by_key(|&(x, y)| {
+            let is_tt = tt_move == Some((x as u8, y as u8));
+            let is_killer = killers.contains(&Some((x, y)));
+            let hist = self.history[player_idx][y * self.width + x];
+            (!is_tt, !is_killer, std::cmp::Reverse(hist))
+        });
+    }
 
-        let hash = self.compute_hash_with_turn(Cell::MyStone);
-        if let Some(entry) = self.tt.probe(hash) {
-            if let Some((tx, ty)) = entry.best_move {
-                let tx = tx as usize;
-                let ty = ty as usize;
-                if let Some(pos) = candidates.iter().position(|&(x, y)| x == tx && y == ty) {
-                    candidates.swap(0, pos);
-                }
+    fn record_killer(&mut self, ply: usize, mv: (usize, usize)) {
+        if let Some(slots) = self.killer_moves.get_mut(ply) {
+            if slots[0] != Some(mv) {
+                slots[1] = slots[0];
+                slots[0] = Some(mv);
             }
         }
+    }
 
-        let deadline = Instant::now() + TIME_BUDGET;
-        let mut best_move: Option<(usize, usize)> = None;
-
-        for depth in 1..=MAX_SEARCH_DEPTH {
-            if Instant::now() >= deadline {
-                break;
-            }
-
-            let mut depth_best_move = None;
-            let mut alpha = MIN_EVAL_SCORE;
-            let beta = MAX_EVAL_SCORE;
-            let mut search_completed = true;
+    fn record_history(&mut self, player: Cell, (x, y): (usize, usize), depth: usize) {
+        let player_idx = if player == Cell::MyStone { 0 } else { 1 };
+        let entry = &mut self.history[player_idx][y * self.width + x];
+        *entry = entry.saturating_add((depth * depth) as i32);
+    }
 
-            for (x, y) in &candidates {
-                if self.validate_move(*x, *y).is_err() {
-                    continue;
-                }
+    fn age_history(&mut self) {
+        for score in self.history.iter_mut().flatten() {
+            *score /= 2;
+        }
+    }
 
-                self.place_stone(*x, *y, Cell::MyStone);
-                let result = self.negamax(depth - 1, -beta, -alpha, Cell::OpStone, deadline);
-                self.remove_stone(*x, *y);
+    pub fn clear_heuristics(&mut self) {
+        self.killer_moves = [[None; 2]; MAX_SEARCH_DEPTH];
+        self.history = [[0; MAX_BOARD_CELLS]; 2];
+    }
 
-                match result {
-                    Some(child_value) => {
-                        let value = -child_value;
-                        if value > alpha {
-                            alpha = value;
-                            depth_best_move = Some((*x, *y));
-                        }
-                    }
-                    None => {
-                        search_completed = false;
-                        break;
-                    }
+    fn null_window_search(
+        &mut self,
+        depth: usize,
+        alpha: i32,
+        beta: i32,
+        player: Cell,
+        deadline: Instant,
+        ply: usize,
+    ) -> Option<i32> {
+        let probe = self.negamax(depth, -alpha - 1, -alpha, player, deadline, ply)?;
+        if -probe > alpha && -probe < beta {
+            self.negamax(depth, -beta, -alpha, player, deadline, ply)
+        } else {
+            Some(probe)
+        }
+    }
+
+    fn reduced_search(
+        &mut self,
+        depth: usize,
+        alpha: i32,
+        beta: i32,
+        player: Cell,
+        deadline: Instant,
+        ply: usize,
+    ) -> Option<i32> {
+        let probe = self.negamax(depth - 2, -alpha - 1, -alpha, player, deadline, ply)?;
+        if -probe > alpha {
+            self.null_window_search(depth - 1, alpha, beta, player, deadline, ply)
+        } else {
+            Some(probe)
+        }
+    }
+
+    fn is_forcing_move(&self, x: usize, y: usize, player: Cell) -> bool {
+        self.detect_threats(x, y, player).makes_four()
+            || self.detect_threats(x, y, player.opponent()).makes_four()
+    }
+
+    #[inline]
+    fn compute_hash_with_turn(&self, player: Cell) -> u64 {
+        let mut hash = self.board.hash();
+        if player == Cell::OpStone {
+            hash ^= self.zobrist.turn_key();
+        }
+        hash
+    }
+
+    fn find_best_move(&mut self, deadline: Instant) -> Option<(usize, usize)> {
+        #[cfg(test)]
+        if let Some(hook) = self.before_search {
+            hook(self);
+        }
+        let planned = search_plan(self.count_stones()).max_depth;
+        let max_depth = self.config.max_depth.min(planned);
+        #[cfg(feature = "parallel")]
+        if self.config.threads > 1 {
+            return self.parallel_search(deadline, max_depth);
+        }
+        self.search_to_depth(deadline, max_depth)
+    }
+
+    #[cfg(feature = "parallel")]
+    fn parallel_search(&mut self, deadline: Instant, max_depth: usize) -> Option<(usize, usize)> {
+        let stop = Arc::new(AtomicBool::new(false));
+        self.tt.new_search();
+        std::thread::scope(|scope| {
+            for helper in 1..self.config.threads {
+                let mut position = self.search_copy();
+                position.stop = Some(Arc::clone(&stop));
+                scope.spawn(move || position.deepen(deadline, 1 + helper % 2, max_depth));
+            }
+            let best = self.deepen(deadline, 1, max_depth);
+            stop.store(true, Ordering::Relaxed);
+            best
+        })
+    }
+
+    fn search_to_depth(&mut self, deadline: Instant, max_depth: usize) -> Option<(usize, usize)> {
+        self.tt.new_search();
+        self.deepen(deadline, 1, max_depth)
+    }
+
+    fn deepen(
+        &mut self,
+        deadline: Instant,
+        first_depth: usize,
+        max_depth: usize,
+    ) -> Option<(usize, usize)> {
+        let started = Instant::now();
+        self.nodes = 0;
+        self.search_path.clear();
+        self.age_history();
+        let mut candidates = canonical_moves(&self.board, self.generate_candidates());
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let hash = self.compute_hash_with_turn(Cell::MyStone);
+        if let Some(entry) = self.tt.probe(hash) {
+            if let Some((tx, ty)) = entry.best_move {
+                let tx = tx as usize;
+                let ty = ty as usize;
+                if let Some(pos) = candidates.iter().position(|&(x, y)| x == tx && y == ty) {
+                    candidates.swap(0, pos);
                 }
             }
+        }
+        if let Some(pos) = self
+            .root_hint
+            .and_then(|hint| candidates.iter().position(|&c| c == hint))
+        {
+            let hint = candidates.remove(pos);
+            candidates.insert(0, hint);
+        }
 
-            if search_completed {
-                best_move = depth_best_move;
-                if let Some((bx, by)) = depth_best_move {
-                    if let Some(pos) = candidates.iter().position(|&(x, y)| x == bx && y == by) {
-                        candidates.swap(0, pos);
-                    }
+        let mut best_move: Option<(usize, usize)> = None;
+        let mut previous_score = None;
+
+        for depth in first_depth..=max_depth.min(MAX_SEARCH_DEPTH) {
+            if self.out_of_time(deadline) {
+                break;
+            }
+
+            let Some((depth_best_move, score)) =
+                self.aspiration_search(depth, previous_score, &candidates, deadline)
+            else {
+                break;
+            };
+            best_move = depth_best_move;
+            previous_score = Some(score);
+            if let Some((bx, by)) = depth_best_move {
+                self.tt
+                    .store(hash, depth as u8, score, TTFlag::Exact, Some((bx, by)));
+                self.last_search = Some(SearchReport {
+                    best_move: (bx, by),
+                    score,
+                    depth,
+                });
+                if let Some(pos) = candidates.iter().position(|&(x, y)| x == bx && y == by) {
+                    candidates.swap(0, pos);
                 }
             }
         }
 
+        if debug::is_debug_enabled() {
+            self.log_search_summary(started);
+        }
         best_move
     }
 
+    fn aspiration_search(
+        &mut self,
+        depth: usize,
+        previous_score: Option<i32>,
+        candidates: &[(usize, usize)],
+        deadline: Instant,
+    ) -> Option<(Option<(usize, usize)>, i32)> {
+        let mut delta = ASPIRATION_DELTA;
+        let (mut alpha, mut beta) = match previous_score {
+            Some(score) if score.abs() < MATE_THRESHOLD => (
+                (score - delta).max(MIN_EVAL_SCORE),
+                (score + delta).min(MAX_EVAL_SCORE),
+            ),
+            _ => (MIN_EVAL_SCORE, MAX_EVAL_SCORE),
+        };
+
+        loop {
+            let (best, score) = self.search_root(depth, alpha, beta, candidates, deadline)?;
+            delta = delta.saturating_mul(2);
+            if best.is_none() && alpha > MIN_EVAL_SCORE {
+                alpha = (alpha - delta).max(MIN_EVAL_SCORE);
+            } else if score >= beta && beta < MAX_EVAL_SCORE {
+                beta = (beta + delta).min(MAX_EVAL_SCORE);
+            } else {
+                return Some((best, score));
+            }
+        }
+    }
+
+    fn search_root(
+        &mut self,
+        depth: usize,
+        mut alpha: i32,
+        beta: i32,
+        candidates: &[(usize, usize)],
+        deadline: Instant,
+    ) -> Option<(Option<(usize, usize)>, i32)> {
+        let mut best = None;
+        let mut searched_any = false;
+        for &(x, y) in candidates {
+            if self.validate_move_for(x, y, Cell::MyStone).is_err() {
+                continue;
+            }
+            if self.out_of_time(deadline) {
+                return None;
+            }
+
+            // A move preferred on a tie is searched one point lower, so an
+            // exact tie comes back as a score rather than a bound.
+            let floor = if best.is_some_and(|b| self.root_tie_key((x, y)) < self.root_tie_key(b)) {
+                alpha - 1
+            } else {
+                alpha
+            };
+            self.place_stone(x, y, Cell::MyStone);
+            let result = if !searched_any {
+                self.negamax(depth - 1, -beta, -alpha, Cell::OpStone, deadline, 1)
+            } else {
+                self.null_window_search(depth - 1, floor, beta, Cell::OpStone, deadline, 1)
+            };
+            self.remove_stone(x, y);
+            searched_any = true;
+
+            let value = -result?;
+            if value > alpha {
+                alpha = value;
+                best = Some((x, y));
+                if alpha >= beta {
+                    break;
+                }
+            } else if value == alpha && floor < alpha {
+                best = Some((x, y));
+            }
+        }
+        Some((best, alpha))
+    }
+
+    fn root_tie_key(&self, (x, y): (usize, usize)) -> (usize, usize, usize) {
+        (self.center_distance(x, y), y, x)
+    }
+
+    fn log_search_summary(&mut self, started: Instant) {
+        let Some(report) = self.last_search else {
+            debug!(
+                "search nodes={} time={}ms no completed iteration",
+                self.nodes,
+                started.elapsed().as_millis()
+            );
+            return;
+        };
+        let (x, y) = report.best_move;
+        self.place_stone(x, y, Cell::MyStone);
+        let pv = self.principal_variation((x, y));
+        self.remove_stone(x, y);
+        debug!(
+            "search depth={} score={} nodes={} movegen_hits={} time={}ms pv={}",
+            report.depth,
+            format_score(report.score),
+            self.nodes,
+            self.move_cache.hits,
+            started.elapsed().as_millis(),
+            pv.iter()
+                .map(|(px, py)| format!("{},{}", px, py))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+    }
+
     #[cfg(test)]
     fn evaluate(&self, player: Cell) -> i32 {
         let mut total_score = 0;
-        let directions = [(1, 0), (0, 1), (1, 1), (1, -1)];
 
-        for y in 0..self.size {
-            for x in 0..self.size {
+        for y in 0..self.height {
+            for x in 0..self.width {
                 if self.board.get_cell(x, y) != Some(player) {
                     continue;
                 }
 
-                for &(dx, dy) in &directions {
+                for &(dx, dy) in &DIRECTIONS {
                     total_score += self.evaluate_sequence(x, y, dx, dy, player);
                 }
             }
@@ -1028,92 +2700,57 @@ impl GameState {
     }
 
     fn evaluate_position(&self) -> i32 {
-        self.inc_scores.evaluate_position()
+        self.inc_scores.evaluate_position() + self.center_bonus()
     }
 
     #[cfg(test)]
     fn evaluate_position_full_scan(&self) -> i32 {
         let my_score = self.evaluate(Cell::MyStone);
         let opp_score = self.evaluate(Cell::OpStone);
-        my_score - opp_score
+        my_score - opp_score + self.center_bonus()
     }
 
-    #[cfg(test)]
-    fn evaluate_sequence(&self, x: usize, y: usize, dx: isize, dy: isize, player: Cell) -> i32 {
-        let mut forward_count = 0;
-        let mut backward_count = 0;
-
-        let mut nx = x as isize + dx;
-        let mut ny = y as isize + dy;
-        while nx >= 0 && ny >= 0 && nx < self.size as isize && ny < self.size as isize {
-            if self.board.get_cell(nx as usize, ny as usize) == Some(player) {
-                forward_count += 1;
-                nx += dx;
-                ny += dy;
-            } else {
-                break;
-            }
-        }
-
-        let forward_open = nx >= 0
-            && ny >= 0
-            && nx < self.size as isize
-            && ny < self.size as isize
-            && self.board.get_cell(nx as usize, ny as usize) == Some(Cell::Empty);
-
-        nx = x as isize - dx;
-        ny = y as isize - dy;
-        while nx >= 0 && ny >= 0 && nx < self.size as isize && ny < self.size as isize {
-            if self.board.get_cell(nx as usize, ny as usize) == Some(player) {
-                backward_count += 1;
-                nx -= dx;
-                ny -= dy;
-            } else {
-                break;
-            }
+    fn center_bonus(&self) -> i32 {
+        let stones = self.count_stones();
+        if stones >= CENTER_BONUS_STONES {
+            return 0;
         }
+        let total: i32 = self
+            .board
+            .occupied()
+            .map(|(x, y)| {
+                let rings = CENTER_BONUS_RINGS.saturating_sub(self.center_distance(x, y)) as i32;
+                let bonus = rings * CENTER_BONUS_PER_RING;
+                match self.board.get_cell(x, y) {
+                    Some(Cell::MyStone) => bonus,
+                    _ => -bonus,
+                }
+            })
+            .sum();
+        total * (CENTER_BONUS_STONES - stones) as i32 / CENTER_BONUS_STONES as i32
+    }
 
-        let backward_open = nx >= 0
-            && ny >= 0
-            && nx < self.size as isize
-            && ny < self.size as isize
-            && self.board.get_cell(nx as usize, ny as usize) == Some(Cell::Empty);
-
-        let total_count = forward_count + backward_count + 1;
-        let open_sides = if forward_open { 1 } else { 0 } + if backward_open { 1 } else { 0 };
+    #[cfg(test)]
+    fn evaluate_sequence(&self, x: usize, y: usize, dx: isize, dy: isize, player: Cell) -> i32 {
+        self.config
+            .eval_weights
+            .score(line_pattern(&self.board, x, y, (dx, dy), player))
+    }
 
-        if total_count >= 4 {
-            if open_sides == 2 {
-                SCORE_OPEN_FOUR
-            } else {
-                SCORE_CLOSED_FOUR
-            }
-        } else if total_count == 3 {
-            if open_sides == 2 {
-                SCORE_OPEN_THREE
-            } else {
-                SCORE_CLOSED_THREE
-            }
-        } else if total_count == 2 {
-            if open_sides == 2 {
-                SCORE_OPEN_TWO
-            } else {
-                SCORE_CLOSED_TWO
-            }
-        } else {
-            0
-        }
+    fn is_empty_at(&self, (x, y): (isize, isize)) -> bool {
+        x >= 0 && y >= 0 && self.board.is_empty(x as usize, y as usize)
     }
 
     fn detect_threats(&self, x: usize, y: usize, player: Cell) -> ThreatInfo {
         let mut info = ThreatInfo::default();
-        let size = self.size as isize;
+        let width = self.width as isize;
+        let height = self.height as isize;
 
         for &(dx, dy) in &DIRECTIONS {
             let mut forward_count = 0i32;
             let mut nx = x as isize + dx;
             let mut ny = y as isize + dy;
-            while nx >= 0 && ny >= 0 && nx < size && ny < size {
+            while nx >= 0 && ny >= 0 && nx < width && ny < height {
                 if self.board.get_cell(nx as usize, ny as usize) == Some(player) {
                     forward_count += 1;
                     nx += dx;
@@ -1124,14 +2761,14 @@ impl GameState {
             }
             let forward_open = nx >= 0
                 && ny >= 0
-                && nx < size
-                && ny < size
+                && nx < width
+                && ny < height
                 && self.board.get_cell(nx as usize, ny as usize) == Some(Cell::Empty);
 
             let mut backward_count = 0i32;
             nx = x as isize - dx;
             ny = y as isize - dy;
-            while nx >= 0 && ny >= 0 && nx < size && ny < size {
+            while nx >= 0 && ny >= 0 && nx < width && ny < height {
                 if self.board.get_cell(nx as usize, ny as usize) == Some(player) {
                     backward_count += 1;
                     nx -= dx;
@@ -1142,533 +2779,3299 @@ impl GameState {
             }
             let backward_open = nx >= 0
                 && ny >= 0
-                && nx < size
-                && ny < size
+                && nx < width
+                && ny < height
                 && self.board.get_cell(nx as usize, ny as usize) == Some(Cell::Empty);
 
             let total = forward_count + backward_count + 1;
             let open_sides = u8::from(forward_open) + u8::from(backward_open);
 
             if total >= 5 {
-                info.open_fours += 1;
+                if total == 5 || !self.exact_five_for(player) {
+                    info.open_fours += 1;
+                    info.fives += 1;
+                }
             } else if total == 4 {
                 if open_sides == 2 {
                     info.open_fours += 1;
                 } else if open_sides >= 1 {
                     info.closed_fours += 1;
                 }
-            } else if total == 3 && open_sides == 2 {
-                info.open_threes += 1;
+            } else {
+                // A split line has a single completion point, so a split four
+                // is only ever as strong as a closed one, but it still beats
+                // the open three its solid part may form.
+                let pattern = line_pattern(&self.board, x, y, (dx, dy), player);
+                if matches!(pattern, Pattern::ClosedFour | Pattern::OpenFour) {
+                    info.closed_fours += 1;
+                } else if pattern == Pattern::OpenThree || (total == 3 && open_sides == 2) {
+                    info.open_threes += 1;
+                    info.fork_threes += u8::from(self.is_fork_three(x, y, (dx, dy), player));
+                }
             }
         }
         info
     }
+
+    fn is_fork_three(&self, x: usize, y: usize, (dx, dy): (isize, isize), player: Cell) -> bool {
+        let at = |k: isize| cell_at(&self.board, x as isize + dx * k, y as isize + dy * k);
+        let open_four_cells = (-4..=4)
+            .filter(|&k| k != 0 && at(k) == Some(Cell::Empty))
+            .filter(|&extension| {
+                let extended = |k: isize| {
+                    if k == extension {
+                        Some(player)
+                    } else {
+                        at(k)
+                    }
+                };
+                window_pattern(extended, player) == Pattern::OpenFour
+            })
+            .count();
+        open_four_cells >= 2
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::DEFAULT_ADJACENCY_RADIUS;
 
     #[test]
     fn test_initialization() {
         let mut game = GameState::new();
-        assert_eq!(game.handle_start(10), "ERROR unsupported size 10");
+        assert_eq!(game.handle_start(4), "ERROR unsupported size 4");
         assert_eq!(game.handle_start(20), "OK");
         assert!(game.is_initialized);
     }
 
     #[test]
-    fn test_validate_move() {
+    fn test_with_config_respects_overrides() {
+        let config = GameConfig::builder()
+            .board_size(15)
+            .rule(RuleSet::Renju)
+            .max_depth(1)
+            .timeout_turn(Duration::from_millis(2000))
+            .time_margin(Duration::from_millis(500))
+            .build();
+        let mut game = GameState::with_config(config);
+        assert_eq!(game.move_time_budget(), Duration::from_millis(1500));
+        assert_eq!((game.board.width(), game.board.height()), (15, 15));
+        assert!(!game.is_initialized);
+
+        game.handle_start(20);
+        let response = game.handle_turn(10, 10);
+        assert!(!response.contains("ERROR"));
+        assert_eq!(game.last_search.map(|r| r.depth), Some(1));
+
+        game.handle_board_start().unwrap();
+        game.handle_board_move(3, 3, 3).unwrap();
+        assert_eq!(game.board.get_cell(3, 3), Some(Cell::Empty));
+    }
+
+    #[test]
+    fn test_start_rejects_oversized_board() {
+        let mut game = GameState::new();
+        let usage = game.memory_usage();
+
+        assert_eq!(game.handle_start(1000), "ERROR unsupported size 1000");
+        assert_eq!(
+            game.handle_start(usize::MAX),
+            format!("ERROR unsupported size {}", usize::MAX)
+        );
+        assert!(!game.is_initialized);
+        assert_eq!(game.memory_usage(), usage);
+    }
+
+    #[test]
+    fn test_oversized_start_keeps_the_current_buffers() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.push_move(10, 10, Cell::OpStone);
+        let cells = game.board.cells_ptr();
+        let board_bytes = game.board.memory_bytes();
+        let tt = Arc::clone(&game.tt);
+        let tt_bytes = game.tt.memory_bytes();
+
+        assert_eq!(
+            game.handle_rect_start(1000, 1000),
+            "ERROR unsupported board 1000x1000"
+        );
+        assert_eq!(game.handle_start(1000), "ERROR unsupported size 1000");
+
+        assert!(std::ptr::eq(game.board.cells_ptr(), cells));
+        assert_eq!(game.board.memory_bytes(), board_bytes);
+        assert!(Arc::ptr_eq(&game.tt, &tt));
+        assert_eq!(game.tt.memory_bytes(), tt_bytes);
+        assert_eq!((game.width, game.height), (20, 20));
+        assert_eq!(game.board.get_cell(10, 10), Some(Cell::OpStone));
+    }
+
+    #[test]
+    fn test_start_respects_configured_cell_cap() {
+        let mut game = GameState::with_config(GameConfig::builder().max_board_cells(100).build());
+        assert_eq!(game.handle_start(20), "ERROR unsupported size 20");
+    }
+
+    #[test]
+    fn test_rect_start() {
+        let mut game = GameState::new();
+        assert_eq!(
+            game.handle_rect_start(30, 20),
+            "ERROR unsupported board 30x20"
+        );
+        assert_eq!(
+            game.handle_rect_start(usize::MAX, 2),
+            format!("ERROR unsupported board {}x2", usize::MAX)
+        );
+        assert!(!game.is_initialized);
+
+        assert_eq!(game.handle_rect_start(20, 20), "OK");
+        assert_eq!((game.width, game.height), (20, 20));
+        assert!(game.validate_move(19, 19).is_ok());
+
+        assert_eq!(game.handle_rect_start(25, 12), "OK");
+        assert!(game.validate_move(24, 11).is_ok());
+        assert!(game.validate_move(11, 24).is_err());
+    }
+
+    #[test]
+    fn test_small_and_wide_boards_play_legal_moves() {
+        for (width, height, x0) in [(15, 15, 8), (40, 10, 30)] {
+            let mut game = GameState::with_config(
+                GameConfig::builder()
+                    .timeout_turn(Duration::from_millis(200))
+                    .build(),
+            );
+            assert_eq!(game.handle_rect_start(width, height), "OK");
+            let y = height - 1;
+            for x in x0..x0 + 3 {
+                game.place_stone(x, y, Cell::OpStone);
+            }
+            let response = game.handle_turn(x0 + 3, y);
+            let blocks = [format!("{},{}", x0 - 1, y), format!("{},{}", x0 + 4, y)];
+            assert!(
+                blocks.contains(&response),
+                "{}x{}: {}",
+                width,
+                height,
+                response
+            );
+            assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+        }
+    }
+
+    #[test]
+    fn test_play_places_our_stone_without_searching() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.handle_turn(10, 10);
+        let stones = game.board.stone_count();
+        game.last_search = None;
+
+        assert_eq!(game.handle_play(3, 4), "3,4");
+        assert_eq!(game.board.get_cell(3, 4), Some(Cell::MyStone));
+        assert_eq!(game.board.stone_count(), stones + 1);
+        assert_eq!(game.move_history.last(), Some(&(3, 4, Cell::MyStone)));
+        assert!(game.last_search.is_none());
+    }
+
+    #[test]
+    fn test_play_rejects_occupied_and_out_of_range_cells() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.handle_turn(10, 10);
+        let before = game.board.labeled();
+
+        assert_eq!(game.handle_play(10, 10), "ERROR cell already occupied");
+        assert_eq!(game.handle_play(20, 0), "ERROR coordinates out of range");
+        assert_eq!(game.board.labeled(), before);
+    }
+
+    #[test]
+    fn test_takeback_removes_stone_and_hash() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        let empty_hash = game.board.hash();
+        game.handle_info("timeout_turn", "200");
+        let response = game.handle_turn(10, 10);
+        let parts: Vec<&str> = response.split(',').collect();
+        let bot_x: usize = parts[0].parse().unwrap();
+        let bot_y: usize = parts[1].parse().unwrap();
+
+        assert_eq!(game.handle_takeback(bot_x, bot_y), "OK");
+        assert_eq!(game.handle_takeback(10, 10), "OK");
+        assert_eq!(game.board.hash(), empty_hash);
+        assert!(game.move_history.is_empty());
+        assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+    }
+
+    #[test]
+    fn test_takeback_rejects_invalid_cells() {
+        let mut game = GameState::new();
+        assert_eq!(game.handle_takeback(0, 0), "ERROR game not initialized");
+        game.handle_start(20);
+        assert_eq!(
+            game.handle_takeback(20, 0),
+            "ERROR coordinates out of range"
+        );
+        assert_eq!(game.handle_takeback(5, 5), "ERROR no stone to take back");
+    }
+
+    #[test]
+    fn test_validate_move() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        assert!(game.validate_move(10, 10).is_ok());
+
+        assert!(game.validate_move(20, 10).is_err());
+        assert!(game.validate_move(10, 20).is_err());
+
+        game.board.set_cell(10, 10, Cell::MyStone).unwrap();
+        assert!(game.validate_move(10, 10).is_err());
+        assert_eq!(game.validate_move(10, 10), Err(GameError::Occupied));
+
+        game.board.set_cell(11, 11, Cell::Forbidden).unwrap();
+        assert!(game.validate_move(11, 11).is_err());
+        assert_eq!(game.validate_move(11, 11), Err(GameError::Forbidden));
+    }
+
+    #[test]
+    fn test_fallback_move_center_then_nearby() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        assert_eq!(game.fallback_move(), Some((10, 10)));
+
+        game.board.set_cell(10, 10, Cell::OpStone).unwrap();
+        let response = game.fallback_move();
+        assert!(response.is_some());
+        assert_ne!(response, Some((10, 10)));
+    }
+
+    #[test]
+    fn test_generate_candidates_empty_board_centers() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        let candidates = game.generate_candidates();
+        let expected = vec![(10, 10), (9, 9), (9, 10), (10, 9)];
+        assert_eq!(candidates, expected);
+    }
+
+    #[test]
+    fn test_generate_candidates_early_game_center_neighborhood() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.board.set_cell(0, 0, Cell::MyStone).unwrap();
+
+        let candidates = game.generate_candidates();
+        assert!(candidates.contains(&(8, 8)));
+        assert!(candidates.contains(&(11, 11)));
+    }
+
+    #[test]
+    fn test_generate_candidates_excludes_forbidden_cells() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.board.set_cell(6, 6, Cell::MyStone).unwrap();
+        game.board.set_cell(5, 5, Cell::Forbidden).unwrap();
+
+        let candidates = game.generate_candidates();
+        assert!(!candidates.contains(&(5, 5)));
+        assert!(candidates.contains(&(6, 7)));
+    }
+
+    #[test]
+    fn test_generate_candidates_bounds_and_radius() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.board.set_cell(0, 0, Cell::MyStone).unwrap();
+
+        let candidates = game.generate_candidates();
+        assert!(candidates.contains(&(2, 2)));
+        assert!(!candidates.contains(&(3, 3)));
+    }
+
+    fn brute_force_candidates(board: &Board, radius: usize) -> Vec<(usize, usize)> {
+        let mut expected = Vec::new();
+        for y in 0..20usize {
+            for x in 0..20usize {
+                if !board.is_empty(x, y) {
+                    continue;
+                }
+                let near_stone = board.iter_indices().any(|(sx, sy)| {
+                    board.get_cell(sx, sy).is_some_and(Cell::is_stone)
+                        && sx.abs_diff(x) <= radius
+                        && sy.abs_diff(y) <= radius
+                });
+                if near_stone {
+                    expected.push((x, y));
+                }
+            }
+        }
+        expected
+    }
+
+    #[test]
+    fn test_cap_candidates_keeps_tactical_moves_past_the_cap() {
+        let mut sorted = vec![(0, false), (1, false), (2, true), (3, false), (4, true)];
+        cap_candidates(&mut sorted, 2, |c| c.1);
+        assert_eq!(sorted, vec![(0, false), (1, false), (2, true), (4, true)]);
+    }
+
+    #[test]
+    fn test_crowded_board_keeps_every_four_cell() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for y in (1..20).step_by(4) {
+            for x in (1..20).step_by(4) {
+                let cell = if (x + y) % 8 == 2 {
+                    Cell::MyStone
+                } else {
+                    Cell::OpStone
+                };
+                game.place_stone(x, y, cell);
+            }
+        }
+        for x in 14..17 {
+            game.place_stone(x, 19, Cell::OpStone);
+        }
+        game.place_stone(13, 19, Cell::MyStone);
+
+        let candidates = game.generate_candidates();
+        let nearby = brute_force_candidates(&game.board, DEFAULT_ADJACENCY_RADIUS);
+        assert!(nearby.len() > CANDIDATE_CAP);
+        for (x, y) in nearby {
+            let tactical = game.detect_threats(x, y, Cell::MyStone).makes_four()
+                || game.detect_threats(x, y, Cell::OpStone).makes_four();
+            if tactical {
+                assert!(candidates.contains(&(x, y)), "{},{} was capped", x, y);
+            }
+        }
+        assert!(candidates.contains(&(17, 19)));
+    }
+
+    #[test]
+    fn test_crowded_board_keeps_a_split_four_cell() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for y in (1..20).step_by(4) {
+            for x in (1..20).step_by(4) {
+                game.place_stone(x, y, Cell::OpStone);
+            }
+        }
+        for &x in &[6, 7, 10] {
+            game.place_stone(x, 19, Cell::MyStone);
+        }
+
+        let nearby = brute_force_candidates(&game.board, DEFAULT_ADJACENCY_RADIUS);
+        assert!(nearby.len() > CANDIDATE_CAP);
+        let fours: Vec<_> = nearby
+            .into_iter()
+            .filter(|&(x, y)| game.detect_threats(x, y, Cell::MyStone).makes_four())
+            .collect();
+        assert_eq!(fours, vec![(8, 19), (9, 19)]);
+        let candidates = game.generate_candidates();
+        assert!(candidates.contains(&(8, 19)));
+        assert!(candidates.contains(&(9, 19)));
+    }
+
+    #[test]
+    fn test_generate_candidates_matches_brute_force_scan() {
+        let positions: [&[(usize, usize, Cell)]; 4] = [
+            &[
+                (4, 4, Cell::MyStone),
+                (5, 5, Cell::OpStone),
+                (15, 3, Cell::MyStone),
+                (0, 19, Cell::OpStone),
+            ],
+            &[
+                (0, 0, Cell::MyStone),
+                (19, 19, Cell::OpStone),
+                (19, 0, Cell::MyStone),
+                (0, 10, Cell::OpStone),
+            ],
+            &[
+                (9, 9, Cell::MyStone),
+                (10, 10, Cell::OpStone),
+                (11, 9, Cell::MyStone),
+                (12, 12, Cell::OpStone),
+            ],
+            &[
+                (1, 10, Cell::OpStone),
+                (18, 10, Cell::MyStone),
+                (10, 1, Cell::OpStone),
+                (10, 18, Cell::MyStone),
+            ],
+        ];
+
+        for stones in positions {
+            let mut game = GameState::new();
+            game.handle_start(20);
+            for &(x, y, cell) in stones {
+                game.place_stone(x, y, cell);
+            }
+            game.remove_stone(stones[1].0, stones[1].1);
+
+            let mut candidates = game.generate_candidates();
+            let mut expected = brute_force_candidates(&game.board, DEFAULT_ADJACENCY_RADIUS);
+            candidates.sort();
+            expected.sort();
+            assert_eq!(candidates, expected);
+        }
+    }
+
+    #[test]
+    fn test_candidates_exclude_cells_beyond_adjacency_radius() {
+        for radius in 1..=3 {
+            let mut game =
+                GameState::with_config(GameConfig::builder().adjacency_radius(radius).build());
+            game.handle_start(20);
+            for &(x, y, cell) in &[
+                (9, 9, Cell::MyStone),
+                (10, 10, Cell::OpStone),
+                (10, 9, Cell::MyStone),
+            ] {
+                game.place_stone(x, y, cell);
+            }
+
+            let mut candidates = game.generate_candidates();
+            assert!(!candidates.contains(&(0, 0)));
+            assert!(!candidates.contains(&(19, 19)));
+            candidates.sort();
+            let mut expected = brute_force_candidates(&game.board, radius);
+            expected.sort();
+            assert_eq!(candidates, expected);
+        }
+    }
+
+    #[test]
+    fn test_info_adjacency_radius_widens_and_narrows_candidates() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for &(x, y, cell) in &[
+            (9, 9, Cell::MyStone),
+            (10, 10, Cell::OpStone),
+            (10, 9, Cell::MyStone),
+        ] {
+            game.place_stone(x, y, cell);
+        }
+        let default_count = game.generate_candidates().len();
+
+        game.handle_info("adjacency_radius", "1");
+        let narrow = game.generate_candidates();
+        assert_eq!(narrow.len(), brute_force_candidates(&game.board, 1).len());
+        assert!(narrow.len() < default_count);
+
+        game.handle_info("adjacency_radius", "9");
+        assert_eq!(game.config.adjacency_radius, 1);
+        game.handle_info("adjacency_radius", "3");
+        let wide = game.generate_candidates();
+        assert_eq!(wide.len(), brute_force_candidates(&game.board, 3).len());
+        assert!(narrow.iter().all(|cell| wide.contains(cell)));
+    }
+
+    #[test]
+    fn test_info_center_radius_sets_the_opening_window() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(3, 3, Cell::OpStone);
+        let near_stone = brute_force_candidates(&game.board, DEFAULT_ADJACENCY_RADIUS).len();
+        let opening = |game: &GameState| {
+            game.generate_candidates()
+                .into_iter()
+                .filter(|&(x, y)| x.abs_diff(3).max(y.abs_diff(3)) > DEFAULT_ADJACENCY_RADIUS)
+                .collect::<Vec<_>>()
+        };
+
+        for (rings, side) in [(0, 2), (1, 4), (2, 6)] {
+            game.handle_info("center_radius", &rings.to_string());
+            let middle = opening(&game);
+            assert_eq!(middle.len(), side * side, "center_radius {}", rings);
+            assert!(middle
+                .iter()
+                .all(|&(x, y)| game.center_distance(x, y) <= rings + 1));
+            assert_eq!(game.generate_candidates().len(), near_stone + side * side);
+        }
+    }
+
+    #[test]
+    fn test_generate_candidates_cap() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        // Stones too far apart, or of alternating colour, to make a three.
+        for y in (0..20).step_by(2) {
+            for x in (0..20).step_by(3) {
+                let cell = if (x / 3 + y / 2) % 2 == 0 {
+                    Cell::MyStone
+                } else {
+                    Cell::OpStone
+                };
+                game.board.set_cell(x, y, cell).unwrap();
+            }
+        }
+
+        let candidates = game.generate_candidates();
+        let plan = search_plan(game.count_stones());
+        assert_eq!(candidates.len(), plan.candidate_cap);
+        assert!(plan.candidate_cap < CANDIDATE_CAP);
+    }
+
+    #[test]
+    fn test_short_clock_keeps_every_three_in_a_sharp_position() {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_millis(300))
+                .build(),
+        );
+        game.handle_start(20);
+        for y in [2, 6, 10, 14, 18] {
+            for x in [3, 13] {
+                game.push_move(x, y, Cell::MyStone);
+                game.push_move(x + 1, y, Cell::MyStone);
+            }
+        }
+        for y in (0..20).step_by(4) {
+            game.push_move(0, y, Cell::OpStone);
+            game.push_move(19, y + 2, Cell::OpStone);
+        }
+        assert!(game.move_time_budget() < FULL_WIDTH_MOVE_TIME / 4);
+
+        // Furthest from the middle, so last among equally scored moves.
+        let critical = (15, 18);
+        assert!(game.detect_threats(15, 18, Cell::MyStone).open_threes > 0);
+        let candidates = game.generate_candidates();
+        assert!(candidates.len() > MIN_CANDIDATE_CAP, "{}", candidates.len());
+        assert!(candidates.contains(&critical));
+    }
+
+    #[test]
+    fn test_candidate_cap_narrows_with_time_and_widens_when_sharp() {
+        let full = FULL_WIDTH_MOVE_TIME;
+        let quiet = [40, 30, 20, 10];
+        assert_eq!(
+            adaptive_candidate_cap(CANDIDATE_CAP, full, quiet),
+            CANDIDATE_CAP
+        );
+        assert_eq!(
+            adaptive_candidate_cap(CANDIDATE_CAP, full / 2, quiet),
+            CANDIDATE_CAP / 2
+        );
+        assert_eq!(
+            adaptive_candidate_cap(CANDIDATE_CAP, Duration::ZERO, quiet),
+            MIN_CANDIDATE_CAP
+        );
+
+        let mut sharp = vec![SCORE_OPEN_THREE; MIN_CANDIDATE_CAP + 10];
+        sharp.extend([SCORE_SHARP_MOVE - 1; 50]);
+        assert_eq!(
+            adaptive_candidate_cap(MIN_CANDIDATE_CAP, Duration::ZERO, sharp.clone()),
+            MIN_CANDIDATE_CAP + 10
+        );
+        sharp.insert(0, SCORE_OPEN_FOUR);
+        assert_eq!(
+            adaptive_candidate_cap(MIN_CANDIDATE_CAP, Duration::ZERO, sharp),
+            MIN_CANDIDATE_CAP
+        );
+        let many = [SCORE_OPEN_THREE; 2 * CANDIDATE_CAP];
+        assert_eq!(
+            adaptive_candidate_cap(MIN_CANDIDATE_CAP, full, many),
+            CANDIDATE_CAP
+        );
+    }
+
+    #[test]
+    fn test_transposed_position_hits_the_move_cache() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.push_move(9, 9, Cell::MyStone);
+        game.push_move(10, 10, Cell::OpStone);
+
+        let first = game.cached_candidates();
+        assert_eq!(game.move_cache.hits, 0);
+        assert_eq!(game.cached_candidates(), first);
+        assert_eq!(game.move_cache.hits, 1);
+
+        game.pop_move();
+        game.pop_move();
+        game.push_move(10, 10, Cell::OpStone);
+        game.push_move(9, 9, Cell::MyStone);
+        assert_eq!(game.cached_candidates(), first);
+        assert_eq!(game.move_cache.hits, 2);
+        assert_eq!(first, game.generate_candidates());
+
+        assert!(game.search_nodes(3) > 0);
+        assert!(game.move_cache.hits > 0);
+    }
+
+    #[test]
+    fn test_move_cache_is_cleared_for_each_move() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.push_move(10, 10, Cell::OpStone);
+        let hash = game.board.hash();
+        game.move_cache
+            .insert(hash, &[(0, 0)], WinningMoves::default());
+
+        game.best_move();
+        assert!(game
+            .move_cache
+            .get(hash)
+            .map_or(true, |(moves, _)| moves != [(0, 0)]));
+    }
+
+    #[test]
+    fn test_search_plan_deepens_and_narrows_as_the_board_fills() {
+        let plans: Vec<SearchPlan> = [0, 8, 30, 80, 400].map(search_plan).to_vec();
+        assert_eq!(plans[0].max_depth, OPENING_DEPTH);
+        assert_eq!(plans[0].candidate_cap, CANDIDATE_CAP);
+        for pair in plans.windows(2) {
+            assert!(pair[1].max_depth >= pair[0].max_depth, "{:?}", pair);
+            assert!(pair[1].candidate_cap <= pair[0].candidate_cap, "{:?}", pair);
+        }
+        assert!(plans[2].max_depth > plans[1].max_depth);
+        assert_eq!(plans[4].max_depth, MAX_SEARCH_DEPTH);
+        assert_eq!(plans[4].candidate_cap, MIN_CANDIDATE_CAP);
+    }
+
+    #[test]
+    fn test_detect_threats_fork_three() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(8, 10, Cell::MyStone);
+        game.place_stone(9, 10, Cell::MyStone);
+
+        let threats = game.detect_threats(10, 10, Cell::MyStone);
+        assert_eq!(threats.open_threes, 1);
+        assert_eq!(threats.fork_threes, 1);
+        assert_eq!(threats.score(), SCORE_FORK_THREE);
+    }
+
+    #[test]
+    fn test_detect_threats_plain_open_three() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(8, 10, Cell::MyStone);
+        game.place_stone(9, 10, Cell::MyStone);
+        game.place_stone(6, 10, Cell::OpStone);
+        game.place_stone(12, 10, Cell::OpStone);
+
+        let threats = game.detect_threats(10, 10, Cell::MyStone);
+        assert_eq!(threats.open_threes, 1);
+        assert_eq!(threats.fork_threes, 0);
+        assert_eq!(threats.score(), SCORE_OPEN_THREE);
+    }
+
+    #[test]
+    fn test_detect_threats_three_with_one_open_four_is_not_a_fork() {
+        // `O _ X X X _ _`: only the right end makes an open four.
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(6, 10, Cell::OpStone);
+        game.place_stone(8, 10, Cell::MyStone);
+        game.place_stone(9, 10, Cell::MyStone);
+
+        let threats = game.detect_threats(10, 10, Cell::MyStone);
+        assert_eq!(threats.open_threes, 1);
+        assert_eq!(threats.fork_threes, 0);
+    }
+
+    #[test]
+    fn test_detect_threats_split_three_is_not_a_fork() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(8, 10, Cell::MyStone);
+        game.place_stone(11, 10, Cell::MyStone);
+
+        let threats = game.detect_threats(10, 10, Cell::MyStone);
+        assert_eq!(threats.open_threes, 1);
+        assert_eq!(threats.fork_threes, 0);
+    }
+
+    #[test]
+    fn test_line_scanners_agree_on_every_direction() {
+        for &(dx, dy) in &DIRECTIONS {
+            let mut game = GameState::new();
+            game.handle_start(20);
+            let start_y = if dy < 0 { 12 } else { 8 };
+            let cells: Vec<(usize, usize)> = (0..5)
+                .map(|i| ((8 + dx * i) as usize, (start_y + dy * i) as usize))
+                .collect();
+
+            for &(x, y) in &cells[..4] {
+                game.place_stone(x, y, Cell::MyStone);
+            }
+            let (lx, ly) = cells[4];
+            let threats = game.detect_threats(lx, ly, Cell::MyStone);
+            assert_eq!(threats.open_fours, 1, "direction ({}, {})", dx, dy);
+            assert!(!game.board.check_five_in_a_row(Cell::MyStone));
+            assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+
+            game.place_stone(lx, ly, Cell::MyStone);
+            assert!(game.board.check_five_in_a_row(Cell::MyStone));
+            assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+        }
+    }
+
+    #[test]
+    fn test_fork_block_prefers_shared_cell() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(8, 10, Cell::OpStone);
+        game.place_stone(9, 10, Cell::OpStone);
+        game.place_stone(10, 8, Cell::OpStone);
+        game.place_stone(10, 9, Cell::OpStone);
+        game.place_stone(5, 5, Cell::MyStone);
+
+        assert_eq!(game.find_fork_block(), Some((10, 10)));
+    }
+
+    #[test]
+    fn test_fork_is_met_by_any_move_that_defuses_it_or_forces() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for &(x, y) in &[(8, 10), (9, 10), (10, 8), (10, 9)] {
+            game.place_stone(x, y, Cell::OpStone);
+        }
+        for &(x, y) in &[(2, 3), (3, 3), (4, 3), (5, 3)] {
+            let cell = if x == 2 { Cell::OpStone } else { Cell::MyStone };
+            game.place_stone(x, y, cell);
+        }
+        let fork = (10, 10);
+
+        assert!(game.meets_fork((10, 7), fork));
+        assert!(game.meets_fork((6, 3), fork));
+        assert!(!game.meets_fork((15, 15), fork));
+        assert_eq!(game.board.stone_count(), 8);
+    }
+
+    #[test]
+    fn test_search_against_fork_never_leaves_it_standing() {
+        let mut game = GameState::with_config(GameConfig::builder().max_depth(2).build());
+        game.handle_start(20);
+        for &(x, y) in &[(8, 10), (9, 10), (10, 8), (10, 9)] {
+            game.place_stone(x, y, Cell::OpStone);
+        }
+        game.place_stone(5, 5, Cell::MyStone);
+
+        let mv = game.search_against_fork(far_deadline()).unwrap();
+        assert!(game.meets_fork(mv, (10, 10)), "{:?}", mv);
+        assert_eq!(game.root_hint, None);
+    }
+
+    #[test]
+    fn test_search_against_fork_keeps_a_proven_win() {
+        let mut game = GameState::with_config(GameConfig::builder().max_depth(4).build());
+        game.handle_start(20);
+        for &(x, y) in &[(3, 10), (4, 10), (6, 11), (7, 13), (7, 14)] {
+            game.place_stone(x, y, Cell::MyStone);
+        }
+        for &(x, y) in &[(14, 4), (15, 4), (16, 2), (16, 3)] {
+            game.place_stone(x, y, Cell::OpStone);
+        }
+        let fork = game.find_fork_block().unwrap();
+
+        let mv = game.search_against_fork(far_deadline()).unwrap();
+        assert_ne!(mv, fork);
+        let report = game.last_search.unwrap();
+        assert_eq!(report.best_move, mv);
+        assert!(report.score >= SCORE_WIN - MAX_SEARCH_PLY as i32);
+    }
+
+    #[test]
+    fn test_turn_blocks_overlapping_threats() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.handle_info("timeout_turn", "500");
+        game.place_stone(8, 10, Cell::OpStone);
+        game.place_stone(9, 10, Cell::OpStone);
+        game.place_stone(10, 8, Cell::OpStone);
+
+        assert_eq!(game.handle_turn(10, 9), "10,10");
+    }
+
+    #[test]
+    fn test_fork_block_skipped_without_fork() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(8, 10, Cell::OpStone);
+        game.place_stone(9, 10, Cell::OpStone);
+
+        assert_eq!(game.find_fork_block(), None);
+    }
+
+    #[test]
+    fn test_static_score_sign_follows_player() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for x in 8..11 {
+            game.place_stone(x, 10, Cell::MyStone);
+        }
+        game.place_stone(3, 3, Cell::OpStone);
+
+        assert!(game.evaluate_position() > 0);
+        assert!(game.static_score(Cell::MyStone) > 0);
+        assert_eq!(
+            game.static_score(Cell::OpStone),
+            -game.static_score(Cell::MyStone)
+        );
+    }
+
+    #[test]
+    fn test_root_search_agrees_with_evaluation_sign() {
+        for max_depth in 1..=3 {
+            let mut game = GameState::with_config(
+                GameConfig::builder()
+                    .max_depth(max_depth)
+                    .timeout_turn(Duration::from_secs(2))
+                    .build(),
+            );
+            game.handle_start(20);
+            game.place_stone(9, 10, Cell::MyStone);
+            game.place_stone(10, 10, Cell::MyStone);
+            game.place_stone(9, 11, Cell::MyStone);
+            game.place_stone(3, 3, Cell::OpStone);
+            game.place_stone(16, 16, Cell::OpStone);
+            let before = game.evaluate_position();
+            assert!(before > 0);
+
+            let best = game
+                .find_best_move(Instant::now() + game.move_time_budget())
+                .unwrap();
+            let report = game.last_search.unwrap();
+            assert_eq!(report.best_move, best);
+            assert!(report.score > 0, "depth {}: {}", max_depth, report.score);
+
+            game.place_stone(best.0, best.1, Cell::MyStone);
+            assert!(game.evaluate_position() > before, "depth {}", max_depth);
+        }
+    }
+
+    #[test]
+    fn test_greedy_engine_plays_the_top_candidate_without_searching() {
+        let mut game =
+            GameState::with_config(GameConfig::builder().engine(EngineKind::Greedy).build());
+        game.handle_start(20);
+        game.handle_board_start().unwrap();
+        for &(x, y, field) in &[(9, 9, 1), (10, 10, 2), (10, 9, 1), (11, 11, 2)] {
+            game.handle_board_move(x, y, field).unwrap();
+        }
+        let (x, y) = game.fallback_move().unwrap();
+
+        assert_eq!(game.handle_board_done(), format!("{},{}", x, y));
+        assert_eq!(game.nodes, 0);
+    }
+
+    #[test]
+    fn test_generated_move_avoids_forbidden_cells() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.handle_info("timeout_turn", "500");
+        game.handle_board_start().unwrap();
+        for x in 5..9 {
+            game.handle_board_move(x, 10, 1).unwrap();
+        }
+        game.handle_board_move(4, 10, 3).unwrap();
+        game.handle_board_move(9, 10, 3).unwrap();
+        for y in 8..13 {
+            for x in 3..11 {
+                if game.board.is_empty(x, y) && (x + y) % 2 == 0 {
+                    game.handle_board_move(x, y, 3).unwrap();
+                }
+            }
+        }
+
+        let response = game.handle_board_done();
+        let (x, y) = response.split_once(',').unwrap();
+        let (x, y): (usize, usize) = (x.parse().unwrap(), y.parse().unwrap());
+        assert_eq!(game.board.get_cell(x, y), Some(Cell::MyStone));
+        assert_eq!(game.board.get_cell(4, 10), Some(Cell::Forbidden));
+        assert_eq!(game.board.get_cell(9, 10), Some(Cell::Forbidden));
+    }
+
+    #[test]
+    fn test_turn_handling() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        assert_eq!(game.handle_turn(20, 20), "ERROR coordinates out of range");
+
+        let response = game.handle_turn(0, 0);
+        assert!(!response.contains("ERROR"));
+        assert_eq!(game.board.get_cell(0, 0), Some(Cell::OpStone));
+
+        let parts: Vec<&str> = response.split(',').collect();
+        let bot_x: usize = parts[0].parse().unwrap();
+        let bot_y: usize = parts[1].parse().unwrap();
+        assert_eq!(game.board.get_cell(bot_x, bot_y), Some(Cell::MyStone));
+
+        assert_eq!(
+            game.handle_turn(bot_x, bot_y),
+            "ERROR cell already occupied"
+        );
+    }
+
+    #[test]
+    fn test_board_command() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        assert!(game.handle_board_start().is_ok());
+        game.handle_board_move(10, 10, 1).unwrap();
+        game.handle_board_move(10, 11, 2).unwrap();
+
+        assert_eq!(game.board.get_cell(10, 10), Some(Cell::MyStone));
+        assert_eq!(game.board.get_cell(10, 11), Some(Cell::OpStone));
+
+        let response = game.handle_board_done();
+        assert!(!response.contains("ERROR"));
+        assert_ne!(response, "10,10");
+        assert_ne!(response, "10,11");
+    }
+
+    #[test]
+    fn test_board_move_accepts_each_field_value() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.handle_board_start().unwrap();
+
+        let expected = [Cell::Empty, Cell::MyStone, Cell::OpStone, Cell::Forbidden];
+        for (field, &cell) in expected.iter().enumerate() {
+            game.handle_board_move(field, 0, field).unwrap();
+            assert_eq!(game.board.get_cell(field, 0), Some(cell));
+        }
+        assert_eq!(game.move_history.len(), 2);
+        for field in [4, 9, usize::MAX] {
+            assert_eq!(
+                game.handle_board_move(5, 5, field),
+                Err(GameError::InvalidField(field))
+            );
+        }
+        assert_eq!(game.board.get_cell(5, 5), Some(Cell::Empty));
+    }
+
+    #[test]
+    fn test_conflicting_board_lines_keep_the_last_write() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.handle_board_start().unwrap();
+        game.handle_board_move(10, 10, 1).unwrap();
+        game.handle_board_move(11, 10, 2).unwrap();
+        game.handle_board_move(10, 10, 2).unwrap();
+        game.handle_board_move(11, 10, 2).unwrap();
+
+        assert_eq!(game.board.get_cell(10, 10), Some(Cell::OpStone));
+        assert_eq!(game.board.stone_count(), 2);
+        assert_eq!(
+            game.move_history,
+            vec![(10, 10, Cell::OpStone), (11, 10, Cell::OpStone)]
+        );
+        assert_eq!(game.move_times.len(), 2);
+
+        let mut fresh = GameState::new();
+        fresh.handle_start(20);
+        fresh.push_move(10, 10, Cell::OpStone);
+        fresh.push_move(11, 10, Cell::OpStone);
+        assert_eq!(game.board.hash(), fresh.board.hash());
+    }
+
+    #[test]
+    fn test_board_move_errors() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        assert_eq!(
+            game.handle_board_move(20, 0, 1),
+            Err(GameError::OutOfBounds)
+        );
+        assert_eq!(
+            game.handle_board_move(0, 20, 1),
+            Err(GameError::OutOfBounds)
+        );
+        assert_eq!(
+            game.handle_board_move(0, 0, 9),
+            Err(GameError::InvalidField(9))
+        );
+    }
+
+    #[test]
+    fn test_canonical_moves_on_empty_board_are_symmetry_unique() {
+        let board = Board::default();
+        let moves = canonical_moves(&board, board.iter_empty().collect());
+        assert_eq!(moves.len(), 55);
+        for &(x, y) in &moves {
+            for s in 1..SYMMETRIES {
+                let image = board.symmetric_coords(x, y, s).unwrap();
+                assert!(image == (x, y) || !moves.contains(&image));
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical_moves_keep_asymmetric_positions_intact() {
+        let mut board = Board::default();
+        board.set_cell(10, 10, Cell::MyStone).unwrap();
+        let moves = canonical_moves(&board, board.iter_empty().collect());
+        assert_eq!(moves.len(), (400 - 1 + 20 - 1) / 2);
+
+        board.set_cell(3, 7, Cell::OpStone).unwrap();
+        let all: Vec<_> = board.iter_empty().collect();
+        assert_eq!(canonical_moves(&board, all.clone()), all);
+    }
+
+    #[test]
+    fn test_opening_book_plays_centre_on_empty_board() {
+        let mut game = GameState::with_config(GameConfig::builder().opening_book(true).build());
+        game.handle_start(20);
+        assert_eq!(game.handle_begin(), "10,10");
+        assert!(game.last_search.is_none());
+    }
+
+    #[test]
+    fn test_opening_book_answers_a_lone_stone_without_searching() {
+        let mut game = GameState::with_config(GameConfig::builder().opening_book(true).build());
+        game.handle_start(20);
+        let response = game.handle_turn(4, 7);
+        let (x, y) = response.split_once(',').unwrap();
+        let (x, y): (usize, usize) = (x.parse().unwrap(), y.parse().unwrap());
+        assert_eq!((x.abs_diff(4), y.abs_diff(7)), (1, 1));
+        assert!(game.last_search.is_none());
+        assert_eq!(game.nodes, 0);
+    }
+
+    #[test]
+    fn test_opening_book_is_off_unless_configured() {
+        let mut game = GameState::with_config(GameConfig::builder().max_depth(1).build());
+        game.handle_start(20);
+        game.handle_turn(4, 7);
+        assert_eq!(game.last_search.map(|r| r.depth), Some(1));
+    }
+
+    #[test]
+    fn test_search_counts_nodes_and_logs_summary() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.push_move(9, 9, Cell::OpStone);
+        game.push_move(9, 10, Cell::MyStone);
+        game.push_move(10, 10, Cell::OpStone);
+        let before = game.board.labeled();
+
+        game.find_best_move(Instant::now() + Duration::from_millis(200));
+        assert!(game.nodes > 0);
+        game.log_search_summary(Instant::now());
+        assert_eq!(game.board.labeled(), before);
+    }
+
+    #[test]
+    fn test_psq_record_lists_stones_in_play_order() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.handle_board_start().unwrap();
+        game.handle_board_move(9, 9, 2).unwrap();
+        game.handle_board_move(10, 10, 1).unwrap();
+        game.handle_turn(11, 11);
+        let (x, y, _) = *game.move_history.last().unwrap();
+
+        let record = game.psq_record();
+        let lines: Vec<&str> = record.lines().collect();
+        assert_eq!(lines[0], "Piskvorky 20x20, 11:11, 0");
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("10,10,"));
+        assert!(lines[2].starts_with("11,11,"));
+        assert!(lines[3].starts_with("12,12,"));
+        assert!(lines[4].starts_with(&format!("{},{},", x + 1, y + 1)));
+        for line in &lines[1..] {
+            let fields: Vec<&str> = line.split(',').collect();
+            assert_eq!(fields.len(), 3);
+            assert!(fields.iter().all(|f| f.parse::<u128>().is_ok()));
+        }
+    }
+
+    #[test]
+    fn test_repeated_start_saves_the_unfinished_game() {
+        let dir = std::env::temp_dir().join(format!("psq-start-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut game =
+            GameState::with_config(GameConfig::builder().psq_dir(Some(dir.clone())).build());
+        game.handle_start(20);
+        game.handle_board_start().unwrap();
+        game.handle_board_move(9, 9, 2).unwrap();
+        game.handle_board_move(10, 10, 1).unwrap();
+
+        game.handle_start(15);
+        let saved: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(saved.len(), 1);
+    }
+
+    #[test]
+    fn test_suggest_leaves_board_unchanged() {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_millis(300))
+                .build(),
+        );
+        game.handle_start(20);
+        game.push_move(9, 9, Cell::OpStone);
+        game.push_move(9, 10, Cell::MyStone);
+        game.push_move(10, 10, Cell::OpStone);
+        let board = game.board.labeled();
+        let hash = game.board.hash();
+        let history = game.move_history.clone();
+
+        let reply = game.handle_suggest();
+        assert!(reply.starts_with("SUGGEST "), "{}", reply);
+        assert!(game.last_search.is_some());
+        assert_eq!(game.board.labeled(), board);
+        assert_eq!(game.board.hash(), hash);
+        assert_eq!(game.move_history, history);
+    }
+
+    #[test]
+    fn test_info_evaluate_scores_without_touching_the_board() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for (x, y, cell) in [
+            (9, 9, Cell::MyStone),
+            (10, 9, Cell::MyStone),
+            (9, 10, Cell::OpStone),
+        ] {
+            game.push_move(x, y, cell);
+        }
+        let hash = game.board.hash();
+        let evaluation = game.evaluation();
+
+        game.handle_info("evaluate", "11,9");
+        let first = game.last_evaluation();
+        game.handle_info("evaluate", " 11 , 9 ");
+        assert_eq!(game.last_evaluation(), first);
+        let ((x, y), score) = first.unwrap();
+        assert_eq!((x, y), (11, 9));
+        assert!(score.unwrap() > evaluation);
+        assert_eq!(game.board.hash(), hash);
+        assert_eq!(game.evaluation(), evaluation);
+        assert_eq!(game.move_history.len(), 3);
+
+        game.handle_info("evaluate", "9,9");
+        assert_eq!(game.last_evaluation(), Some(((9, 9), None)));
+        game.handle_info("evaluate", "nowhere");
+        assert_eq!(game.last_evaluation(), Some(((9, 9), None)));
+        assert_eq!(game.board.hash(), hash);
+    }
+
+    #[test]
+    fn test_info_rule_selects_rule_set() {
+        let mut game = GameState::new();
+        assert_eq!(game.rule(), RuleSet::Freestyle);
+        game.handle_info("rule", "1");
+        assert_eq!(game.rule(), RuleSet::Standard);
+        game.handle_info("rule", "4");
+        assert_eq!(game.rule(), RuleSet::Renju);
+        game.handle_info("rule", "0");
+        assert_eq!(game.rule(), RuleSet::Freestyle);
+    }
+
+    #[test]
+    fn test_info_max_depth_bounds_the_search() {
+        let config = GameConfig::builder()
+            .timeout_turn(Duration::from_secs(5))
+            .build();
+        let mut game = GameState::with_config(config);
+        game.handle_info("max_depth", "0");
+        game.handle_info("max_depth", "40");
+        assert_eq!(game.config.max_depth, config::DEFAULT_MAX_DEPTH);
+        game.handle_info("max_depth", "2");
+        assert_eq!(game.config.max_depth, 2);
+
+        game.handle_start(20);
+        let reply = game.handle_turn(10, 10);
+        assert!(!reply.contains("ERROR"), "{}", reply);
+        let depth = game.last_search.map(|r| r.depth).unwrap();
+        assert!((1..=2).contains(&depth), "searched to depth {}", depth);
+    }
+
+    #[test]
+    fn test_info_stores_game_type_and_folder() {
+        let mut game = GameState::new();
+        game.handle_info("game_type", "2");
+        game.handle_info("folder", "/tmp/gomoku");
+        game.handle_info("evaluate", "3,4");
+        game.handle_info("unknown_key", "whatever");
+        assert_eq!(game.game_type(), Some(2));
+        assert_eq!(game.folder(), Some("/tmp/gomoku"));
+        assert_eq!(game.rule(), RuleSet::Freestyle);
+    }
+
+    #[test]
+    fn test_board_forbidden_marker_depends_on_rule() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.handle_board_start().unwrap();
+        game.handle_board_move(4, 4, 3).unwrap();
+        assert_eq!(game.validate_move(4, 4), Err(GameError::Forbidden));
+
+        game.handle_info("rule", "4");
+        game.handle_board_start().unwrap();
+        game.handle_board_move(4, 4, 3).unwrap();
+        assert_eq!(game.board.get_cell(4, 4), Some(Cell::Empty));
+        assert!(game.validate_move(4, 4).is_ok());
+
+        for (i, &(x, y)) in [(8, 10), (10, 8), (9, 10), (10, 9)].iter().enumerate() {
+            game.handle_board_move(x, y, 1).unwrap();
+            game.handle_board_move(2 * i, 0, 2).unwrap();
+        }
+        assert_eq!(game.board.get_cell(10, 10), Some(Cell::Empty));
+        assert_eq!(
+            game.validate_move_for(10, 10, Cell::MyStone),
+            Err(GameError::Forbidden)
+        );
+    }
+
+    #[test]
+    fn test_commands_before_start_share_one_error() {
+        let mut game = GameState::new();
+        let replies = [
+            game.handle_turn(5, 5),
+            game.handle_play(5, 5),
+            game.handle_begin(),
+            game.handle_board_done(),
+            game.handle_swap(),
+            game.handle_suggest(),
+            game.handle_restart(),
+            game.handle_takeback(5, 5),
+        ];
+        for reply in replies {
+            assert_eq!(reply, error_response(GameError::NotInitialized));
+        }
+        assert_eq!(game.handle_board_start(), Err(GameError::NotInitialized));
+        assert_eq!(
+            game.handle_board_move(5, 5, 1),
+            Err(GameError::NotInitialized)
+        );
+        assert_eq!(game.validate_move(5, 5), Err(GameError::NotInitialized));
+        assert!(!game.is_initialized);
+        assert_eq!(game.board.stone_count(), 0);
+    }
+
+    #[test]
+    fn test_load_position_rejects_bad_input() {
+        let mut game = GameState::with_config(GameConfig::builder().max_board_cells(100).build());
+        let board = Board::new(10).unwrap();
+        assert_eq!(
+            game.load_position(&board, Cell::Empty),
+            Err(GameError::NotAStone)
+        );
+        let board = Board::new(20).unwrap();
+        assert_eq!(
+            game.load_position(&board, Cell::MyStone),
+            Err(GameError::UnsupportedSize {
+                width: 20,
+                height: 20
+            })
+        );
+    }
+
+    #[test]
+    fn test_restart() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        game.handle_turn(0, 0);
+        assert!(game.board.get_cell(0, 0).is_some());
+        assert_ne!(game.board.get_cell(0, 0), Some(Cell::Empty));
+
+        game.handle_restart();
+        assert_eq!(game.board.get_cell(0, 0), Some(Cell::Empty));
+        assert!(!game.game_in_progress);
+    }
+
+    #[test]
+    fn test_undo_move_reverts_last_stone() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.handle_board_start().unwrap();
+        game.handle_board_move(10, 10, 2).unwrap();
+        game.handle_board_move(11, 10, 1).unwrap();
+
+        assert_eq!(game.undo_move(), Some((11, 10)));
+        assert_eq!(game.board.get_cell(11, 10), Some(Cell::Empty));
+        assert_eq!(game.undo_move(), Some((10, 10)));
+        assert_eq!(game.undo_move(), None);
+        assert_eq!(game.evaluate_position(), 0);
+    }
+
+    #[test]
+    fn test_search_timeout_leaves_no_stray_stones() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.handle_info("timeout_turn", "300");
+
+        game.handle_turn(10, 10);
+        game.handle_turn(11, 11);
+
+        assert_eq!(game.board.occupied().count(), 4);
+        assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+    }
+
+    #[test]
+    fn test_game_over_win() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        for x in 0..5 {
+            game.board.set_cell(x, 0, Cell::MyStone).unwrap();
+        }
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::MyStone)));
+
+        game.handle_restart();
+        game.handle_start(20);
+
+        for x in 0..5 {
+            game.board.set_cell(x, 0, Cell::OpStone).unwrap();
+        }
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::OpStone)));
+    }
+
+    #[test]
+    fn test_game_over_draw() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        assert_eq!(game.game_over(), None);
+    }
+
+    #[test]
+    fn test_turn_handling_loss() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        for x in 0..4 {
+            game.handle_board_move(x, 0, 2).unwrap();
+        }
+
+        let response = game.handle_turn(4, 0);
+
+        assert!(!response.contains("ERROR"));
+        assert!(!game.game_in_progress);
+    }
+
+    #[test]
+    fn test_make_move_win() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        for x in 6..10 {
+            game.board.set_cell(x, 10, Cell::MyStone).unwrap();
+        }
+
+        let response = game.handle_turn(0, 0);
+
+        let parts: Vec<&str> = response.split(',').collect();
+        let bot_x: usize = parts[0].parse().unwrap();
+        let bot_y: usize = parts[1].parse().unwrap();
+
+        assert_eq!(game.board.get_cell(bot_x, bot_y), Some(Cell::MyStone));
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::MyStone)));
+        assert!(!game.game_in_progress);
+    }
+
+    #[test]
+    fn test_block_opponent_immediate_win() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        for x in 0..3 {
+            game.board.set_cell(x, 5, Cell::OpStone).unwrap();
+        }
+
+        let response = game.handle_turn(3, 5);
+
+        assert_eq!(response, "4,5");
+        assert_eq!(game.board.get_cell(4, 5), Some(Cell::MyStone));
+        assert_eq!(game.game_over(), None);
+        assert!(game.game_in_progress);
+    }
+
+    #[test]
+    fn test_blocks_open_four_at_either_end() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for x in 7..10 {
+            game.place_stone(x, 12, Cell::OpStone);
+        }
+        game.place_stone(8, 8, Cell::MyStone);
+        game.place_stone(12, 4, Cell::MyStone);
+
+        let response = game.handle_turn(10, 12);
+
+        assert!(
+            response == "6,12" || response == "11,12",
+            "{} does not block the open four",
+            response
+        );
+        assert_eq!(game.game_over(), None);
+    }
+
+    #[test]
+    fn test_own_four_beats_blocking_open_four() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for x in 7..10 {
+            game.place_stone(x, 12, Cell::OpStone);
+        }
+        for y in 3..7 {
+            game.place_stone(15, y, Cell::MyStone);
+        }
+        game.place_stone(15, 2, Cell::OpStone);
+
+        let response = game.handle_turn(10, 12);
+
+        assert_eq!(response, "15,7");
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::MyStone)));
+    }
+
+    #[test]
+    fn test_prioritize_win_over_block() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        for x in 6..10 {
+            game.place_stone(x, 10, Cell::MyStone);
+        }
+        game.place_stone(5, 10, Cell::OpStone);
+
+        for x in 0..4 {
+            game.place_stone(x, 5, Cell::OpStone);
+        }
+
+        let response = game.handle_turn(15, 15);
+
+        assert_eq!(response, "10,10");
+        assert_eq!(game.board.get_cell(10, 10), Some(Cell::MyStone));
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::MyStone)));
+        assert!(!game.game_in_progress);
+    }
+
+    #[test]
+    fn test_recover_move_preserves_turn_parity() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(10, 10, Cell::MyStone);
+        game.place_stone(11, 11, Cell::OpStone);
+
+        // Dies with a stone of ours half placed, as a search would.
+        game.before_search = Some(|game| {
+            game.place_stone(3, 3, Cell::MyStone);
+            panic!("injected search failure");
+        });
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| game.handle_turn(9, 9)));
+        assert!(result.is_err());
+        game.before_search = None;
+
+        let response = game.recover_move();
+        assert_ne!(response, "3,3");
+        let parts: Vec<&str> = response.split(',').collect();
+        let x: usize = parts[0].parse().unwrap();
+        let y: usize = parts[1].parse().unwrap();
+        assert_eq!(game.board.get_cell(x, y), Some(Cell::MyStone));
+        assert_eq!(game.board.get_cell(9, 9), Some(Cell::OpStone));
+
+        let mine = game
+            .board
+            .iter_indices()
+            .filter(|&(x, y)| game.board.get_cell(x, y) == Some(Cell::MyStone))
+            .count();
+        let theirs = game
+            .board
+            .iter_indices()
+            .filter(|&(x, y)| game.board.get_cell(x, y) == Some(Cell::OpStone))
+            .count();
+        assert_eq!(mine, 2);
+        assert_eq!(theirs, 2);
+        assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+    }
+
+    #[test]
+    fn test_recover_move_plays_the_best_move_searched_so_far() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(10, 10, Cell::MyStone);
+        game.place_stone(11, 11, Cell::OpStone);
+
+        game.before_search = Some(|game| {
+            game.last_search = Some(SearchReport {
+                best_move: (12, 8),
+                score: 0,
+                depth: 3,
+            });
+            panic!("injected search failure");
+        });
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| game.handle_turn(9, 9)));
+        assert!(result.is_err());
+        game.before_search = None;
+
+        assert_eq!(game.recover_move(), "12,8");
+        assert_eq!(game.board.get_cell(9, 9), Some(Cell::OpStone));
+    }
+
+    #[test]
+    fn test_recover_move_ignores_a_search_from_an_earlier_move() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(10, 10, Cell::MyStone);
+        game.last_search = Some(SearchReport {
+            best_move: (12, 8),
+            score: 0,
+            depth: 3,
+        });
+
+        game.before_search = Some(|_| panic!("injected search failure"));
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| game.handle_turn(9, 9)));
+        assert!(result.is_err());
+        game.before_search = None;
+
+        assert_ne!(game.recover_move(), "12,8");
+    }
+
+    #[test]
+    fn test_time_budget_zero_turn_uses_match_time() {
+        let mut game = GameState::new();
+        game.handle_info("timeout_turn", "0");
+        game.handle_info("timeout_match", "180000");
+        assert!(game.move_time_budget() >= Duration::from_secs(5));
+
+        game.handle_info("time_left", "100000");
+        let budget = game.move_time_budget();
+        assert!(budget >= Duration::from_secs(4));
+        assert!(budget < Duration::from_secs(100));
+    }
+
+    #[test]
+    fn test_time_budget_turn_limit_caps_match_share() {
+        let mut game = GameState::new();
+        game.handle_info("timeout_turn", "1000");
+        game.handle_info("timeout_match", "180000");
+        assert!(game.move_time_budget() < Duration::from_secs(1));
+
+        game.handle_info("time_left", "2000");
+        assert!(game.move_time_budget() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_format_score_mate() {
+        assert_eq!(format_score(SCORE_WIN - 1), "mate in 1");
+        assert_eq!(format_score(SCORE_WIN - 5), "mate in 3");
+        assert_eq!(format_score(-(SCORE_WIN - 2)), "mated in 1");
+        assert_eq!(format_score(1234), "1234");
+    }
+
+    #[test]
+    fn test_analysis_message_precedes_winning_move() {
+        let mut game = GameState::with_config(GameConfig::builder().analysis(true).build());
+        game.handle_start(20);
+        for x in 6..10 {
+            game.place_stone(x, 10, Cell::MyStone);
+        }
+        game.place_stone(5, 10, Cell::OpStone);
+
+        let response = game.handle_turn(0, 0);
+        let lines: Vec<&str> = response.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "MESSAGE move=10,10 score=mate in 1 depth=1");
+        assert_eq!(lines[1], "MESSAGE pv 10,10");
+        assert_eq!(lines[2], "10,10");
+    }
+
+    #[test]
+    fn test_resign_threshold_switches_to_fast_play() {
+        let mut game =
+            GameState::with_config(GameConfig::builder().resign_threshold(Some(50000)).build());
+        game.handle_start(20);
+        for y in [2, 6, 10, 14] {
+            for x in 5..9 {
+                game.place_stone(x, y, Cell::OpStone);
+            }
+        }
+
+        let first = game.handle_turn(0, 19);
+        let second = game.handle_turn(19, 19);
+        assert!(!first.contains("resign-eval"));
+        assert!(!second.contains("resign-eval"));
+        assert!(!game.fast_play);
+
+        let third = game.handle_turn(19, 0);
+        let lines: Vec<&str> = third.lines().collect();
+        assert_eq!(lines[0], "MESSAGE resign-eval");
+        assert!(game.fast_play);
+        assert!(game.move_time_budget() <= FAST_PLAY_BUDGET);
+
+        let (x, y) = lines[1].split_once(',').unwrap();
+        let (x, y): (usize, usize) = (x.parse().unwrap(), y.parse().unwrap());
+        assert_eq!(game.board.get_cell(x, y), Some(Cell::MyStone));
+    }
+
+    #[test]
+    fn test_resign_threshold_disabled_by_default() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for x in 5..9 {
+            game.place_stone(x, 10, Cell::OpStone);
+        }
+        for _ in 0..RESIGN_PATIENCE {
+            assert!(!game.update_resign_state(0, 0));
+        }
+        assert!(!game.fast_play);
+    }
+
+    #[test]
+    fn test_memory_usage_sums_known_tables() {
+        use std::mem::size_of;
+        let mut game = GameState::with_config(GameConfig::builder().max_memory(1 << 24).build());
+        game.handle_start(20);
+        let usage = game.memory_usage();
+
+        let tt_budget = ((1 << 24) - MoveCache::MAX_BYTES) / 2;
+        assert!(
+            usage.tt <= tt_budget && usage.tt > tt_budget / 2,
+            "{:?}",
+            usage
+        );
+        assert!(usage.eval >= 2 * MAX_BOARD_CELLS * 4 * size_of::<i32>());
+        assert!(usage.board >= size_of::<Board>() + 400 * size_of::<Cell>());
+        assert_eq!(
+            usage.move_cache,
+            MOVE_CACHE_SLOTS * size_of::<Option<CachedMoves>>()
+        );
+        assert_eq!(
+            usage.heuristics,
+            MAX_SEARCH_DEPTH * 2 * size_of::<Option<(usize, usize)>>()
+                + 2 * MAX_BOARD_CELLS * size_of::<i32>()
+                + MAX_SEARCH_PLY * size_of::<u64>()
+        );
+        assert_eq!((usage.moves, usage.pending), (0, 0));
+
+        for x in 0..3 {
+            game.push_move(x, 0, Cell::OpStone);
+        }
+        game.begin_pending_move(None);
+        let usage = game.memory_usage();
+        assert!(usage.moves >= 3 * (size_of::<(usize, usize, Cell)>() + size_of::<Instant>()));
+        assert!(
+            usage.pending
+                >= size_of::<IncrementalScores>() + size_of::<Board>() + 400 * size_of::<Cell>()
+        );
+        assert_eq!(
+            usage.total(),
+            usage.tt
+                + usage.eval
+                + usage.board
+                + usage.move_cache
+                + usage.heuristics
+                + usage.moves
+                + usage.pending
+        );
+    }
+
+    #[test]
+    fn test_forced_win_score_open_four_continuation() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for x in 8..11 {
+            game.place_stone(x, 10, Cell::MyStone);
+        }
+        game.place_stone(9, 9, Cell::OpStone);
+        game.place_stone(10, 11, Cell::OpStone);
+
+        let (_, wins) = game.scan_candidates();
+        assert_eq!(
+            GameState::forced_win_score(wins, Cell::MyStone, 0),
+            Some(SCORE_WIN - 3)
+        );
+        assert_eq!(GameState::forced_win_score(wins, Cell::OpStone, 0), None);
+
+        for x in 1..5 {
+            game.place_stone(x, 0, Cell::OpStone);
+        }
+        let (_, wins) = game.scan_candidates();
+        assert_eq!(GameState::forced_win_score(wins, Cell::MyStone, 0), None);
+        assert_eq!(
+            GameState::forced_win_score(wins, Cell::OpStone, 4),
+            Some(SCORE_WIN - 5)
+        );
+    }
+
+    #[test]
+    fn test_search_selects_unstoppable_open_four() {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_millis(1000))
+                .analysis(true)
+                .build(),
+        );
+        game.handle_start(20);
+        for x in 8..11 {
+            game.place_stone(x, 10, Cell::MyStone);
+        }
+        game.place_stone(9, 9, Cell::OpStone);
+
+        let response = game.handle_turn(10, 11);
+        let lines: Vec<&str> = response.lines().collect();
+        assert!(lines[0].contains("score=mate in 2"), "{}", lines[0]);
+        assert!(lines[2] == "7,10" || lines[2] == "11,10", "{}", lines[2]);
+    }
+
+    #[test]
+    fn test_principal_variation_follows_stored_replies_until_a_miss() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(10, 10, Cell::MyStone);
+        let line = [
+            (11, 11, Cell::OpStone),
+            (9, 9, Cell::MyStone),
+            (12, 12, Cell::OpStone),
+        ];
+        for &(x, y, player) in &line {
+            let hash = game.compute_hash_with_turn(player);
+            game.tt.store(hash, 3, 0, TTFlag::Exact, Some((x, y)));
+            game.place_stone(x, y, player);
+        }
+        for &(x, y, _) in line.iter().rev() {
+            game.remove_stone(x, y);
+        }
+
+        let pv = game.principal_variation((10, 10));
+        assert_eq!(pv, vec![(10, 10), (11, 11), (9, 9), (12, 12)]);
+        assert_eq!(game.board.stone_count(), 1);
+        assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+    }
+
+    #[test]
+    fn test_position_repeated_on_the_search_line_scores_neutral() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for x in 5..8 {
+            game.push_move(x, 10, Cell::MyStone);
+        }
+        game.push_move(0, 0, Cell::OpStone);
+        game.push_move(0, 2, Cell::OpStone);
+        let deadline = far_deadline();
+        let hash = game.compute_hash_with_turn(Cell::MyStone);
+        // A table that sends the line straight back here, as a collision
+        // would.
+        game.tt.store(hash, 1, 0, TTFlag::LowerBound, Some((8, 10)));
+
+        let winning = game.negamax(
+            3,
+            MIN_EVAL_SCORE,
+            MAX_EVAL_SCORE,
+            Cell::MyStone,
+            deadline,
+            0,
+        );
+        assert!(winning.unwrap() >= MATE_THRESHOLD);
+        assert!(game.search_path.is_empty());
+
+        game.search_path.push(hash);
+        game.nodes = 0;
+        let repeated = game.negamax(
+            3,
+            MIN_EVAL_SCORE,
+            MAX_EVAL_SCORE,
+            Cell::MyStone,
+            deadline,
+            0,
+        );
+        assert_eq!(repeated, Some(0));
+        assert_eq!(game.nodes, 1);
+    }
+
+    #[test]
+    fn test_search_line_is_unwound_when_time_runs_out() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.push_move(10, 10, Cell::OpStone);
+        game.push_move(11, 11, Cell::MyStone);
+        let deadline = Instant::now() + Duration::from_millis(20);
+        game.negamax(
+            12,
+            MIN_EVAL_SCORE,
+            MAX_EVAL_SCORE,
+            Cell::OpStone,
+            deadline,
+            0,
+        );
+        assert!(game.search_path.is_empty());
+    }
+
+    #[test]
+    fn test_principal_variation_stops_at_an_illegal_stored_move() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(10, 10, Cell::MyStone);
+        let hash = game.compute_hash_with_turn(Cell::OpStone);
+        game.tt.store(hash, 3, 0, TTFlag::Exact, Some((10, 10)));
+        assert_eq!(game.principal_variation((10, 10)), vec![(10, 10)]);
+    }
+
+    #[test]
+    fn test_analysis_pv_starts_with_move_and_stays_legal() {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_millis(1000))
+                .analysis(true)
+                .build(),
+        );
+        game.handle_start(20);
+        game.place_stone(9, 10, Cell::MyStone);
+        game.place_stone(10, 10, Cell::MyStone);
+        game.place_stone(9, 9, Cell::OpStone);
+        let before = game.board.clone();
+
+        let response = game.handle_turn(11, 11);
+        let lines: Vec<&str> = response.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let played = lines[2];
+        let pv: Vec<&str> = lines[1]
+            .strip_prefix("MESSAGE pv ")
+            .unwrap()
+            .split(' ')
+            .collect();
+        assert_eq!(pv[0], played);
+
+        let mut replay = GameState::new();
+        replay.handle_start(20);
+        replay.board = before;
+        replay.board.set_cell(11, 11, Cell::OpStone).unwrap();
+        let mut player = Cell::MyStone;
+        for mv in pv {
+            let (x, y) = mv.split_once(',').unwrap();
+            let (x, y): (usize, usize) = (x.parse().unwrap(), y.parse().unwrap());
+            assert!(replay.validate_move(x, y).is_ok(), "illegal pv move {}", mv);
+            replay.board.set_cell(x, y, player).unwrap();
+            player = player.opponent();
+        }
+    }
+
+    #[test]
+    fn test_search_stores_root_move_in_tt() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(10, 10, Cell::OpStone);
+        game.find_best_move(Instant::now() + Duration::from_millis(300));
+
+        let report = game.last_search.expect("search should have run");
+        let hash = game.compute_hash_with_turn(Cell::MyStone);
+        let (bx, by) = report.best_move;
+        let entry = game.tt.probe(hash).expect("root entry should be stored");
+        assert_eq!(entry.flag, TTFlag::Exact);
+        assert_eq!(entry.best_move, Some((bx as u8, by as u8)));
+    }
+
+    #[test]
+    fn test_upper_bound_entry_does_not_cut_above_alpha() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.place_stone(10, 10, Cell::OpStone);
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        let exact = game
+            .negamax(
+                1,
+                MIN_EVAL_SCORE,
+                MAX_EVAL_SCORE,
+                Cell::MyStone,
+                deadline,
+                0,
+            )
+            .unwrap();
+        game.tt.clear();
+
+        let hash = game.compute_hash_with_turn(Cell::MyStone);
+        game.tt
+            .store(hash, 5, exact + 500, TTFlag::UpperBound, None);
+        let value = game
+            .negamax(1, exact - 100, exact + 100, Cell::MyStone, deadline, 0)
+            .unwrap();
+        assert_eq!(value, exact);
+    }
+
+    #[test]
+    fn test_info_max_memory_resizes_tt() {
+        let mut game = GameState::new();
+        assert!(game.memory_usage().total() <= DEFAULT_MAX_MEMORY);
+
+        game.handle_info("max_memory", "8388608");
+        assert!(game.tt.memory_bytes() <= 4 * 1024 * 1024);
+        assert!(game.memory_usage().total() <= 8 * 1024 * 1024);
+
+        game.handle_info("max_memory", "0");
+        assert_eq!(game.config.max_memory, DEFAULT_MAX_MEMORY);
+        game.handle_info("max_memory", "lots");
+        assert_eq!(game.config.max_memory, DEFAULT_MAX_MEMORY);
+    }
+
+    #[test]
+    fn test_full_move_cache_fits_the_memory_budget() {
+        let mut game = GameState::new();
+        game.handle_info("max_memory", "8388608");
+        let full_list = vec![(0, 0); CANDIDATE_CAP];
+        for slot in 0..MOVE_CACHE_SLOTS as u64 {
+            game.move_cache
+                .insert(slot, &full_list, WinningMoves::default());
+        }
+        game.move_cache
+            .insert(0, &vec![(0, 0); CANDIDATE_CAP + 1], WinningMoves::default());
+
+        let usage = game.memory_usage();
+        assert_eq!(usage.move_cache, MoveCache::MAX_BYTES);
+        assert!(usage.total() <= 8 * 1024 * 1024, "{:?}", usage);
+    }
+
+    #[test]
+    fn test_tt_mate_scores_keep_distance_across_plies() {
+        let tt = TranspositionTable::with_capacity(1 << 16);
+        // At ply 4 the side to move mates in 2, landing the five at ply 7.
+        let mate_in_2 = SCORE_WIN - 7;
+        tt.store(42, 3, score_to_tt(mate_in_2, 4), TTFlag::Exact, None);
+
+        let stored = tt.probe(42).unwrap().score;
+        assert_eq!(score_from_tt(stored, 4), mate_in_2);
+        // Reached at ply 2, the same position still mates three plies later.
+        assert_eq!(score_from_tt(stored, 2), SCORE_WIN - 5);
+        assert_eq!(
+            score_from_tt(score_to_tt(-mate_in_2, 4), 6),
+            -(SCORE_WIN - 9)
+        );
+        assert_eq!(score_from_tt(score_to_tt(1234, 4), 9), 1234);
+    }
+
+    #[test]
+    fn test_push_and_pop_moves_restore_empty_board() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        let empty_hash = game.board.hash();
+        let moves = [
+            (10, 10, Cell::OpStone),
+            (11, 11, Cell::MyStone),
+            (9, 10, Cell::OpStone),
+            (12, 12, Cell::MyStone),
+        ];
+        for &(x, y, cell) in &moves {
+            game.push_move(x, y, cell);
+        }
+        assert_eq!(game.board.stone_count(), moves.len());
+        assert_ne!(game.board.hash(), empty_hash);
+
+        for &expected in moves.iter().rev() {
+            assert_eq!(game.pop_move(), Some(expected));
+        }
+        assert_eq!(game.pop_move(), None);
+        assert_eq!(game.board.stone_count(), 0);
+        assert_eq!(game.board.hash(), empty_hash);
+        assert_eq!(game.evaluate_position(), 0);
+    }
+
+    fn swap_game(opening: &[(usize, usize, Cell)]) -> GameState {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .max_depth(2)
+                .timeout_turn(Duration::from_millis(2000))
+                .build(),
+        );
+        game.handle_start(20);
+        for &(x, y, cell) in opening {
+            game.push_move(x, y, cell);
+        }
+        game
+    }
+
+    #[test]
+    fn test_swap_takes_the_stronger_opening() {
+        let opening = [
+            (9, 9, Cell::OpStone),
+            (10, 10, Cell::OpStone),
+            (10, 12, Cell::MyStone),
+        ];
+        let mut game = swap_game(&opening);
+        assert_eq!(game.handle_swap(), "SWAP");
+        assert_eq!(game.board.get_cell(9, 9), Some(Cell::MyStone));
+        assert_eq!(game.board.get_cell(10, 12), Some(Cell::OpStone));
+        assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+        assert!(game.evaluation() > 0);
+    }
+
+    #[test]
+    fn test_swap_decision_is_deterministic() {
+        let opening = [
+            (9, 9, Cell::MyStone),
+            (10, 10, Cell::MyStone),
+            (10, 12, Cell::OpStone),
+        ];
+        let first = swap_game(&opening).handle_swap();
+        let second = swap_game(&opening).handle_swap();
+        assert_ne!(first, "SWAP");
+        assert_eq!(first, second);
+    }
+
+    fn renju_double_three(black: Cell) -> GameState {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .rule(RuleSet::Renju)
+                .timeout_turn(Duration::from_millis(300))
+                .build(),
+        );
+        game.handle_start(20);
+        let white = black.opponent();
+        for (i, &(x, y)) in [(8, 10), (10, 8), (9, 10), (10, 9)].iter().enumerate() {
+            game.push_move(x, y, black);
+            game.push_move(2 * i, 0, white);
+        }
+        game
+    }
+
+    #[test]
+    fn test_renju_forbids_double_three_for_black_only() {
+        let game = renju_double_three(Cell::MyStone);
+        assert_eq!(game.black_player(), Cell::MyStone);
+        assert_eq!(
+            game.validate_move_for(10, 10, Cell::MyStone),
+            Err(GameError::Forbidden)
+        );
+        assert!(game.validate_move(10, 10).is_ok());
+
+        let game = renju_double_three(Cell::OpStone);
+        assert_eq!(game.black_player(), Cell::OpStone);
+        assert!(game.validate_move_for(10, 10, Cell::MyStone).is_ok());
+        assert_eq!(
+            game.validate_move_for(10, 10, Cell::OpStone),
+            Err(GameError::Forbidden)
+        );
+    }
+
+    #[test]
+    fn test_begin_makes_us_the_first_player() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        assert_eq!(game.first_player(), None);
+        game.handle_begin();
+        assert_eq!(game.first_player(), Some(Cell::MyStone));
+        assert_eq!(game.black_player(), Cell::MyStone);
+
+        game.handle_turn(0, 0);
+        assert_eq!(game.first_player(), Some(Cell::MyStone));
+        game.handle_restart();
+        assert_eq!(game.first_player(), None);
+    }
+
+    #[test]
+    fn test_opening_turn_makes_the_opponent_the_first_player() {
+        let mut game = GameState::with_config(GameConfig::builder().rule(RuleSet::Renju).build());
+        game.handle_start(20);
+        game.handle_turn(10, 10);
+        assert_eq!(game.first_player(), Some(Cell::OpStone));
+        assert_eq!(game.black_player(), Cell::OpStone);
+        assert!(game.exact_five_for(Cell::OpStone));
+        assert!(!game.exact_five_for(Cell::MyStone));
+
+        game.swap_colors();
+        assert_eq!(game.first_player(), Some(Cell::MyStone));
+    }
+
+    #[test]
+    fn test_freestyle_never_forbids() {
+        let mut game = renju_double_three(Cell::MyStone);
+        game.config.rule = RuleSet::Freestyle;
+        assert!(game.validate_move_for(10, 10, Cell::MyStone).is_ok());
+    }
+
+    #[test]
+    fn test_black_engine_avoids_forbidden_point() {
+        let mut game = renju_double_three(Cell::MyStone);
+        game.begin_pending_move(None);
+        let response = game.generate_move();
+        let parts: Vec<&str> = response.split(',').collect();
+        let x: usize = parts[0].parse().unwrap();
+        let y: usize = parts[1].trim().parse().unwrap();
+        assert_ne!((x, y), (10, 10));
+    }
+
+    #[test]
+    fn test_overline_wins_in_freestyle_but_not_standard() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for x in [3, 4, 5, 7, 8] {
+            game.push_move(x, 7, Cell::OpStone);
+        }
+        assert!(game.detect_threats(6, 7, Cell::OpStone).fives > 0);
+        game.push_move(6, 7, Cell::OpStone);
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::OpStone)));
+
+        game.handle_info("rule", "1");
+        assert_eq!(game.game_over(), None);
+        game.pop_move();
+        assert_eq!(game.detect_threats(6, 7, Cell::OpStone).fives, 0);
+    }
+
+    #[test]
+    fn test_renju_overline_counts_for_white_only() {
+        let mut game = GameState::with_config(GameConfig::builder().rule(RuleSet::Renju).build());
+        game.handle_start(20);
+        game.push_move(0, 19, Cell::MyStone);
+        for x in 3..9 {
+            game.push_move(x, 7, Cell::OpStone);
+        }
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::OpStone)));
+
+        game.handle_restart();
+        game.push_move(0, 19, Cell::OpStone);
+        for x in 3..9 {
+            game.push_move(x, 7, Cell::OpStone);
+        }
+        assert_eq!(game.game_over(), None);
+    }
+
+    #[test]
+    fn test_default_time_budget_is_below_turn_limit() {
+        let game = GameState::new();
+        assert_eq!(game.move_time_budget(), Duration::from_millis(4900));
+    }
+
+    #[test]
+    fn test_turn_respects_timeout_turn() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.handle_info("timeout_turn", "300");
+
+        let started = Instant::now();
+        let response = game.handle_turn(10, 10);
+        assert!(started.elapsed() < Duration::from_millis(600));
+        assert!(!response.contains("ERROR"));
+        assert!(game.last_search.is_some());
+    }
+
+    #[test]
+    fn test_emergency_move_returns_valid_coords() {
+        let game = GameState::new();
+        let response = game.emergency_move();
+        let parts: Vec<&str> = response.split(',').collect();
+        assert_eq!(parts.len(), 2);
+        let x: usize = parts[0].parse().unwrap();
+        let y: usize = parts[1].parse().unwrap();
+        assert!(x < 20 && y < 20);
+    }
+
+    #[test]
+    fn test_emergency_move_full_board() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for y in 0..20 {
+            for x in 0..20 {
+                game.board.set_cell(x, y, Cell::MyStone).unwrap();
+            }
+        }
+        let response = game.emergency_move();
+        assert_eq!(response, BOARD_FULL);
+    }
+
+    #[test]
+    fn test_move_request_on_a_full_board_answers_board_full() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for y in 0..20 {
+            for x in 0..20 {
+                game.board.set_cell(x, y, Cell::MyStone).unwrap();
+            }
+        }
+        assert_eq!(game.generate_move(), BOARD_FULL);
+        assert_eq!(game.handle_begin(), BOARD_FULL);
+        assert_eq!(game.recover_move(), BOARD_FULL);
+    }
+
+    #[test]
+    fn test_turn_filling_the_last_cell_answers_board_full() {
+        let mut game = GameState::new();
+        game.handle_start(5);
+        let rows = ["XXOOX", "OOXXO", "XXOOX", "OOXXO", "XXOO."];
+        game.load_position(&Board::from_grid(&rows).unwrap(), Cell::MyStone)
+            .unwrap();
+        assert_eq!(game.handle_turn(4, 4), BOARD_FULL);
+        assert_eq!(game.game_over(), Some(GameResult::Draw));
+        assert_eq!(game.board().stone_count(), 25);
+    }
+
+    #[test]
+    fn test_out_of_bounds_coordinates() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        assert!(game.handle_turn(100, 100).contains("ERROR"));
+        assert!(game.handle_turn(0, 100).contains("ERROR"));
+        assert!(game.handle_turn(100, 0).contains("ERROR"));
+    }
+
+    #[test]
+    fn test_incremental_scores_single_stone() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        game.place_stone(10, 10, Cell::MyStone);
+
+        let incremental = game.evaluate_position();
+        let full_scan = game.evaluate_position_full_scan();
+        assert_eq!(incremental, full_scan);
+    }
+
+    #[test]
+    fn test_incremental_scores_horizontal_line() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        for x in 5..9 {
+            game.place_stone(x, 10, Cell::MyStone);
+        }
+
+        let incremental = game.evaluate_position();
+        let full_scan = game.evaluate_position_full_scan();
+        assert_eq!(incremental, full_scan);
+    }
+
+    #[test]
+    fn test_incremental_scores_mixed_stones() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        game.place_stone(10, 10, Cell::MyStone);
+        game.place_stone(11, 10, Cell::MyStone);
+        game.place_stone(12, 10, Cell::OpStone);
+        game.place_stone(10, 11, Cell::OpStone);
+        game.place_stone(10, 12, Cell::OpStone);
+
+        let incremental = game.evaluate_position();
+        let full_scan = game.evaluate_position_full_scan();
+        assert_eq!(incremental, full_scan);
+    }
+
+    #[test]
+    fn test_incremental_scores_after_removal() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        game.place_stone(10, 10, Cell::MyStone);
+        game.place_stone(11, 10, Cell::MyStone);
+        game.place_stone(12, 10, Cell::MyStone);
+
+        let before_removal = game.evaluate_position();
+        assert_eq!(before_removal, game.evaluate_position_full_scan());
+
+        game.remove_stone(11, 10);
+
+        let after_removal = game.evaluate_position();
+        let full_scan = game.evaluate_position_full_scan();
+        assert_eq!(after_removal, full_scan);
+    }
+
+    #[test]
+    fn test_incremental_scores_place_remove_place() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        game.place_stone(10, 10, Cell::MyStone);
+        game.place_stone(11, 10, Cell::OpStone);
+
+        game.remove_stone(11, 10);
+        game.place_stone(11, 10, Cell::MyStone);
+
+        let incremental = game.evaluate_position();
+        let full_scan = game.evaluate_position_full_scan();
+        assert_eq!(incremental, full_scan);
+    }
+
+    #[test]
+    fn test_incremental_scores_diagonal() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        for i in 0..4 {
+            game.place_stone(5 + i, 5 + i, Cell::MyStone);
+        }
+        game.place_stone(4, 4, Cell::OpStone);
+
+        let incremental = game.evaluate_position();
+        let full_scan = game.evaluate_position_full_scan();
+        assert_eq!(incremental, full_scan);
+    }
+
+    #[test]
+    fn test_incremental_scores_complex_pattern() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        let moves = [
+            (10, 10, Cell::MyStone),
+            (11, 10, Cell::OpStone),
+            (9, 10, Cell::MyStone),
+            (10, 9, Cell::OpStone),
+            (10, 11, Cell::MyStone),
+            (12, 10, Cell::OpStone),
+            (8, 10, Cell::MyStone),
+            (10, 8, Cell::OpStone),
+        ];
+
+        for (x, y, player) in moves {
+            game.place_stone(x, y, player);
+            assert_eq!(
+                game.evaluate_position(),
+                game.evaluate_position_full_scan(),
+                "Mismatch after placing stone at ({}, {})",
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn test_incremental_scores_many_removals() {
         let mut game = GameState::new();
         game.handle_start(20);
 
-        assert!(game.validate_move(10, 10).is_ok());
+        for i in 0..5 {
+            game.place_stone(5 + i, 10, Cell::MyStone);
+        }
+        assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
 
-        assert!(game.validate_move(20, 10).is_err());
-        assert!(game.validate_move(10, 20).is_err());
+        for i in (0..5).rev() {
+            game.remove_stone(5 + i, 10);
+            assert_eq!(
+                game.evaluate_position(),
+                game.evaluate_position_full_scan(),
+                "Mismatch after removing stone at ({}, 10)",
+                5 + i
+            );
+        }
 
-        game.board.set_cell(10, 10, Cell::MyStone).unwrap();
-        assert!(game.validate_move(10, 10).is_err());
-        assert_eq!(
-            game.validate_move(10, 10),
-            Err("ERROR cell already occupied")
-        );
+        assert_eq!(game.evaluate_position(), 0);
+    }
 
-        game.board.set_cell(11, 11, Cell::Forbidden).unwrap();
-        assert!(game.validate_move(11, 11).is_err());
-        assert_eq!(game.validate_move(11, 11), Err("ERROR move forbidden"));
+    #[test]
+    fn test_centre_stone_evaluates_above_corner_stone() {
+        let mut centre = GameState::new();
+        centre.handle_start(20);
+        centre.place_stone(10, 10, Cell::MyStone);
+        let mut corner = GameState::new();
+        corner.handle_start(20);
+        corner.place_stone(0, 0, Cell::MyStone);
+
+        let gain = centre.evaluate_position() - corner.evaluate_position();
+        assert!(gain > 0);
+        assert!(gain < EvalWeights::DEFAULT.closed_two * 2, "gain {}", gain);
     }
 
     #[test]
-    fn test_fallback_move_center_then_nearby() {
+    fn test_centre_bonus_fades_as_the_board_fills() {
         let mut game = GameState::new();
         game.handle_start(20);
+        game.place_stone(10, 10, Cell::MyStone);
+        let early = game.center_bonus();
+        for i in 0..CENTER_BONUS_STONES / 2 {
+            game.place_stone(i, 0, Cell::MyStone);
+            game.place_stone(i, 19, Cell::OpStone);
+        }
+        assert!(early > 0);
+        assert_eq!(game.center_bonus(), 0);
+    }
 
-        assert_eq!(game.fallback_move(), Some((10, 10)));
-
-        game.board.set_cell(10, 10, Cell::OpStone).unwrap();
-        let response = game.fallback_move();
-        assert!(response.is_some());
-        assert_ne!(response, Some((10, 10)));
+    #[test]
+    fn test_make_unmake_deltas_match_full_recompute_on_random_playouts() {
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..5 {
+            let mut game = GameState::new();
+            game.handle_start(20);
+            let mut played = Vec::new();
+            let mut lines = 0;
+            for ply in 0..60 {
+                let (x, y) = loop {
+                    let cell = ((next() % 20) as usize, (next() % 20) as usize);
+                    if game.board.is_empty(cell.0, cell.1) {
+                        break cell;
+                    }
+                };
+                let player = if ply % 2 == 0 {
+                    Cell::MyStone
+                } else {
+                    Cell::OpStone
+                };
+                lines += game.place_stone(x, y, player);
+                played.push((x, y));
+                assert_eq!(lines, game.inc_scores.evaluate_position());
+                assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+            }
+            for &(x, y) in played.iter().rev() {
+                lines += game.remove_stone(x, y);
+                assert_eq!(lines, game.inc_scores.evaluate_position());
+                assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+            }
+            assert_eq!(lines, 0);
+        }
     }
 
     #[test]
-    fn test_generate_candidates_empty_board_centers() {
+    fn test_incremental_scores_rebuild() {
         let mut game = GameState::new();
         game.handle_start(20);
 
-        let candidates = game.generate_candidates();
-        let expected = vec![(10, 10), (9, 9), (9, 10), (10, 9)];
-        assert_eq!(candidates, expected);
+        game.board.set_cell(10, 10, Cell::MyStone).unwrap();
+        game.board.set_cell(11, 10, Cell::MyStone).unwrap();
+        game.board.set_cell(12, 10, Cell::OpStone).unwrap();
+
+        game.inc_scores.rebuild_from_board(&game.board);
+
+        let incremental = game.evaluate_position();
+        let full_scan = game.evaluate_position_full_scan();
+        assert_eq!(incremental, full_scan);
+    }
+
+    fn place_pattern(
+        game: &mut GameState,
+        origin: (usize, usize),
+        dir: (isize, isize),
+        pattern: &str,
+    ) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        for (k, ch) in pattern.chars().enumerate() {
+            let x = (origin.0 as isize + dir.0 * k as isize) as usize;
+            let y = (origin.1 as isize + dir.1 * k as isize) as usize;
+            match ch {
+                'X' => {
+                    game.place_stone(x, y, Cell::MyStone);
+                }
+                'O' => {
+                    game.place_stone(x, y, Cell::OpStone);
+                }
+                _ => {}
+            }
+            assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+            cells.push((x, y));
+        }
+        cells
+    }
+
+    fn edge_origin(dir: (isize, isize)) -> (usize, usize) {
+        let x = if dir.0 == 0 { 5 } else { 0 };
+        let y = match dir.1 {
+            1 => 0,
+            -1 => 19,
+            _ => 5,
+        };
+        (x, y)
     }
 
     #[test]
-    fn test_generate_candidates_early_game_center_neighborhood() {
-        let mut game = GameState::new();
-        game.handle_start(20);
-        game.board.set_cell(0, 0, Cell::MyStone).unwrap();
+    fn test_split_four_scores_as_closed_four_in_every_direction() {
+        for &dir in &DIRECTIONS {
+            for shape in ["_XX_XX_", "_X_XXX_", "_XXX_X_"] {
+                let mut game = GameState::new();
+                game.handle_start(20);
+                let cells = place_pattern(&mut game, (5, 12), dir, shape);
+                for (k, c) in shape.chars().enumerate() {
+                    if c == 'X' {
+                        let (x, y) = cells[k];
+                        assert_eq!(
+                            line_pattern(&game.board, x, y, dir, Cell::MyStone),
+                            Pattern::ClosedFour,
+                            "{} at {}",
+                            shape,
+                            k
+                        );
+                    }
+                }
+            }
+        }
+    }
 
-        let candidates = game.generate_candidates();
-        assert!(candidates.contains(&(8, 8)));
-        assert!(candidates.contains(&(11, 11)));
+    #[test]
+    fn test_split_three_scores_as_open_three_in_every_direction() {
+        for &dir in &DIRECTIONS {
+            let mut game = GameState::new();
+            game.handle_start(20);
+            let cells = place_pattern(&mut game, (5, 12), dir, "_XX_X_");
+            for &k in &[1, 2, 4] {
+                let (x, y) = cells[k];
+                assert_eq!(
+                    line_pattern(&game.board, x, y, dir, Cell::MyStone),
+                    Pattern::OpenThree
+                );
+            }
+        }
     }
 
     #[test]
-    fn test_generate_candidates_excludes_forbidden_cells() {
-        let mut game = GameState::new();
-        game.handle_start(20);
-        game.board.set_cell(6, 6, Cell::MyStone).unwrap();
-        game.board.set_cell(5, 5, Cell::Forbidden).unwrap();
+    fn test_four_without_an_empty_completion_scores_nothing() {
+        for &dir in &DIRECTIONS {
+            let mut game = GameState::new();
+            game.handle_start(20);
+            let cells = place_pattern(&mut game, (3, 10), dir, "OXXXXO");
+            let (x, y) = cells[2];
+            assert_eq!(
+                line_pattern(&game.board, x, y, dir, Cell::MyStone),
+                Pattern::None
+            );
 
-        let candidates = game.generate_candidates();
-        assert!(!candidates.contains(&(5, 5)));
-        assert!(candidates.contains(&(6, 7)));
+            let mut game = GameState::new();
+            game.handle_start(20);
+            let cells = place_pattern(&mut game, edge_origin(dir), dir, "XXXXO");
+            let (x, y) = cells[0];
+            assert_eq!(
+                line_pattern(&game.board, x, y, dir, Cell::MyStone),
+                Pattern::None
+            );
+        }
     }
 
     #[test]
-    fn test_generate_candidates_bounds_and_radius() {
-        let mut game = GameState::new();
-        game.handle_start(20);
-        game.board.set_cell(0, 0, Cell::MyStone).unwrap();
+    fn test_four_with_one_completion_scores_as_closed_four() {
+        for &dir in &DIRECTIONS {
+            let mut game = GameState::new();
+            game.handle_start(20);
+            let cells = place_pattern(&mut game, (3, 10), dir, "OXXXX_");
+            let (x, y) = cells[1];
+            assert_eq!(
+                line_pattern(&game.board, x, y, dir, Cell::MyStone),
+                Pattern::ClosedFour
+            );
+            let (x, y) = cells[5];
+            assert_eq!(game.find_immediate_win(Cell::MyStone), Some((x, y)));
 
-        let candidates = game.generate_candidates();
-        assert!(candidates.contains(&(2, 2)));
-        assert!(!candidates.contains(&(3, 3)));
+            let mut game = GameState::new();
+            game.handle_start(20);
+            let cells = place_pattern(&mut game, (3, 10), dir, "OXX_XXO");
+            let (x, y) = cells[1];
+            assert_eq!(
+                line_pattern(&game.board, x, y, dir, Cell::MyStone),
+                Pattern::ClosedFour
+            );
+        }
     }
 
     #[test]
-    fn test_generate_candidates_cap() {
+    fn test_split_patterns_at_board_edge_are_closed() {
+        for &dir in &DIRECTIONS {
+            let mut game = GameState::new();
+            game.handle_start(20);
+            let cells = place_pattern(&mut game, edge_origin(dir), dir, "XX_XX");
+            let (x, y) = cells[0];
+            assert_eq!(
+                line_pattern(&game.board, x, y, dir, Cell::MyStone),
+                Pattern::ClosedFour
+            );
+
+            let mut game = GameState::new();
+            game.handle_start(20);
+            let cells = place_pattern(&mut game, edge_origin(dir), dir, "XX_X_");
+            let (x, y) = cells[3];
+            assert_eq!(
+                line_pattern(&game.board, x, y, dir, Cell::MyStone),
+                Pattern::ClosedThree
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_three_blocked_by_opponent_is_closed() {
         let mut game = GameState::new();
         game.handle_start(20);
+        let cells = place_pattern(&mut game, (5, 5), (1, 0), "OXX_X_");
+        let (x, y) = cells[4];
+        assert_eq!(
+            line_pattern(&game.board, x, y, (1, 0), Cell::MyStone),
+            Pattern::ClosedThree
+        );
+    }
 
-        for y in 0..20 {
-            for x in 0..20 {
-                if (x + y) % 3 == 0 {
-                    game.board.set_cell(x, y, Cell::MyStone).unwrap();
+    #[test]
+    fn test_pattern_table_matches_direct_scan_on_random_boards() {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for density in [20, 40, 60] {
+            for _ in 0..10 {
+                let mut board = Board::default();
+                for (x, y) in Board::default().iter_indices() {
+                    let roll = next() % 100;
+                    if roll < density / 2 {
+                        board.set_cell(x, y, Cell::MyStone).unwrap();
+                    } else if roll < density {
+                        board.set_cell(x, y, Cell::OpStone).unwrap();
+                    }
+                }
+                for (x, y) in board.iter_indices() {
+                    for &(dx, dy) in &DIRECTIONS {
+                        for player in [Cell::MyStone, Cell::OpStone] {
+                            let at = |k: isize| {
+                                cell_at(&board, x as isize + dx * k, y as isize + dy * k)
+                            };
+                            assert_eq!(
+                                line_pattern(&board, x, y, (dx, dy), player),
+                                window_pattern(at, player),
+                                "({}, {}) along ({}, {})",
+                                x,
+                                y,
+                                dx,
+                                dy
+                            );
+                        }
+                    }
                 }
             }
         }
-
-        let candidates = game.generate_candidates();
-        assert_eq!(candidates.len(), 80);
     }
 
     #[test]
-    fn test_turn_handling() {
+    fn test_long_split_line_falls_back_to_direct_scan() {
         let mut game = GameState::new();
         game.handle_start(20);
+        let cells = place_pattern(&mut game, (2, 5), (1, 0), "_XX_XXXX_");
+        let (x, y) = cells[1];
+        assert_eq!(
+            line_pattern(&game.board, x, y, (1, 0), Cell::MyStone),
+            Pattern::ClosedFour
+        );
+    }
 
-        assert_eq!(game.handle_turn(20, 20), "ERROR coordinates out of range");
-
-        let response = game.handle_turn(0, 0);
-        assert!(!response.contains("ERROR"));
-        assert_eq!(game.board.get_cell(0, 0), Some(Cell::OpStone));
-
-        let parts: Vec<&str> = response.split(',').collect();
-        let bot_x: usize = parts[0].parse().unwrap();
-        let bot_y: usize = parts[1].parse().unwrap();
-        assert_eq!(game.board.get_cell(bot_x, bot_y), Some(Cell::MyStone));
+    #[test]
+    fn test_custom_weights_change_the_evaluation() {
+        let weights = EvalWeights {
+            open_three: 2 * EvalWeights::DEFAULT.open_three,
+            ..EvalWeights::DEFAULT
+        };
+        let mut tuned = GameState::with_config(GameConfig::builder().eval_weights(weights).build());
+        let mut default = GameState::new();
+        for game in [&mut tuned, &mut default] {
+            game.handle_start(20);
+            place_pattern(game, (5, 5), (1, 0), "_XXX_");
+            game.push_move(15, 15, Cell::OpStone);
+        }
 
+        // Each of the three stones is part of the open three.
         assert_eq!(
-            game.handle_turn(bot_x, bot_y),
-            "ERROR cell already occupied"
+            tuned.evaluation() - default.evaluation(),
+            3 * EvalWeights::DEFAULT.open_three
+        );
+        assert_eq!(
+            tuned.evaluate_position(),
+            tuned.evaluate_position_full_scan()
         );
     }
 
     #[test]
-    fn test_board_command() {
+    fn test_two_gaps_do_not_join() {
         let mut game = GameState::new();
         game.handle_start(20);
+        let cells = place_pattern(&mut game, (5, 5), (1, 0), "_X_X_X_");
+        let (x, y) = cells[1];
+        assert_eq!(
+            line_pattern(&game.board, x, y, (1, 0), Cell::MyStone),
+            Pattern::OpenTwo
+        );
 
-        assert!(game.handle_board_start().is_ok());
-        game.handle_board_move(10, 10, 1).unwrap();
-        game.handle_board_move(10, 11, 2).unwrap();
+        let (mx, my) = cells[3];
+        game.remove_stone(mx, my);
+        assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+    }
 
-        assert_eq!(game.board.get_cell(10, 10), Some(Cell::MyStone));
-        assert_eq!(game.board.get_cell(10, 11), Some(Cell::OpStone));
+    #[test]
+    fn test_open_four_outranks_double_threat_and_blocks() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        place_pattern(&mut game, (5, 10), (1, 0), "_XXX_");
+        place_pattern(&mut game, (5, 14), (1, 0), "_OOO_");
 
-        let response = game.handle_board_done();
-        assert!(!response.contains("ERROR"));
-        assert_ne!(response, "10,10");
-        assert_ne!(response, "10,11");
+        let info = game.detect_threats(9, 10, Cell::MyStone);
+        assert_eq!(info.score(), SCORE_OPEN_FOUR);
+        assert!(info.score() > SCORE_DOUBLE_THREAT);
+
+        let first = game.generate_candidates()[0];
+        assert!(first == (5, 10) || first == (9, 10), "{:?}", first);
     }
 
     #[test]
-    fn test_board_move_errors() {
+    fn test_makes_open_four_when_available() {
         let mut game = GameState::new();
         game.handle_start(20);
+        place_pattern(&mut game, (5, 10), (1, 0), "_XXX_");
+        place_pattern(&mut game, (5, 14), (1, 0), "O_O");
 
-        assert_eq!(
-            game.handle_board_move(20, 0, 1),
-            Err("ERROR coordinates out of range")
-        );
-        assert_eq!(
-            game.handle_board_move(0, 20, 1),
-            Err("ERROR coordinates out of range")
+        let response = game.handle_turn(12, 3);
+        assert!(response == "5,10" || response == "9,10", "{}", response);
+    }
+
+    #[test]
+    fn test_blocks_opponent_open_three_before_it_opens() {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_millis(500))
+                .build(),
         );
-        assert_eq!(
-            game.handle_board_move(0, 0, 9),
-            Err("ERROR invalid board field")
+        game.handle_start(20);
+        place_pattern(&mut game, (5, 14), (1, 0), "_OO__");
+        place_pattern(&mut game, (12, 4), (1, 1), "X_X");
+
+        let response = game.handle_turn(8, 14);
+        assert!(
+            ["4,14", "5,14", "9,14", "10,14"].contains(&response.as_str()),
+            "{} lets the open three become an open four",
+            response
         );
     }
 
     #[test]
-    fn test_auto_initialize_on_commands() {
+    fn test_split_three_and_solid_three_make_a_fork() {
         let mut game = GameState::new();
-        assert!(!game.is_initialized);
-
-        assert!(game.handle_board_start().is_ok());
-        assert!(game.is_initialized);
+        game.handle_start(20);
+        for &(x, y) in &[(9, 10), (10, 10), (12, 8), (12, 9)] {
+            game.place_stone(x, y, Cell::MyStone);
+        }
 
-        let mut game2 = GameState::new();
-        let response = game2.handle_begin();
-        assert!(!response.contains("ERROR"));
-        assert!(game2.is_initialized);
+        let info = game.detect_threats(12, 10, Cell::MyStone);
+        assert_eq!(info.open_threes, 2);
+        assert!(info.is_winning());
+        assert_eq!(info.score(), SCORE_DOUBLE_THREAT);
+        assert!(info.score() < SCORE_OPEN_FOUR);
 
-        let mut game3 = GameState::new();
-        let response = game3.handle_turn(5, 5);
-        assert!(!response.contains("ERROR"));
-        assert!(game3.is_initialized);
+        let single = game.detect_threats(11, 10, Cell::MyStone);
+        assert!(!single.is_winning());
     }
 
     #[test]
-    fn test_restart() {
+    fn test_split_four_counts_as_closed_four() {
         let mut game = GameState::new();
         game.handle_start(20);
+        for &x in &[8, 9, 11] {
+            game.place_stone(x, 10, Cell::MyStone);
+        }
 
-        game.handle_turn(0, 0);
-        assert!(game.board.get_cell(0, 0).is_some());
-        assert_ne!(game.board.get_cell(0, 0), Some(Cell::Empty));
-
-        game.handle_restart();
-        assert_eq!(game.board.get_cell(0, 0), Some(Cell::Empty));
-        assert!(!game.game_in_progress);
+        let info = game.detect_threats(12, 10, Cell::MyStone);
+        assert_eq!((info.open_fours, info.closed_fours), (0, 1));
     }
 
     #[test]
-    fn test_game_over_win() {
+    fn test_split_four_beats_open_three_on_the_same_line() {
         let mut game = GameState::new();
         game.handle_start(20);
-
-        for x in 0..5 {
-            game.board.set_cell(x, 0, Cell::MyStone).unwrap();
+        for &x in &[6, 7, 10] {
+            game.place_stone(x, 10, Cell::MyStone);
         }
-        assert_eq!(game.game_over(), Some(Cell::MyStone));
-
-        game.handle_restart();
-        game.handle_start(20);
+        game.place_stone(0, 0, Cell::OpStone);
 
-        for x in 0..5 {
-            game.board.set_cell(x, 0, Cell::OpStone).unwrap();
-        }
-        assert_eq!(game.game_over(), Some(Cell::OpStone));
+        let info = game.detect_threats(8, 10, Cell::MyStone);
+        assert_eq!((info.closed_fours, info.open_threes), (1, 0));
+        assert!(info.makes_four());
+        assert!(game.is_forcing_move(8, 10, Cell::MyStone));
+        assert!(game.is_forcing_move(8, 10, Cell::OpStone));
     }
 
     #[test]
-    fn test_game_over_draw() {
+    fn test_blocks_split_double_three() {
         let mut game = GameState::new();
         game.handle_start(20);
-        assert_eq!(game.game_over(), None);
+        for &(x, y) in &[(9, 10), (10, 10), (12, 8), (12, 9)] {
+            game.place_stone(x, y, Cell::OpStone);
+        }
+        game.place_stone(3, 3, Cell::MyStone);
+        game.place_stone(3, 16, Cell::MyStone);
+
+        assert_eq!(game.find_fork_block(), Some((12, 10)));
     }
 
     #[test]
-    fn test_turn_handling_loss() {
+    fn test_killer_is_tried_early_at_sibling_node() {
         let mut game = GameState::new();
         game.handle_start(20);
+        let mut siblings = vec![(1, 1), (2, 2), (3, 3), (4, 4)];
 
-        for x in 0..4 {
-            game.handle_board_move(x, 0, 2).unwrap();
-        }
+        game.record_killer(3, (4, 4));
+        game.order_moves(&mut siblings, None, 3, Cell::MyStone);
+        assert_eq!(siblings, vec![(4, 4), (1, 1), (2, 2), (3, 3)]);
 
-        let response = game.handle_turn(4, 0);
+        game.order_moves(&mut siblings, Some((3, 3)), 3, Cell::MyStone);
+        assert_eq!(siblings[..2], [(3, 3), (4, 4)]);
 
-        assert!(!response.contains("ERROR"));
-        assert!(!game.game_in_progress);
+        let mut other_ply = vec![(1, 1), (4, 4)];
+        game.order_moves(&mut other_ply, None, 4, Cell::MyStone);
+        assert_eq!(other_ply, vec![(1, 1), (4, 4)]);
     }
 
     #[test]
-    fn test_make_move_win() {
+    fn test_record_killer_keeps_two_distinct_moves() {
         let mut game = GameState::new();
-        game.handle_start(20);
-
-        for x in 6..10 {
-            game.board.set_cell(x, 10, Cell::MyStone).unwrap();
-        }
+        game.record_killer(0, (1, 1));
+        game.record_killer(0, (1, 1));
+        assert_eq!(game.killer_moves[0], [Some((1, 1)), None]);
+        game.record_killer(0, (2, 2));
+        assert_eq!(game.killer_moves[0], [Some((2, 2)), Some((1, 1))]);
+        game.record_killer(MAX_SEARCH_DEPTH, (3, 3));
+    }
 
-        let response = game.handle_turn(0, 0);
+    #[test]
+    fn test_history_orders_quiet_moves_and_ages() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.record_history(Cell::MyStone, (2, 2), 3);
+        game.record_history(Cell::MyStone, (1, 1), 2);
 
-        let parts: Vec<&str> = response.split(',').collect();
-        let bot_x: usize = parts[0].parse().unwrap();
-        let bot_y: usize = parts[1].parse().unwrap();
+        let mut moves = vec![(1, 1), (2, 2), (3, 3)];
+        game.order_moves(&mut moves, None, 0, Cell::MyStone);
+        assert_eq!(moves, vec![(2, 2), (1, 1), (3, 3)]);
 
-        assert_eq!(game.board.get_cell(bot_x, bot_y), Some(Cell::MyStone));
-        assert_eq!(game.game_over(), Some(Cell::MyStone));
-        assert!(!game.game_in_progress);
+        let mut theirs = vec![(3, 3), (2, 2)];
+        game.order_moves(&mut theirs, None, 0, Cell::OpStone);
+        assert_eq!(theirs, vec![(3, 3), (2, 2)]);
+
+        game.age_history();
+        assert_eq!(game.history[0][2 * game.width + 2], 4);
     }
 
     #[test]
-    fn test_block_opponent_immediate_win() {
+    fn test_restart_clears_heuristics() {
         let mut game = GameState::new();
         game.handle_start(20);
+        game.record_history(Cell::OpStone, (5, 5), 4);
+        game.record_killer(1, (5, 5));
 
-        for x in 0..3 {
-            game.board.set_cell(x, 5, Cell::OpStone).unwrap();
-        }
+        assert_eq!(game.handle_restart(), "OK");
+        assert!(game.history.iter().flatten().all(|&h| h == 0));
+        assert!(game.killer_moves.iter().flatten().all(Option::is_none));
+    }
 
-        let response = game.handle_turn(3, 5);
+    #[test]
+    fn test_restart_and_start_forget_stored_positions() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        game.push_move(10, 10, Cell::OpStone);
+        let position = game.compute_hash_with_turn(Cell::MyStone);
+        game.tt.store(position, 5, 40, TTFlag::Exact, Some((3, 3)));
 
-        assert_eq!(response, "4,5");
-        assert_eq!(game.board.get_cell(4, 5), Some(Cell::MyStone));
-        assert_eq!(game.game_over(), None);
-        assert!(game.game_in_progress);
+        assert_eq!(game.handle_restart(), "OK");
+        game.push_move(10, 10, Cell::OpStone);
+        assert_eq!(game.compute_hash_with_turn(Cell::MyStone), position);
+        assert!(game.tt.probe(position).is_none());
+
+        game.tt.store(position, 5, 40, TTFlag::Exact, Some((3, 3)));
+        assert_eq!(game.handle_start(20), "OK");
+        assert!(game.tt.probe(position).is_none());
     }
 
     #[test]
-    fn test_prioritize_win_over_block() {
+    fn test_equal_root_moves_resolve_to_the_same_choice_in_any_order() {
         let mut game = GameState::new();
         game.handle_start(20);
-
-        for x in 6..10 {
-            game.place_stone(x, 10, Cell::MyStone);
+        game.place_stone(10, 10, Cell::OpStone);
+        let deadline = far_deadline();
+
+        for order in [[(11, 10), (9, 10)], [(9, 10), (11, 10)]] {
+            let (best, _) = game
+                .search_root(1, MIN_EVAL_SCORE, MAX_EVAL_SCORE, &order, deadline)
+                .unwrap();
+            assert_eq!(best, Some((9, 10)), "order {:?}", order);
         }
-        game.place_stone(5, 10, Cell::OpStone);
+    }
 
-        for x in 0..4 {
-            game.place_stone(x, 5, Cell::OpStone);
-        }
+    fn far_deadline() -> Instant {
+        Instant::now() + Duration::from_secs(3600)
+    }
 
-        let response = game.handle_turn(15, 15);
+    #[test]
+    fn test_vcf_finds_double_four() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for &(x, y) in &[(5, 5), (6, 5), (7, 5), (8, 6), (8, 7), (8, 8)] {
+            game.place_stone(x, y, Cell::MyStone);
+        }
+        game.place_stone(4, 5, Cell::OpStone);
+        game.place_stone(8, 9, Cell::OpStone);
 
-        assert_eq!(response, "10,10");
-        assert_eq!(game.board.get_cell(10, 10), Some(Cell::MyStone));
-        assert_eq!(game.game_over(), Some(Cell::MyStone));
-        assert!(!game.game_in_progress);
+        assert_eq!(
+            game.find_vcf(Cell::MyStone, 1, far_deadline()),
+            Some((8, 5))
+        );
     }
 
     #[test]
-    fn test_emergency_move_returns_valid_coords() {
-        let game = GameState::new();
-        let response = game.emergency_move();
-        let parts: Vec<&str> = response.split(',').collect();
-        assert_eq!(parts.len(), 2);
-        let x: usize = parts[0].parse().unwrap();
-        let y: usize = parts[1].parse().unwrap();
-        assert!(x < 20 && y < 20);
+    fn test_vct_finds_threat_sequence_depth_four_search_misses() {
+        let mut game = GameState::with_config(GameConfig::builder().max_depth(4).build());
+        game.handle_start(20);
+        for &(x, y) in &[(11, 6), (12, 12), (11, 9), (9, 8), (13, 9)] {
+            game.place_stone(x, y, Cell::MyStone);
+        }
+        for &(x, y) in &[(7, 12), (7, 7), (8, 7), (10, 8)] {
+            game.place_stone(x, y, Cell::OpStone);
+        }
+        let deadline = Instant::now() + Duration::from_secs(60);
+
+        assert_eq!(game.find_vcf(Cell::MyStone, VCF_MAX_DEPTH, deadline), None);
+        let vct = game.find_vct(Cell::MyStone, VCT_MAX_DEPTH, deadline);
+        assert_eq!(vct, Some((10, 7)));
+
+        game.find_best_move(deadline);
+        let report = game.last_search.unwrap();
+        assert_eq!(report.depth, 4);
+        assert!(report.score < MATE_THRESHOLD, "{}", report.score);
+
+        game.place_stone(10, 7, Cell::MyStone);
+        for (x, y) in game.generate_candidates() {
+            game.place_stone(x, y, Cell::OpStone);
+            let mut nodes = 0;
+            let follow_up = game.vct_attack(Cell::MyStone, 2, deadline, &mut nodes);
+            assert!(follow_up.is_some(), "{},{} refutes the VCT", x, y);
+            game.remove_stone(x, y);
+        }
     }
 
     #[test]
-    fn test_emergency_move_full_board() {
+    fn test_vct_needs_time_to_spare() {
         let mut game = GameState::new();
         game.handle_start(20);
-        for y in 0..20 {
-            for x in 0..20 {
-                game.board.set_cell(x, y, Cell::MyStone).unwrap();
-            }
+        for &(x, y) in &[(11, 6), (12, 12), (11, 9), (9, 8), (13, 9)] {
+            game.place_stone(x, y, Cell::MyStone);
         }
-        let response = game.emergency_move();
-        assert_eq!(response, "10,10");
+        let deadline = Instant::now() + VCT_MIN_TIME / 2;
+        assert_eq!(game.find_vct(Cell::MyStone, VCT_MAX_DEPTH, deadline), None);
     }
 
     #[test]
-    fn test_generate_move_never_errors() {
+    fn test_vct_keeps_to_its_share_of_the_move() {
         let mut game = GameState::new();
         game.handle_start(20);
-        for y in 0..20 {
-            for x in 0..20 {
-                game.board.set_cell(x, y, Cell::MyStone).unwrap();
-            }
+        for &(x, y) in &[(9, 9), (10, 10), (8, 10), (11, 8)] {
+            game.place_stone(x, y, Cell::MyStone);
         }
-        let response = game.generate_move();
-        assert!(!response.starts_with("ERROR"));
+        for &(x, y) in &[(10, 9), (9, 10), (11, 11), (8, 8)] {
+            game.place_stone(x, y, Cell::OpStone);
+        }
+        let budget = Duration::from_secs(2);
+        let start = Instant::now();
+        game.find_vct(Cell::MyStone, VCT_MAX_DEPTH, start + budget);
+        assert!(start.elapsed() < budget / VCT_TIME_DIVISOR + Duration::from_millis(100));
     }
 
     #[test]
-    fn test_out_of_bounds_coordinates() {
+    fn test_vcf_chains_fours_into_a_win() {
         let mut game = GameState::new();
         game.handle_start(20);
+        for &(x, y) in &[
+            (11, 10),
+            (12, 10),
+            (13, 10),
+            (10, 11),
+            (10, 12),
+            (11, 14),
+            (12, 15),
+            (13, 16),
+        ] {
+            game.place_stone(x, y, Cell::MyStone);
+        }
+        for &(x, y) in &[(14, 10), (10, 14), (14, 17), (2, 2)] {
+            game.place_stone(x, y, Cell::OpStone);
+        }
 
-        assert!(game.handle_turn(100, 100).contains("ERROR"));
-        assert!(game.handle_turn(0, 100).contains("ERROR"));
-        assert!(game.handle_turn(100, 0).contains("ERROR"));
+        assert_eq!(game.find_vcf(Cell::MyStone, 1, far_deadline()), None);
+        let first = game
+            .find_vcf(Cell::MyStone, VCF_MAX_DEPTH, far_deadline())
+            .expect("a two-four sequence wins");
+        let threats = game.detect_threats(first.0, first.1, Cell::MyStone);
+        assert!(threats.open_fours + threats.closed_fours > 0);
+        let report = game.last_search.unwrap();
+        assert_eq!((report.best_move, report.score), (first, SCORE_WIN - 5));
     }
 
     #[test]
-    fn test_incremental_scores_single_stone() {
+    fn test_vcf_plays_through_a_split_four() {
         let mut game = GameState::new();
         game.handle_start(20);
+        for &(x, y) in &[(6, 10), (7, 10), (10, 10), (8, 11), (8, 12)] {
+            game.place_stone(x, y, Cell::MyStone);
+        }
+        game.place_stone(0, 0, Cell::OpStone);
 
-        game.place_stone(10, 10, Cell::MyStone);
-
-        let incremental = game.evaluate_position();
-        let full_scan = game.evaluate_position_full_scan();
-        assert_eq!(incremental, full_scan);
+        assert_eq!(
+            game.find_vcf(Cell::MyStone, VCF_MAX_DEPTH, far_deadline()),
+            Some((8, 10))
+        );
     }
 
     #[test]
-    fn test_incremental_scores_horizontal_line() {
+    fn test_vcf_yields_to_opponent_five() {
         let mut game = GameState::new();
         game.handle_start(20);
-
-        for x in 5..9 {
-            game.place_stone(x, 10, Cell::MyStone);
+        for &(x, y) in &[(5, 5), (6, 5), (7, 5), (8, 6), (8, 7), (8, 8)] {
+            game.place_stone(x, y, Cell::MyStone);
+        }
+        game.place_stone(4, 5, Cell::OpStone);
+        game.place_stone(8, 9, Cell::OpStone);
+        for y in 12..16 {
+            game.place_stone(0, y, Cell::OpStone);
         }
 
-        let incremental = game.evaluate_position();
-        let full_scan = game.evaluate_position_full_scan();
-        assert_eq!(incremental, full_scan);
+        assert_eq!(
+            game.find_vcf(Cell::MyStone, VCF_MAX_DEPTH, far_deadline()),
+            None
+        );
     }
 
     #[test]
-    fn test_incremental_scores_mixed_stones() {
+    fn test_vcf_none_without_fours() {
         let mut game = GameState::new();
         game.handle_start(20);
-
         game.place_stone(10, 10, Cell::MyStone);
-        game.place_stone(11, 10, Cell::MyStone);
-        game.place_stone(12, 10, Cell::OpStone);
-        game.place_stone(10, 11, Cell::OpStone);
-        game.place_stone(10, 12, Cell::OpStone);
-
-        let incremental = game.evaluate_position();
-        let full_scan = game.evaluate_position_full_scan();
-        assert_eq!(incremental, full_scan);
+        game.place_stone(11, 11, Cell::OpStone);
+        assert_eq!(
+            game.find_vcf(Cell::MyStone, VCF_MAX_DEPTH, far_deadline()),
+            None
+        );
     }
 
     #[test]
-    fn test_incremental_scores_after_removal() {
+    fn test_quiescence_sees_open_four_behind_quiet_eval() {
         let mut game = GameState::new();
         game.handle_start(20);
+        for x in 8..12 {
+            game.place_stone(x, 10, Cell::OpStone);
+        }
+        for &(x, y) in &[(3, 3), (4, 4), (5, 5), (14, 3), (14, 4), (14, 5)] {
+            game.place_stone(x, y, Cell::MyStone);
+        }
 
-        game.place_stone(10, 10, Cell::MyStone);
-        game.place_stone(11, 10, Cell::MyStone);
-        game.place_stone(12, 10, Cell::MyStone);
-
-        let before_removal = game.evaluate_position();
-        assert_eq!(before_removal, game.evaluate_position_full_scan());
-
-        game.remove_stone(11, 10);
+        assert!(game.static_score(Cell::MyStone) > -MATE_THRESHOLD);
+        let value = game
+            .quiescence(
+                MIN_EVAL_SCORE,
+                MAX_EVAL_SCORE,
+                Cell::MyStone,
+                far_deadline(),
+                MAX_QUIESCENCE_DEPTH,
+                0,
+            )
+            .unwrap();
+        assert_eq!(value, -(SCORE_WIN - 2));
+    }
 
-        let after_removal = game.evaluate_position();
-        let full_scan = game.evaluate_position_full_scan();
-        assert_eq!(after_removal, full_scan);
+    fn near_full_game(rows: &[&str]) -> GameState {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_secs(10))
+                .build(),
+        );
+        game.handle_start(rows.len());
+        game.load_position(&Board::from_grid(rows).unwrap(), Cell::MyStone)
+            .unwrap();
+        game
     }
 
     #[test]
-    fn test_incremental_scores_place_remove_place() {
-        let mut game = GameState::new();
-        game.handle_start(20);
-
-        game.place_stone(10, 10, Cell::MyStone);
-        game.place_stone(11, 10, Cell::OpStone);
-
-        game.remove_stone(11, 10);
-        game.place_stone(11, 10, Cell::MyStone);
+    fn test_endgame_solver_finds_the_only_winning_move() {
+        let rows = ["XXOO.", "X.XO.", ".X.XX", "..OXO", "OOOOX"];
+        let mut game = near_full_game(&rows);
+        assert_eq!(game.find_immediate_win(Cell::MyStone), None);
+        assert_eq!(game.find_immediate_win(Cell::OpStone), None);
+
+        assert_eq!(game.solve_endgame(far_deadline()), Some((2, 2)));
+        let report = game.last_search.unwrap();
+        assert!(report.score >= MATE_THRESHOLD, "{}", report.score);
+        assert_eq!(report.depth, 7);
+
+        let mut game = near_full_game(&rows);
+        assert_eq!(game.best_move(), Some((2, 2)));
+    }
 
-        let incremental = game.evaluate_position();
-        let full_scan = game.evaluate_position_full_scan();
-        assert_eq!(incremental, full_scan);
+    #[test]
+    fn test_endgame_solver_scores_a_dead_board_as_a_draw() {
+        let mut game = near_full_game(&["XXOOX", "OOXXO", "XXOOX", "OOXXO", "XXOO."]);
+        assert_eq!(game.solve_endgame(far_deadline()), Some((4, 4)));
+        assert_eq!(game.last_search.unwrap().score, 0);
     }
 
     #[test]
-    fn test_incremental_scores_diagonal() {
+    fn test_endgame_solver_waits_for_few_empty_cells() {
         let mut game = GameState::new();
         game.handle_start(20);
-
-        for i in 0..4 {
-            game.place_stone(5 + i, 5 + i, Cell::MyStone);
-        }
-        game.place_stone(4, 4, Cell::OpStone);
-
-        let incremental = game.evaluate_position();
-        let full_scan = game.evaluate_position_full_scan();
-        assert_eq!(incremental, full_scan);
+        game.place_stone(10, 10, Cell::OpStone);
+        assert_eq!(game.solve_endgame(far_deadline()), None);
     }
 
     #[test]
-    fn test_incremental_scores_complex_pattern() {
+    fn test_threat_extensions_stop_at_the_ply_cap() {
         let mut game = GameState::new();
         game.handle_start(20);
+        for row in [2, 6, 10, 14] {
+            for x in 4..7 {
+                game.place_stone(x, row, Cell::MyStone);
+                game.place_stone(x + 9, row + 2, Cell::OpStone);
+            }
+        }
+        let mut nodes_for = |qdepth: usize| {
+            game.nodes = 0;
+            let ply = MAX_SEARCH_PLY - 2;
+            game.quiescence(
+                MIN_EVAL_SCORE,
+                MAX_EVAL_SCORE,
+                Cell::MyStone,
+                far_deadline(),
+                qdepth,
+                ply,
+            )
+            .unwrap();
+            game.nodes
+        };
 
-        let moves = [
-            (10, 10, Cell::MyStone),
-            (11, 10, Cell::OpStone),
-            (9, 10, Cell::MyStone),
-            (10, 9, Cell::OpStone),
-            (10, 11, Cell::MyStone),
-            (12, 10, Cell::OpStone),
-            (8, 10, Cell::MyStone),
-            (10, 8, Cell::OpStone),
-        ];
+        let capped = nodes_for(2);
+        assert!(capped > 1);
+        assert_eq!(nodes_for(64), capped);
+    }
 
-        for (x, y, player) in moves {
+    fn reference_negamax(game: &mut GameState, depth: usize, player: Cell, ply: usize) -> i32 {
+        let far = Instant::now() + Duration::from_secs(3600);
+        if let Some(score) = game.terminal_score(player, ply) {
+            return score;
+        }
+        if depth == 0 {
+            return game
+                .quiescence(
+                    MIN_EVAL_SCORE,
+                    MAX_EVAL_SCORE,
+                    player,
+                    far,
+                    MAX_QUIESCENCE_DEPTH,
+                    ply,
+                )
+                .unwrap();
+        }
+        let (candidates, wins) = game.scan_candidates();
+        if candidates.is_empty() {
+            return game.static_score(player);
+        }
+        if let Some(score) = GameState::forced_win_score(wins, player, ply) {
+            return score;
+        }
+        let mut best = MIN_EVAL_SCORE;
+        for (x, y) in candidates {
+            if game.validate_move(x, y).is_err() {
+                continue;
+            }
             game.place_stone(x, y, player);
-            assert_eq!(
-                game.evaluate_position(),
-                game.evaluate_position_full_scan(),
-                "Mismatch after placing stone at ({}, {})",
-                x,
-                y
-            );
+            best = best.max(-reference_negamax(
+                game,
+                depth - 1,
+                player.opponent(),
+                ply + 1,
+            ));
+            game.remove_stone(x, y);
         }
+        best
     }
 
     #[test]
-    fn test_incremental_scores_many_removals() {
+    fn test_forcing_moves_are_recognised() {
         let mut game = GameState::new();
         game.handle_start(20);
-
-        for i in 0..5 {
-            game.place_stone(5 + i, 10, Cell::MyStone);
+        for y in 5..8 {
+            game.place_stone(3, y, Cell::MyStone);
+            game.place_stone(15, y, Cell::OpStone);
         }
-        assert_eq!(game.evaluate_position(), game.evaluate_position_full_scan());
+        assert!(game.is_forcing_move(3, 8, Cell::MyStone));
+        assert!(game.is_forcing_move(15, 8, Cell::MyStone));
+        assert!(!game.is_forcing_move(9, 9, Cell::MyStone));
+    }
 
-        for i in (0..5).rev() {
-            game.remove_stone(5 + i, 10);
-            assert_eq!(
-                game.evaluate_position(),
-                game.evaluate_position_full_scan(),
-                "Mismatch after removing stone at ({}, 10)",
-                5 + i
-            );
+    #[test]
+    fn test_tactical_block_survives_late_move_reductions() {
+        let mut game = GameState::with_config(GameConfig::builder().max_depth(4).build());
+        game.handle_start(20);
+        for &(x, y, cell) in &[
+            (10, 5, Cell::OpStone),
+            (10, 6, Cell::OpStone),
+            (10, 7, Cell::OpStone),
+            (12, 12, Cell::OpStone),
+            (5, 12, Cell::MyStone),
+            (6, 13, Cell::MyStone),
+            (13, 4, Cell::MyStone),
+        ] {
+            game.place_stone(x, y, cell);
         }
 
-        assert_eq!(game.evaluate_position(), 0);
+        let best = game
+            .find_best_move(Instant::now() + Duration::from_secs(60))
+            .unwrap();
+        assert!(
+            [(10, 3), (10, 4), (10, 8), (10, 9)].contains(&best),
+            "{:?} leaves the open three alone",
+            best
+        );
+        assert_eq!(game.last_search.map(|r| r.depth), Some(4));
     }
 
     #[test]
-    fn test_incremental_scores_rebuild() {
+    fn test_aspiration_research_matches_full_window() {
+        let setup = || {
+            let mut game = GameState::new();
+            game.handle_start(20);
+            for &(x, y, cell) in &[
+                (10, 10, Cell::OpStone),
+                (11, 11, Cell::MyStone),
+                (10, 11, Cell::OpStone),
+            ] {
+                game.place_stone(x, y, cell);
+            }
+            game
+        };
+        let far = Instant::now() + Duration::from_secs(3600);
+        let mut game = setup();
+        let candidates = game.generate_candidates();
+        let (best, score) = game
+            .search_root(2, MIN_EVAL_SCORE, MAX_EVAL_SCORE, &candidates, far)
+            .unwrap();
+        assert!(best.is_some());
+
+        for previous in [score + 1000, score - 1000] {
+            let mut game = setup();
+            let found = game
+                .aspiration_search(2, Some(previous), &candidates, far)
+                .unwrap();
+            assert_eq!(found, (best, score), "previous score {}", previous);
+        }
+    }
+
+    #[test]
+    fn test_pvs_matches_plain_minimax() {
         let mut game = GameState::new();
         game.handle_start(20);
+        for &(x, y, cell) in &[
+            (10, 10, Cell::OpStone),
+            (11, 11, Cell::MyStone),
+            (10, 11, Cell::OpStone),
+        ] {
+            game.place_stone(x, y, cell);
+        }
 
-        game.board.set_cell(10, 10, Cell::MyStone).unwrap();
-        game.board.set_cell(11, 10, Cell::MyStone).unwrap();
-        game.board.set_cell(12, 10, Cell::OpStone).unwrap();
-
-        game.inc_scores.rebuild_from_board(&game.board);
-
-        let incremental = game.evaluate_position();
-        let full_scan = game.evaluate_position_full_scan();
-        assert_eq!(incremental, full_scan);
+        let expected = reference_negamax(&mut game, 2, Cell::MyStone, 0);
+        let far = Instant::now() + Duration::from_secs(3600);
+        let value = game
+            .negamax(2, MIN_EVAL_SCORE, MAX_EVAL_SCORE, Cell::MyStone, far, 0)
+            .unwrap();
+        assert_eq!(value, expected);
     }
 
     #[test]
@@ -1724,7 +6127,7 @@ mod tests {
         game.handle_start(20);
 
         game.board.set_cell(5, 5, Cell::MyStone).unwrap();
-        assert_eq!(game.validate_move(5, 5), Err("ERROR cell already occupied"));
+        assert_eq!(game.validate_move(5, 5), Err(GameError::Occupied));
     }
 
     #[test]
@@ -1733,7 +6136,7 @@ mod tests {
         game.handle_start(20);
 
         game.board.set_cell(5, 5, Cell::OpStone).unwrap();
-        assert_eq!(game.validate_move(5, 5), Err("ERROR cell already occupied"));
+        assert_eq!(game.validate_move(5, 5), Err(GameError::Occupied));
     }
 
     #[test]
@@ -1742,7 +6145,7 @@ mod tests {
         game.handle_start(20);
 
         game.board.set_cell(7, 7, Cell::Forbidden).unwrap();
-        assert_eq!(game.validate_move(7, 7), Err("ERROR move forbidden"));
+        assert_eq!(game.validate_move(7, 7), Err(GameError::Forbidden));
     }
 
     #[test]
@@ -1752,22 +6155,10 @@ mod tests {
 
         assert!(game.validate_move(19, 19).is_ok());
 
-        assert_eq!(
-            game.validate_move(20, 0),
-            Err("ERROR coordinates out of range")
-        );
-        assert_eq!(
-            game.validate_move(0, 20),
-            Err("ERROR coordinates out of range")
-        );
-        assert_eq!(
-            game.validate_move(20, 19),
-            Err("ERROR coordinates out of range")
-        );
-        assert_eq!(
-            game.validate_move(19, 20),
-            Err("ERROR coordinates out of range")
-        );
+        assert_eq!(game.validate_move(20, 0), Err(GameError::OutOfBounds));
+        assert_eq!(game.validate_move(0, 20), Err(GameError::OutOfBounds));
+        assert_eq!(game.validate_move(20, 19), Err(GameError::OutOfBounds));
+        assert_eq!(game.validate_move(19, 20), Err(GameError::OutOfBounds));
     }
 
     #[test]
@@ -1775,27 +6166,15 @@ mod tests {
         let mut game = GameState::new();
         game.handle_start(20);
 
-        assert_eq!(
-            game.validate_move(100, 0),
-            Err("ERROR coordinates out of range")
-        );
-        assert_eq!(
-            game.validate_move(0, 100),
-            Err("ERROR coordinates out of range")
-        );
-        assert_eq!(
-            game.validate_move(1000, 1000),
-            Err("ERROR coordinates out of range")
-        );
+        assert_eq!(game.validate_move(100, 0), Err(GameError::OutOfBounds));
+        assert_eq!(game.validate_move(0, 100), Err(GameError::OutOfBounds));
+        assert_eq!(game.validate_move(1000, 1000), Err(GameError::OutOfBounds));
     }
 
     #[test]
     fn test_validate_move_not_initialized() {
         let game = GameState::new();
-        assert_eq!(
-            game.validate_move(10, 10),
-            Err("ERROR game not initialized")
-        );
+        assert_eq!(game.validate_move(10, 10), Err(GameError::NotInitialized));
     }
 
     #[test]
@@ -1856,7 +6235,7 @@ mod tests {
         for x in 0..5 {
             game.board.set_cell(x, 0, Cell::MyStone).unwrap();
         }
-        assert_eq!(game.game_over(), Some(Cell::MyStone));
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::MyStone)));
     }
 
     #[test]
@@ -1867,7 +6246,7 @@ mod tests {
         for x in 0..5 {
             game.board.set_cell(x, 19, Cell::OpStone).unwrap();
         }
-        assert_eq!(game.game_over(), Some(Cell::OpStone));
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::OpStone)));
     }
 
     #[test]
@@ -1878,7 +6257,7 @@ mod tests {
         for y in 0..5 {
             game.board.set_cell(0, y, Cell::MyStone).unwrap();
         }
-        assert_eq!(game.game_over(), Some(Cell::MyStone));
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::MyStone)));
     }
 
     #[test]
@@ -1889,7 +6268,7 @@ mod tests {
         for y in 0..5 {
             game.board.set_cell(19, y, Cell::OpStone).unwrap();
         }
-        assert_eq!(game.game_over(), Some(Cell::OpStone));
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::OpStone)));
     }
 
     #[test]
@@ -1900,7 +6279,7 @@ mod tests {
         for i in 0..5 {
             game.board.set_cell(i, i, Cell::MyStone).unwrap();
         }
-        assert_eq!(game.game_over(), Some(Cell::MyStone));
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::MyStone)));
 
         game.handle_restart();
         game.handle_start(20);
@@ -1908,7 +6287,7 @@ mod tests {
         for i in 0..5 {
             game.board.set_cell(15 + i, 15 + i, Cell::OpStone).unwrap();
         }
-        assert_eq!(game.game_over(), Some(Cell::OpStone));
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::OpStone)));
     }
 
     #[test]
@@ -1919,7 +6298,7 @@ mod tests {
         for i in 0..5 {
             game.board.set_cell(19 - i, i, Cell::MyStone).unwrap();
         }
-        assert_eq!(game.game_over(), Some(Cell::MyStone));
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::MyStone)));
 
         game.handle_restart();
         game.handle_start(20);
@@ -1927,7 +6306,7 @@ mod tests {
         for i in 0..5 {
             game.board.set_cell(4 - i, 15 + i, Cell::OpStone).unwrap();
         }
-        assert_eq!(game.game_over(), Some(Cell::OpStone));
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::OpStone)));
     }
 
     #[test]
@@ -1954,6 +6333,39 @@ mod tests {
         assert_eq!(game.game_over(), None);
     }
 
+    #[test]
+    fn test_full_board_without_five_is_a_draw() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for (x, y) in game.board.clone().iter_indices() {
+            let cell = if (x / 2 + y) % 2 == 0 {
+                Cell::MyStone
+            } else {
+                Cell::OpStone
+            };
+            assert_eq!(game.game_over(), None);
+            game.place_stone(x, y, cell);
+        }
+        assert_eq!(game.game_over(), Some(GameResult::Draw));
+        assert_eq!(game.terminal_score(Cell::MyStone, 3), Some(0));
+    }
+
+    #[test]
+    fn test_turn_after_opponent_five_answers_without_searching() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        for x in 0..4 {
+            game.push_move(x, 5, Cell::OpStone);
+        }
+        game.game_in_progress = true;
+        let reply = game.handle_turn(4, 5);
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::OpStone)));
+        assert!(!game.game_in_progress);
+        assert!(game.last_search.is_none());
+        let (x, y) = reply.split_once(',').unwrap();
+        assert!(game.board.is_empty(x.parse().unwrap(), y.parse().unwrap()));
+    }
+
     #[test]
     fn test_multiple_win_lines_game_over() {
         let mut game = GameState::new();
@@ -1965,7 +6377,7 @@ mod tests {
         for y in 0..5 {
             game.board.set_cell(10, y, Cell::MyStone).unwrap();
         }
-        assert_eq!(game.game_over(), Some(Cell::MyStone));
+        assert_eq!(game.game_over(), Some(GameResult::Win(Cell::MyStone)));
     }
 
     #[test]
@@ -1996,11 +6408,47 @@ mod tests {
 
         assert_eq!(
             game.handle_board_move(20, 0, 1),
-            Err("ERROR coordinates out of range")
+            Err(GameError::OutOfBounds)
         );
         assert_eq!(
             game.handle_board_move(0, 20, 1),
-            Err("ERROR coordinates out of range")
+            Err(GameError::OutOfBounds)
         );
     }
 }
+
+#[cfg(all(test, feature = "parallel"))]
+mod parallel_tests {
+    use super::*;
+
+    fn four_three(threads: usize) -> GameState {
+        let mut game =
+            GameState::with_config(GameConfig::builder().max_depth(2).threads(threads).build());
+        game.handle_start(20);
+        for (x, y) in [(5, 5), (6, 5), (7, 5), (8, 6), (8, 7)] {
+            game.push_move(x, y, Cell::MyStone);
+        }
+        for (x, y) in [(4, 5), (12, 12), (13, 3), (2, 14), (15, 9)] {
+            game.push_move(x, y, Cell::OpStone);
+        }
+        game
+    }
+
+    #[test]
+    fn test_parallel_and_serial_search_agree_on_a_four_three() {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        let serial = four_three(1).find_best_move(deadline);
+        let parallel = four_three(4).find_best_move(deadline);
+        assert_eq!(serial, Some((8, 5)));
+        assert_eq!(parallel, serial);
+    }
+
+    #[test]
+    fn test_parallel_search_keeps_to_the_deadline() {
+        let mut game = four_three(4);
+        game.config.max_depth = MAX_SEARCH_DEPTH;
+        let started = Instant::now();
+        game.find_best_move(started + Duration::from_millis(200));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,26 @@
+//! Gomoku engine behind the `pbrain-gomoku-ai` protocol binary. [`Engine`]
+//! answers "what should this side play here" without going through the
+//! text protocol; [`GameState`] is the stateful engine the protocol drives.
+
+#[macro_use]
+pub mod debug;
+
+pub mod bench;
+pub mod board;
+pub mod config;
+pub mod engine;
+pub mod error;
+pub mod game;
+pub mod opening;
+pub mod ponder;
+pub mod protocol;
+pub mod psq;
+pub mod rules;
+pub mod selfplay;
+pub mod time_manager;
+pub mod zobrist;
+
+pub use board::{Board, Cell};
+pub use engine::Engine;
+pub use error::GameError;
+pub use game::{GameResult, GameState};
@@ -1,22 +1,95 @@
-mod board;
-mod game;
-mod protocol;
-mod zobrist;
-
-use crate::game::GameState;
-use protocol::{parse_board_line, parse_line, BoardLine, Command};
+use pbrain_gomoku_ai::config::{
+    self, EngineKind, EvalWeights, GameConfig, DEFAULT_ADJACENCY_RADIUS, DEFAULT_CENTER_RADIUS,
+    DEFAULT_MAX_DEPTH,
+};
+use pbrain_gomoku_ai::ponder::Ponder;
+use pbrain_gomoku_ai::protocol::{
+    about_string, error_response, parse_board_line, parse_line, BoardLine, Command,
+};
+use pbrain_gomoku_ai::{bench, debug, selfplay, GameState};
+use std::env;
 use std::io::{self, BufRead, Write};
 use std::panic;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+const SELFPLAY_MOVE_TIME: Duration = Duration::from_millis(1000);
+const DEFAULT_BENCH_DEPTH: usize = 3;
+
+fn analysis_enabled() -> bool {
+    env::var("GOMOKU_ANALYSIS").is_ok_and(|v| v == "1")
+}
+
+fn env_setting<T>(name: &str, parse: impl Fn(&str) -> Option<T>, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|v| parse(&v))
+        .unwrap_or(default)
+}
+
+fn debug_enabled() -> bool {
+    env::var("GOMOKU_DEBUG").is_ok_and(|v| v == "1")
+}
+
+fn ponder_enabled() -> bool {
+    env::var("GOMOKU_PONDER").is_ok_and(|v| v == "1")
+}
 
-fn handle_board_section<I>(lines: &mut I, game: &mut GameState) -> String
+fn eval_weights() -> EvalWeights {
+    let mut weights = EvalWeights::default();
+    for name in EvalWeights::NAMES {
+        let value = env::var(format!("GOMOKU_W_{}", name.to_ascii_uppercase()))
+            .ok()
+            .and_then(|v| v.trim().parse().ok());
+        if let Some(value) = value {
+            weights.set(name, value);
+        }
+    }
+    weights
+}
+
+fn psq_dir() -> Option<PathBuf> {
+    env::var_os("GOMOKU_PSQ_DIR").map(PathBuf::from)
+}
+
+fn repl_enabled() -> bool {
+    env::var("GOMOKU_REPL").is_ok_and(|v| v == "1")
+}
+
+fn process_repl_command(input: &str, game: &mut GameState) -> Option<String> {
+    match input.to_ascii_lowercase().as_str() {
+        "show" => Some(game.board().labeled().trim_end().to_string()),
+        "eval" => Some(format!("eval {}", game.evaluation())),
+        "undo" => Some(match game.undo_move() {
+            Some((x, y)) => format!("undid {},{}", x, y),
+            None => "nothing to undo".to_string(),
+        }),
+        "moves" => Some(
+            game.candidate_moves()
+                .iter()
+                .map(|(x, y)| format!("{},{}", x, y))
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        _ => None,
+    }
+}
+
+fn handle_board_section<I>(lines: &mut I, game: &mut GameState, incremental: bool) -> String
 where
     I: Iterator<Item = Result<String, io::Error>>,
 {
     let mut error: Option<String> = None;
     let mut done_received = false;
+    let (name, started) = if incremental {
+        ("YXBOARD", game.handle_yxboard_start())
+    } else {
+        ("BOARD", game.handle_board_start())
+    };
 
-    if let Err(err) = game.handle_board_start() {
-        error = Some(err.to_string());
+    if let Err(err) = started {
+        error = Some(error_response(err));
     }
 
     for board_line in lines {
@@ -34,13 +107,15 @@ where
                     Ok(BoardLine::Move { x, y, field }) => {
                         if error.is_none() {
                             if let Err(err) = game.handle_board_move(x, y, field) {
-                                error = Some(err.to_string());
+                                debug!("rejected {} line '{}': {}", name, content, err);
+                                error = Some(error_response(err));
                             }
                         }
                     }
                     Err(err) => {
+                        debug!("unparsable {} line '{}': {}", name, content, err);
                         if error.is_none() {
-                            error = Some(format!("ERROR {}", err));
+                            error = Some(error_response(err));
                         }
                     }
                 }
@@ -53,7 +128,7 @@ where
     }
 
     if !done_received && error.is_none() {
-        error = Some("ERROR missing DONE for BOARD".to_string());
+        error = Some(format!("ERROR missing DONE for {}", name));
     }
 
     match error {
@@ -62,6 +137,22 @@ where
     }
 }
 
+fn memory_message(game: &GameState) -> String {
+    let usage = game.memory_usage();
+    format!(
+        "MESSAGE memory tt={} eval={} board={} move_cache={} heuristics={} moves={} pending={} \
+         total={}",
+        usage.tt,
+        usage.eval,
+        usage.board,
+        usage.move_cache,
+        usage.heuristics,
+        usage.moves,
+        usage.pending,
+        usage.total()
+    )
+}
+
 fn process_command(
     command: Command,
     lines: &mut impl Iterator<Item = Result<String, io::Error>>,
@@ -69,29 +160,83 @@ fn process_command(
 ) -> Option<String> {
     match command {
         Command::Start(size) => Some(game.handle_start(size)),
+        Command::RectStart(width, height) => Some(game.handle_rect_start(width, height)),
         Command::Turn(x, y) => Some(game.handle_turn(x, y)),
+        Command::Play(x, y) => Some(game.handle_play(x, y)),
+        Command::Takeback(x, y) => Some(game.handle_takeback(x, y)),
         Command::Begin => Some(game.handle_begin()),
-        Command::Board => Some(handle_board_section(lines, game)),
-        Command::Info(_, _) => None,
-        Command::About => Some(
-            "name=\"pbrain-brainrot\", version=\"1.0.0\", author=\"Brainrot\", country=\"FR\""
-                .to_string(),
-        ),
+        Command::Board => Some(handle_board_section(lines, game, false)),
+        Command::YxBoard => Some(handle_board_section(lines, game, true)),
+        Command::Info(key, value) => {
+            game.handle_info(&key, &value);
+            None
+        }
+        Command::About => Some(about_string()),
         Command::Restart => Some(game.handle_restart()),
-        Command::End => None,
-        Command::Error(msg) => Some(format!("ERROR {}", msg)),
+        Command::Swap => Some(game.handle_swap()),
+        Command::Suggest => Some(game.handle_suggest()),
+        Command::Debug(subcommand) => {
+            if subcommand == "BOARD" {
+                debug!("{:?}", game.board());
+            }
+            None
+        }
+        Command::Memory if game.analysis_enabled() => Some(memory_message(game)),
+        Command::Memory => Some("UNKNOWN MEMORY".to_string()),
+        Command::End => {
+            game.handle_end();
+            None
+        }
+        Command::Error(msg) => Some(error_response(msg)),
         Command::Unknown(msg) => Some(format!("UNKNOWN {}", msg)),
     }
 }
 
-fn main() {
-    panic::set_hook(Box::new(|_| {}));
-
-    let stdin = io::stdin();
+fn run_cli_mode(args: &[String]) -> Option<io::Result<()>> {
+    let report = match args.first().map(String::as_str) {
+        Some("--selfplay") => {
+            let seed = args.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+            let config = GameConfig::builder()
+                .timeout_turn(SELFPLAY_MOVE_TIME)
+                .opening_book(true)
+                .seed(seed)
+                .build();
+            selfplay::run(&config)
+        }
+        Some("--bench") => {
+            let depth = args
+                .get(1)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_BENCH_DEPTH);
+            bench::run(depth)
+        }
+        _ => return None,
+    };
     let mut stdout = io::stdout();
-    let mut lines = stdin.lock().lines();
-    let mut game = GameState::new();
+    Some(writeln!(stdout, "{}", report).and_then(|_| stdout.flush()))
+}
+
+fn serve<W: Write>(
+    lines: &mut impl Iterator<Item = Result<String, io::Error>>,
+    out: &mut W,
+    game: &mut GameState,
+    repl: bool,
+) -> io::Result<()> {
+    let mut pondering: Option<Ponder> = None;
+    let result = serve_until_done(lines, out, game, repl, &mut pondering);
+    if let Some(ponder) = pondering {
+        ponder.finish(None);
+    }
+    result
+}
 
+fn serve_until_done<W: Write>(
+    lines: &mut impl Iterator<Item = Result<String, io::Error>>,
+    out: &mut W,
+    game: &mut GameState,
+    repl: bool,
+    pondering: &mut Option<Ponder>,
+) -> io::Result<()> {
     while let Some(line) = lines.next() {
         match line {
             Ok(input) => {
@@ -100,43 +245,128 @@ fn main() {
                     continue;
                 }
 
+                // Managers send INFO time_left ahead of each TURN, so those
+                // lines leave the ponder running.
                 let command = parse_line(input);
+                if !matches!(command, Command::Info(..)) {
+                    if let Some(ponder) = pondering.take() {
+                        let reply = match command {
+                            Command::Turn(x, y) => Some((x, y)),
+                            _ => None,
+                        };
+                        ponder.finish(reply);
+                    }
+                }
+
+                if repl {
+                    if let Some(output) = process_repl_command(input, game) {
+                        writeln!(out, "{}", output)?;
+                        out.flush()?;
+                        continue;
+                    }
+                }
+
                 let is_end = matches!(command, Command::End);
                 let needs_move_response = matches!(
                     command,
-                    Command::Turn(_, _) | Command::Begin | Command::Board
+                    Command::Turn(_, _)
+                        | Command::Begin
+                        | Command::Board
+                        | Command::YxBoard
+                        | Command::Swap
                 );
 
                 let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
-                    process_command(command, &mut lines, &mut game)
+                    process_command(command, lines, game)
                 }));
 
-                match result {
-                    Ok(Some(response)) => println!("{}", response),
-                    Ok(None) => {}
+                let written = match result {
+                    Ok(Some(response)) => writeln!(out, "{}", response),
+                    Ok(None) => Ok(()),
                     Err(_) => {
                         if needs_move_response {
-                            let fallback = game.emergency_move();
-                            println!("{}", fallback);
+                            let fallback = game.recover_move();
+                            writeln!(out, "{}", fallback)
                         } else {
-                            println!("ERROR internal error");
+                            writeln!(out, "ERROR internal error")
                         }
                     }
-                }
+                };
 
+                written?;
+                out.flush()?;
                 if is_end {
-                    break;
+                    return Ok(());
                 }
 
-                if let Err(e) = stdout.flush() {
-                    eprintln!("Failed to flush stdout: {}", e);
-                    break;
+                if needs_move_response && game.ponder_enabled() {
+                    *pondering = Ponder::start(game);
                 }
             }
-            Err(e) => {
-                eprintln!("Error reading input: {}", e);
-                break;
-            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Exits with 0 on `END` or end of input and 1 once stdin or stdout fails,
+/// for example when the manager has closed its end of the pipe.
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(result) = run_cli_mode(&args) {
+        return exit_code(result);
+    }
+
+    panic::set_hook(Box::new(|_| {}));
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut lines = stdin.lock().lines();
+    let config = GameConfig::builder()
+        .opening_book(true)
+        .analysis(analysis_enabled())
+        .ponder(ponder_enabled())
+        .threads(env_setting("GOMOKU_THREADS", config::parse_threads, 1))
+        .eval_weights(eval_weights())
+        .resign_threshold(env_setting(
+            "GOMOKU_RESIGN_THRESHOLD",
+            |v| v.trim().parse().ok().map(Some),
+            None,
+        ))
+        .psq_dir(psq_dir())
+        .max_depth(env_setting(
+            "GOMOKU_MAX_DEPTH",
+            config::parse_max_depth,
+            DEFAULT_MAX_DEPTH,
+        ))
+        .adjacency_radius(env_setting(
+            "GOMOKU_ADJACENCY_RADIUS",
+            config::parse_adjacency_radius,
+            DEFAULT_ADJACENCY_RADIUS,
+        ))
+        .center_radius(env_setting(
+            "GOMOKU_CENTER_RADIUS",
+            config::parse_center_radius,
+            DEFAULT_CENTER_RADIUS,
+        ))
+        .engine(env_setting(
+            "GOMOKU_ENGINE",
+            EngineKind::from_name,
+            EngineKind::Search,
+        ))
+        .debug(debug_enabled())
+        .build();
+    debug::set_enabled(config.debug);
+    let mut game = GameState::with_config(config);
+    exit_code(serve(&mut lines, &mut stdout, &mut game, repl_enabled()))
+}
+
+fn exit_code(result: io::Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("I/O error: {}", e);
+            ExitCode::FAILURE
         }
     }
 }
@@ -144,6 +374,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pbrain_gomoku_ai::Cell;
 
     #[test]
     fn test_handle_board_section_success() {
@@ -152,7 +383,7 @@ mod tests {
 
         let mut lines = vec![Ok("10,10,2".to_string()), Ok("DONE".to_string())].into_iter();
 
-        let response = handle_board_section(&mut lines, &mut game);
+        let response = handle_board_section(&mut lines, &mut game, false);
         assert!(!response.contains("ERROR"));
 
         let parts: Vec<&str> = response.split(',').collect();
@@ -169,7 +400,7 @@ mod tests {
         game.handle_start(20);
 
         let mut lines = vec![Ok("10,10,2".to_string())].into_iter();
-        let response = handle_board_section(&mut lines, &mut game);
+        let response = handle_board_section(&mut lines, &mut game, false);
 
         assert_eq!(response, "ERROR missing DONE for BOARD");
     }
@@ -180,29 +411,303 @@ mod tests {
         game.handle_start(20);
 
         let mut lines = vec![Ok("bad".to_string()), Ok("DONE".to_string())].into_iter();
-        let response = handle_board_section(&mut lines, &mut game);
+        let response = handle_board_section(&mut lines, &mut game, false);
 
         assert_eq!(response, "ERROR Invalid BOARD line 'bad'");
     }
 
     #[test]
-    fn test_handle_board_section_auto_initializes() {
+    fn test_handle_board_section_error_still_consumes_through_done() {
         let mut game = GameState::new();
-        let mut lines = vec![Ok("DONE".to_string())].into_iter();
-        let response = handle_board_section(&mut lines, &mut game);
+        game.handle_start(20);
 
-        assert!(!response.contains("ERROR"));
-        let parts: Vec<&str> = response.split(',').collect();
-        assert_eq!(parts.len(), 2);
+        let mut lines = vec![
+            Ok("10,10,2".to_string()),
+            Ok("10,x,1".to_string()),
+            Ok("11,11,1".to_string()),
+            Ok("DONE".to_string()),
+            Ok("TURN 5,5".to_string()),
+        ]
+        .into_iter();
+        let response = handle_board_section(&mut lines, &mut game, false);
+
+        assert_eq!(response, "ERROR Invalid BOARD line '10,x,1'");
+        assert_eq!(lines.next().unwrap().unwrap(), "TURN 5,5");
+    }
+
+    #[test]
+    fn test_handle_board_section_truncated_after_error_reports_first_error() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+
+        let mut lines = vec![Ok("10,10".to_string())].into_iter();
+        let response = handle_board_section(&mut lines, &mut game, false);
+
+        assert_eq!(response, "ERROR Invalid BOARD line '10,10'");
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn test_handle_board_section_before_start_consumes_through_done() {
+        let mut game = GameState::new();
+        let mut lines = vec![
+            Ok("10,10,2".to_string()),
+            Ok("DONE".to_string()),
+            Ok("START 20".to_string()),
+        ]
+        .into_iter();
+        let response = handle_board_section(&mut lines, &mut game, false);
+
+        assert_eq!(response, "ERROR game not initialized");
+        assert_eq!(lines.next().unwrap().unwrap(), "START 20");
+    }
+
+    #[test]
+    fn test_commands_before_start_get_the_same_error() {
+        let mut game = GameState::new();
+        let output = serve_lines(
+            &[
+                "TURN 5,5", "BEGIN", "BOARD", "1,1,1", "DONE", "YXBOARD", "2,2,1", "DONE",
+                "SUGGEST", "PLAY 1,1",
+            ],
+            &mut game,
+        );
+        assert_eq!(output, vec!["ERROR game not initialized"; 6]);
+    }
+
+    #[test]
+    fn test_yxboard_adds_to_the_current_position() {
+        let mut game = GameState::new();
+        let output = serve_lines(
+            &[
+                "START 20", "BOARD", "10,10,1", "10,11,2", "DONE", "YXBOARD", "3,3,2", "4,4,2",
+                "DONE",
+            ],
+            &mut game,
+        );
+
+        assert_eq!(output.len(), 3, "{:?}", output);
+        for reply in &output[1..] {
+            assert!(!reply.starts_with("ERROR"), "{}", reply);
+        }
+        let board = game.board();
+        assert_eq!(board.get_cell(10, 10), Some(Cell::MyStone));
+        assert_eq!(board.get_cell(10, 11), Some(Cell::OpStone));
+        assert_eq!(board.get_cell(3, 3), Some(Cell::OpStone));
+        assert_eq!(board.get_cell(4, 4), Some(Cell::OpStone));
+        // Both blocks were answered with a stone of ours.
+        assert_eq!(board.stone_count(), 6);
+    }
+
+    #[test]
+    fn test_yxboard_refuses_to_overwrite_an_earlier_stone() {
+        let mut game = GameState::new();
+        let output = serve_lines(
+            &[
+                "START 20", "BOARD", "10,10,1", "10,11,2", "DONE", "YXBOARD", "3,3,2", "3,3,1",
+                "10,10,2", "DONE",
+            ],
+            &mut game,
+        );
+
+        assert_eq!(output.len(), 3, "{:?}", output);
+        assert_eq!(output[2], "ERROR cell already occupied");
+        assert_eq!(game.board().get_cell(10, 10), Some(Cell::MyStone));
+        assert_eq!(game.board().get_cell(3, 3), Some(Cell::MyStone));
+    }
+
+    fn serve_lines(input: &[&str], game: &mut GameState) -> Vec<String> {
+        let mut lines = input.iter().map(|line| Ok(line.to_string()));
+        let mut out = Vec::new();
+        serve(&mut lines, &mut out, game, false).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_end_stops_output_and_resets_the_game() {
+        let mut game = GameState::new();
+        let output = serve_lines(
+            &["START 20", "TURN 10,10", "END", "TURN 5,5", "ABOUT"],
+            &mut game,
+        );
+
+        assert_eq!(output.len(), 2, "{:?}", output);
+        assert_eq!(output[0], "OK");
+        assert!(!output[1].starts_with("ERROR"), "{}", output[1]);
+        assert_eq!(game.board().stone_count(), 0);
+        assert_eq!(game.handle_restart(), "ERROR game not initialized");
+        assert_eq!(serve_lines(&["START 20"], &mut game), ["OK"]);
+    }
+
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+    }
+
+    #[test]
+    fn test_end_of_input_stops_serving_cleanly() {
+        let mut game = GameState::new();
+        let mut lines = ["START 20", "TURN 10,10"]
+            .iter()
+            .map(|line| Ok(line.to_string()));
+        let mut out = Vec::new();
+        assert!(serve(&mut lines, &mut out, &mut game, false).is_ok());
+        assert_eq!(game.board().stone_count(), 2);
+    }
+
+    #[test]
+    fn test_closed_output_is_reported_and_stops_serving() {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_millis(200))
+                .ponder(true)
+                .build(),
+        );
+        let mut lines = ["START 20", "BEGIN", "TURN 0,0"]
+            .iter()
+            .map(|line| Ok(line.to_string()));
+        let result = serve(&mut lines, &mut ClosedPipe, &mut game, false);
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn test_read_error_is_reported_and_abandons_the_ponder() {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_millis(200))
+                .ponder(true)
+                .build(),
+        );
+        let mut lines = vec![
+            Ok("START 20".to_string()),
+            Ok("BEGIN".to_string()),
+            Err(io::Error::from(io::ErrorKind::InvalidData)),
+            Ok("TURN 0,0".to_string()),
+        ]
+        .into_iter();
+        let mut out = Vec::new();
+        let result = serve(&mut lines, &mut out, &mut game, false);
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(game.board().stone_count(), 1);
+    }
+
+    #[test]
+    fn test_pondering_between_moves_keeps_the_protocol_in_step() {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_millis(200))
+                .ponder(true)
+                .build(),
+        );
+        let table = game.memory_usage().tt;
+        let output = serve_lines(
+            &["START 20", "BEGIN", "TURN 0,0", "TURN 19,19", "ABOUT"],
+            &mut game,
+        );
+
+        assert_eq!(output.len(), 5, "{:?}", output);
+        for reply in &output[1..4] {
+            assert!(!reply.starts_with("ERROR"), "{}", reply);
+        }
+        assert_eq!(game.memory_usage().tt, table);
+        assert_eq!(game.board().stone_count(), 5);
+    }
+
+    #[test]
+    fn test_info_before_turn_keeps_pondering() {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_millis(200))
+                .ponder(true)
+                .build(),
+        );
+        let mut pondering = None;
+        let mut out = Vec::new();
+        let mut lines = ["START 20", "BEGIN", "INFO time_left 100000"]
+            .iter()
+            .map(|line| Ok(line.to_string()));
+        serve_until_done(&mut lines, &mut out, &mut game, false, &mut pondering).unwrap();
+        let guess = pondering.as_ref().expect("still pondering").guess();
+
+        let turn = format!("TURN {},{}", guess.0, guess.1);
+        let mut lines = std::iter::once(Ok(turn));
+        serve_until_done(&mut lines, &mut out, &mut game, false, &mut pondering).unwrap();
+        if let Some(ponder) = pondering.take() {
+            ponder.finish(None);
+        }
+
+        let output = String::from_utf8(out).unwrap();
+        assert_eq!(output.lines().count(), 3, "{}", output);
+        assert!(!output.contains("ERROR"), "{}", output);
+        assert_eq!(game.board().stone_count(), 3);
+    }
+
+    #[test]
+    fn test_memory_command_requires_analysis() {
+        let mut game = GameState::new();
+        let mut lines = Vec::new().into_iter();
+        assert_eq!(
+            process_command(Command::Memory, &mut lines, &mut game),
+            Some("UNKNOWN MEMORY".to_string())
+        );
+
+        let mut game = GameState::with_config(GameConfig::builder().analysis(true).build());
+        let usage = game.memory_usage();
+        let response = process_command(Command::Memory, &mut lines, &mut game).unwrap();
+        assert!(response.starts_with("MESSAGE memory "));
+        assert!(response.ends_with(&format!("total={}", usage.total())));
+    }
+
+    #[test]
+    fn test_repl_commands() {
+        let mut game = GameState::new();
+        game.handle_start(20);
+        let mut lines = vec![
+            Ok("10,10,2".to_string()),
+            Ok("11,11,1".to_string()),
+            Ok("DONE".to_string()),
+        ]
+        .into_iter();
+        handle_board_section(&mut lines, &mut game, false);
+
+        let shown = process_repl_command("show", &mut game).unwrap();
+        assert_eq!(shown.lines().count(), 21);
+        assert!(shown.lines().nth(11).unwrap().contains('O'));
+
+        assert!(process_repl_command("eval", &mut game)
+            .unwrap()
+            .starts_with("eval "));
+        assert!(!process_repl_command("moves", &mut game).unwrap().is_empty());
+
+        let undone = process_repl_command("UNDO", &mut game).unwrap();
+        assert!(undone.starts_with("undid "));
+        assert_eq!(game.board().occupied().count(), 2);
+
+        assert_eq!(process_repl_command("START 20", &mut game), None);
     }
 
     #[test]
+    #[allow(clippy::io_other_error)]
     fn test_handle_board_section_io_error() {
         let mut game = GameState::new();
         game.handle_start(20);
 
         let mut lines = vec![Err(io::Error::new(io::ErrorKind::Other, "boom"))].into_iter();
-        let response = handle_board_section(&mut lines, &mut game);
+        let response = handle_board_section(&mut lines, &mut game, false);
 
         assert_eq!(response, "ERROR reading board line: boom");
     }
@@ -0,0 +1,124 @@
+use crate::board::{Board, Cell};
+
+/// A known position and its reply, as offsets from the first stone listed.
+/// Every rotation and reflection of a line is matched as the same line.
+struct BookLine {
+    stones: &'static [(isize, isize, Cell)],
+    reply: (isize, isize),
+}
+
+const fn line(stones: &'static [(isize, isize, Cell)], reply: (isize, isize)) -> BookLine {
+    BookLine { stones, reply }
+}
+
+const M: Cell = Cell::MyStone;
+const O: Cell = Cell::OpStone;
+
+static BOOK: [BookLine; 12] = [
+    // Answering the opponent's first stone.
+    line(&[(0, 0, O)], (1, 1)),
+    // Our centre stone against the direct and indirect replies.
+    line(&[(0, 0, M), (0, -1, O)], (1, -1)),
+    line(&[(0, 0, M), (1, -1, O)], (1, 0)),
+    line(&[(0, 0, M), (0, -2, O)], (1, -1)),
+    line(&[(0, 0, M), (2, -2, O)], (1, 0)),
+    line(&[(0, 0, M), (1, -2, O)], (1, -1)),
+    line(&[(0, 0, M), (2, -1, O)], (1, -1)),
+    // Second move after answering diagonally.
+    line(&[(0, 0, O), (1, 1, M), (1, 0, O)], (1, -1)),
+    line(&[(0, 0, O), (1, 1, M), (-1, -1, O)], (0, 1)),
+    line(&[(0, 0, O), (1, 1, M), (2, 2, O)], (1, 0)),
+    line(&[(0, 0, O), (1, 1, M), (0, 2, O)], (1, 0)),
+    line(&[(0, 0, O), (1, 1, M), (-1, 0, O)], (0, 1)),
+];
+
+/// Book reply for the current position, if it is one of the book lines.
+/// An empty board is answered with the centre.
+pub fn book_move(board: &Board) -> Option<(usize, usize)> {
+    if board.stone_count() == 0 {
+        return Some((board.width() / 2, board.height() / 2));
+    }
+    BOOK.iter()
+        .filter(|line| line.stones.len() == board.stone_count())
+        .find_map(|line| match_line(board, line))
+}
+
+fn match_line(board: &Board, line: &BookLine) -> Option<(usize, usize)> {
+    let (_, _, anchor_cell) = line.stones[0];
+    for (ax, ay) in board.occupied() {
+        if board.get_cell(ax, ay) != Some(anchor_cell) {
+            continue;
+        }
+        for symmetry in 0..8 {
+            let place = |offset| shift(board, ax, ay, transform(symmetry, offset));
+            let matches = line.stones.iter().all(|&(dx, dy, cell)| {
+                place((dx, dy)).and_then(|(x, y)| board.get_cell(x, y)) == Some(cell)
+            });
+            if matches {
+                if let Some((x, y)) = place(line.reply).filter(|&(x, y)| board.is_empty(x, y)) {
+                    return Some((x, y));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// One of the eight symmetries of the square applied to an offset.
+fn transform(symmetry: usize, (dx, dy): (isize, isize)) -> (isize, isize) {
+    let (dx, dy) = if symmetry & 4 != 0 {
+        (dy, dx)
+    } else {
+        (dx, dy)
+    };
+    let dx = if symmetry & 1 != 0 { -dx } else { dx };
+    let dy = if symmetry & 2 != 0 { -dy } else { dy };
+    (dx, dy)
+}
+
+fn shift(board: &Board, x: usize, y: usize, (dx, dy): (isize, isize)) -> Option<(usize, usize)> {
+    let nx = x.checked_add_signed(dx)?;
+    let ny = y.checked_add_signed(dy)?;
+    (nx < board.width() && ny < board.height()).then_some((nx, ny))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board_with(stones: &[(usize, usize, Cell)]) -> Board {
+        let mut board = Board::default();
+        for &(x, y, cell) in stones {
+            board.set_cell(x, y, cell).unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn test_empty_board_plays_centre() {
+        assert_eq!(book_move(&Board::default()), Some((10, 10)));
+    }
+
+    #[test]
+    fn test_first_reply_is_adjacent_to_opponent() {
+        let board = board_with(&[(4, 7, O)]);
+        let (x, y) = book_move(&board).unwrap();
+        assert_eq!((x.abs_diff(4), y.abs_diff(7)), (1, 1));
+    }
+
+    #[test]
+    fn test_rotated_line_maps_to_rotated_reply() {
+        let board = board_with(&[(10, 10, M), (10, 9, O)]);
+        assert_eq!(book_move(&board), Some((11, 9)));
+        let rotated = board_with(&[(10, 10, M), (11, 10, O)]);
+        let (x, y) = book_move(&rotated).unwrap();
+        assert_eq!(x, 11);
+        assert_eq!(y.abs_diff(10), 1);
+    }
+
+    #[test]
+    fn test_unknown_position_is_not_in_book() {
+        let board = board_with(&[(10, 10, M), (3, 3, O)]);
+        assert_eq!(book_move(&board), None);
+    }
+}
@@ -0,0 +1,110 @@
+//! Thinking on the opponent's time: after each of our moves, a background
+//! thread searches our answer to the reply the engine expects, filling the
+//! shared transposition table the next search starts from.
+
+use crate::game::GameState;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// A ponder search running on its own thread until `finish`.
+pub struct Ponder {
+    guess: (usize, usize),
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Ponder {
+    /// Starts pondering the reply `game` expects, if it expects one.
+    pub fn start(game: &mut GameState) -> Option<Ponder> {
+        let (mut position, guess) = game.ponder_position()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let spawned = thread::Builder::new()
+            .name("ponder".to_string())
+            .spawn(move || position.ponder(flag));
+        match spawned {
+            Ok(handle) => Some(Ponder {
+                guess,
+                stop,
+                handle,
+            }),
+            Err(err) => {
+                debug!("could not start pondering: {}", err);
+                None
+            }
+        }
+    }
+
+    /// The opponent move being pondered.
+    pub fn guess(&self) -> (usize, usize) {
+        self.guess
+    }
+
+    /// Stops the search and waits for its thread, leaving the table to the
+    /// game again. Returns whether the opponent's `reply` was the move
+    /// pondered.
+    pub fn finish(self, reply: Option<(usize, usize)>) -> bool {
+        self.stop.store(true, Ordering::Relaxed);
+        if self.handle.join().is_err() {
+            debug!("ponder search panicked");
+        }
+        let hit = reply == Some(self.guess);
+        debug!(
+            "ponder on {},{} {}",
+            self.guess.0,
+            self.guess.1,
+            if hit { "hit" } else { "missed" }
+        );
+        hit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use std::time::{Duration, Instant};
+
+    fn pondering_game() -> (GameState, Ponder) {
+        let mut game = GameState::with_config(
+            GameConfig::builder()
+                .timeout_turn(Duration::from_millis(300))
+                .ponder(true)
+                .build(),
+        );
+        game.handle_start(20);
+        game.handle_begin();
+        let ponder = Ponder::start(&mut game).expect("a predicted reply");
+        (game, ponder)
+    }
+
+    #[test]
+    fn test_pondering_stops_cleanly_when_another_move_arrives() {
+        let (mut game, ponder) = pondering_game();
+        let table = game.memory_usage().tt;
+        let guess = ponder.guess();
+        let other = [(0, 0), (19, 19)]
+            .into_iter()
+            .find(|&m| m != guess)
+            .unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        let started = Instant::now();
+        assert!(!ponder.finish(Some(other)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(game.memory_usage().tt, table);
+
+        let reply = game.handle_turn(other.0, other.1);
+        assert!(!reply.starts_with("ERROR"), "{}", reply);
+    }
+
+    #[test]
+    fn test_pondered_reply_is_a_hit() {
+        let (mut game, ponder) = pondering_game();
+        let guess = ponder.guess();
+        assert!(ponder.finish(Some(guess)));
+        let reply = game.handle_turn(guess.0, guess.1);
+        assert!(!reply.starts_with("ERROR"), "{}", reply);
+    }
+}
@@ -1,13 +1,33 @@
+use crate::board::{MAX_BOARD_CELLS, MIN_BOARD_SIDE};
+use std::fmt;
+
+pub const ABOUT_NAME: &str = "pbrain-brainrot";
+pub const ABOUT_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const ABOUT_AUTHOR: &str = "Brainrot";
+pub const ABOUT_COUNTRY: &str = "FR";
+
+/// Largest coordinate any supported board has: its longest side is a
+/// minimum-width board holding every cell.
+pub const MAX_COORDINATE: usize = MAX_BOARD_CELLS / MIN_BOARD_SIDE - 1;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Command {
     Start(usize),
+    RectStart(usize, usize),
     Turn(usize, usize),
+    Play(usize, usize),
+    Takeback(usize, usize),
     Begin,
     Board,
+    YxBoard,
     Info(String, String),
     End,
     About,
     Restart,
+    Memory,
+    Swap,
+    Suggest,
+    Debug(String),
     Error(String),
     Unknown(String),
 }
@@ -40,20 +60,66 @@ pub fn parse_line(line: &str) -> Command {
                 Command::Error("Missing size for START".to_string())
             }
         }
+        "RECTSTART" => {
+            if parts.len() >= 2 {
+                let dims_str = parts[1..].join(" ");
+                parse_pair(&dims_str)
+                    .map(|(width, height)| Command::RectStart(width, height))
+                    .unwrap_or_else(|_| {
+                        Command::Error(format!("Invalid size for RECTSTART: {}", dims_str))
+                    })
+            } else {
+                Command::Error("Missing size for RECTSTART".to_string())
+            }
+        }
         "TURN" => {
             if parts.len() >= 2 {
-                let coords_str = parts[1..].join("");
+                let coords_str = parts[1..].join(" ");
                 parse_coordinates(&coords_str)
                     .map(|(x, y)| Command::Turn(x, y))
-                    .unwrap_or_else(|_| {
-                        Command::Error(format!("Invalid coordinates for TURN: {}", coords_str))
+                    .unwrap_or_else(|reason| {
+                        Command::Error(format!(
+                            "Invalid coordinates for TURN: {} ({})",
+                            coords_str, reason
+                        ))
                     })
             } else {
                 Command::Error("Missing coordinates for TURN".to_string())
             }
         }
+        "PLAY" => {
+            if parts.len() >= 2 {
+                let coords_str = parts[1..].join(" ");
+                parse_coordinates(&coords_str)
+                    .map(|(x, y)| Command::Play(x, y))
+                    .unwrap_or_else(|reason| {
+                        Command::Error(format!(
+                            "Invalid coordinates for PLAY: {} ({})",
+                            coords_str, reason
+                        ))
+                    })
+            } else {
+                Command::Error("Missing coordinates for PLAY".to_string())
+            }
+        }
+        "TAKEBACK" => {
+            if parts.len() >= 2 {
+                let coords_str = parts[1..].join(" ");
+                parse_coordinates(&coords_str)
+                    .map(|(x, y)| Command::Takeback(x, y))
+                    .unwrap_or_else(|reason| {
+                        Command::Error(format!(
+                            "Invalid coordinates for TAKEBACK: {} ({})",
+                            coords_str, reason
+                        ))
+                    })
+            } else {
+                Command::Error("Missing coordinates for TAKEBACK".to_string())
+            }
+        }
         "BEGIN" => Command::Begin,
         "BOARD" => Command::Board,
+        "YXBOARD" => Command::YxBoard,
         "INFO" => {
             if parts.len() >= 3 {
                 let value = parts[2..].join(" ");
@@ -65,19 +131,58 @@ pub fn parse_line(line: &str) -> Command {
         "END" => Command::End,
         "ABOUT" => Command::About,
         "RESTART" => Command::Restart,
+        "MEMORY" => Command::Memory,
+        "SWAP" => Command::Swap,
+        "SUGGEST" => Command::Suggest,
+        "DEBUG" => Command::Debug(parts[1..].join(" ").to_ascii_uppercase()),
         _ => Command::Unknown(raw_command.to_string()),
     }
 }
 
-fn parse_coordinates(s: &str) -> Result<(usize, usize), ()> {
-    let s = s.replace(' ', "");
-    let coords: Vec<&str> = s.split(',').filter(|p| !p.is_empty()).collect();
-    if coords.len() < 2 {
-        return Err(());
+pub fn about_string() -> String {
+    format!(
+        "name=\"{}\", version=\"{}\", author=\"{}\", country=\"{}\"",
+        ABOUT_NAME, ABOUT_VERSION, ABOUT_AUTHOR, ABOUT_COUNTRY
+    )
+}
+
+pub fn error_response(err: impl fmt::Display) -> String {
+    format!("ERROR {}", err)
+}
+
+pub fn parse_coordinates(s: &str) -> Result<(usize, usize), String> {
+    let (x, y) = parse_pair(s)?;
+    match [x, y].into_iter().find(|&c| c > MAX_COORDINATE) {
+        Some(c) => Err(format!("{} exceeds {}", c, MAX_COORDINATE)),
+        None => Ok((x, y)),
+    }
+}
+
+fn parse_pair(s: &str) -> Result<(usize, usize), String> {
+    let parts: Vec<&str> = if s.contains(',') {
+        s.split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect()
+    } else {
+        s.split_whitespace().collect()
+    };
+    if parts.len() < 2 {
+        return Err("expected two numbers".to_string());
     }
-    let x = coords[0].parse::<usize>().map_err(|_| ())?;
-    let y = coords[1].parse::<usize>().map_err(|_| ())?;
-    Ok((x, y))
+    Ok((parse_number(parts[0])?, parse_number(parts[1])?))
+}
+
+fn parse_number(part: &str) -> Result<usize, String> {
+    part.parse::<usize>().map_err(|_| {
+        if part.parse::<i64>().is_ok_and(|n| n < 0) {
+            format!("{} is negative", part)
+        } else if part.bytes().all(|b| b.is_ascii_digit()) {
+            format!("{} is too large", part)
+        } else {
+            format!("{} is not a number", part)
+        }
+    })
 }
 
 pub fn parse_board_line(line: &str) -> Result<BoardLine, String> {
@@ -114,6 +219,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_parse_start() {
         assert_eq!(parse_line("START 20"), Command::Start(20));
         match parse_line("START invalid") {
@@ -123,6 +229,21 @@ mod tests {
     }
 
     #[test]
+    fn test_parse_rectstart() {
+        assert_eq!(parse_line("RECTSTART 20,15"), Command::RectStart(20, 15));
+        assert_eq!(parse_line("rectstart 20, 15"), Command::RectStart(20, 15));
+        assert!(
+            matches!(parse_line("RECTSTART 20"), Command::Error(_)),
+            "Should be Error"
+        );
+        assert!(
+            matches!(parse_line("RECTSTART"), Command::Error(_)),
+            "Should be Error"
+        );
+    }
+
+    #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_parse_turn() {
         assert_eq!(parse_line("TURN 10,11"), Command::Turn(10, 11));
         match parse_line("TURN 10,invalid") {
@@ -131,13 +252,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_takeback() {
+        assert_eq!(parse_line("TAKEBACK 3,4"), Command::Takeback(3, 4));
+        assert!(
+            matches!(parse_line("TAKEBACK 3"), Command::Error(_)),
+            "Should be Error"
+        );
+    }
+
     #[test]
     fn test_parse_simple_commands() {
         assert_eq!(parse_line("BEGIN"), Command::Begin);
         assert_eq!(parse_line("BOARD"), Command::Board);
+        assert_eq!(parse_line("yxboard"), Command::YxBoard);
         assert_eq!(parse_line("END"), Command::End);
         assert_eq!(parse_line("ABOUT"), Command::About);
         assert_eq!(parse_line("RESTART"), Command::Restart);
+        assert_eq!(parse_line("MEMORY"), Command::Memory);
+        assert_eq!(parse_line("SWAP"), Command::Swap);
+        assert_eq!(parse_line("suggest"), Command::Suggest);
+        assert_eq!(parse_line("PLAY 3,4"), Command::Play(3, 4));
+        assert_eq!(parse_line("play 3 4"), Command::Play(3, 4));
+        assert!(matches!(parse_line("PLAY"), Command::Error(_)));
+        assert!(matches!(parse_line("PLAY 3,x"), Command::Error(_)));
     }
 
     #[test]
@@ -146,6 +284,24 @@ mod tests {
         assert_eq!(parse_line("Start 20"), Command::Start(20));
         assert_eq!(parse_line("begin"), Command::Begin);
         assert_eq!(parse_line("tUrN 3,4"), Command::Turn(3, 4));
+        assert_eq!(parse_line("turn 1,1"), Command::Turn(1, 1));
+        assert_eq!(parse_line("About"), Command::About);
+    }
+
+    #[test]
+    fn test_parse_info_keeps_key_and_value_case() {
+        assert_eq!(
+            parse_line("info Folder /Tmp/Brain"),
+            Command::Info("Folder".to_string(), "/Tmp/Brain".to_string())
+        );
+    }
+
+    #[test]
+    fn test_about_string_uses_crate_version() {
+        let about = about_string();
+        assert!(about.starts_with("name=\"pbrain-brainrot\""));
+        assert!(about.contains(&format!("version=\"{}\"", env!("CARGO_PKG_VERSION"))));
+        assert!(about.contains("country=\"FR\""));
     }
 
     #[test]
@@ -157,6 +313,16 @@ mod tests {
     }
 
     #[test]
+    fn test_parse_debug() {
+        assert_eq!(
+            parse_line("DEBUG board"),
+            Command::Debug("BOARD".to_string())
+        );
+        assert_eq!(parse_line("debug"), Command::Debug(String::new()));
+    }
+
+    #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_parse_unknown() {
         match parse_line("INVALID") {
             Command::Unknown(_) => assert!(true),
@@ -215,6 +381,46 @@ mod tests {
         assert_eq!(parse_line("TURN 10 , 11"), Command::Turn(10, 11));
         assert_eq!(parse_line("TURN  10,11"), Command::Turn(10, 11));
         assert_eq!(parse_line("TURN 10,  11"), Command::Turn(10, 11));
+        assert_eq!(parse_line("TURN 10, 10"), Command::Turn(10, 10));
+        assert_eq!(parse_line("TURN 10 10"), Command::Turn(10, 10));
+        assert_eq!(parse_line("TAKEBACK 3 4"), Command::Takeback(3, 4));
+        assert_eq!(parse_line("RECTSTART 20 20"), Command::RectStart(20, 20));
+    }
+
+    #[test]
+    fn test_parse_malformed_coordinates_are_errors() {
+        for line in ["TURN 10", "TURN a,b", "TURN 10 x", "TURN 1 0,11", "TURN ,"] {
+            assert!(
+                matches!(parse_line(line), Command::Error(_)),
+                "{} should not parse",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_coordinates_explains_bad_input() {
+        let error = |line: &str| match parse_line(line) {
+            Command::Error(message) => message,
+            other => panic!("{} parsed as {:?}", line, other),
+        };
+        assert!(error("TURN -1,5").contains("-1 is negative"));
+        assert!(error("PLAY 3,-0x").contains("-0x is not a number"));
+        assert!(error("TURN 999999,0").contains("999999 exceeds 79"));
+        assert!(error("TAKEBACK 99999999999999999999999,0").contains("too large"));
+        assert!(error("TURN ,").contains("expected two numbers"));
+        assert!(error("TURN   ").starts_with("Missing coordinates"));
+    }
+
+    #[test]
+    fn test_parse_coordinates_accepts_edges_and_whitespace() {
+        assert_eq!(parse_line("  TURN  0 , 0  "), Command::Turn(0, 0));
+        assert_eq!(
+            parse_line("TURN 79,\t79 "),
+            Command::Turn(MAX_COORDINATE, MAX_COORDINATE)
+        );
+        assert!(matches!(parse_line("TURN 80,0"), Command::Error(_)));
+        assert_eq!(parse_line("RECTSTART 80,5"), Command::RectStart(80, 5));
     }
 
     #[test]
@@ -247,4 +453,123 @@ mod tests {
         assert_eq!(parse_board_line("Done"), Ok(BoardLine::Done));
         assert_eq!(parse_board_line("dOnE"), Ok(BoardLine::Done));
     }
+
+    struct Fuzzer(u64);
+
+    impl Fuzzer {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 = crate::zobrist::xorshift64(self.0);
+            (self.0 % bound as u64) as usize
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            items[self.below(items.len())]
+        }
+
+        fn garbage(&mut self) -> String {
+            const ALPHABET: &[u8] = b"TURNSTARBEGIDOINFO0123456789,- \t\r";
+            let bytes: Vec<u8> = (0..self.below(40))
+                .map(|_| match self.below(4) {
+                    0 => self.below(256) as u8,
+                    _ => ALPHABET[self.below(ALPHABET.len())],
+                })
+                .collect();
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+
+        fn blanks(&mut self) -> &'static str {
+            self.pick(&["", " ", "  ", "\t"])
+        }
+
+        fn keyword(&mut self, word: &str) -> String {
+            word.chars()
+                .map(|c| match self.below(2) {
+                    0 => c.to_ascii_lowercase(),
+                    _ => c,
+                })
+                .collect()
+        }
+
+        fn number(&mut self) -> usize {
+            self.below(MAX_COORDINATE + 1)
+        }
+
+        fn word(&mut self) -> String {
+            self.pick(&["timeout_turn", "rule", "1000", "/tmp/Brain", "x"])
+                .to_string()
+        }
+
+        fn command(&mut self) -> (Command, String) {
+            let pair = |f: &mut Fuzzer, x: usize, y: usize| match f.below(3) {
+                0 => format!("{},{}", x, y),
+                1 => format!("{} , {}", x, y),
+                _ => format!("{} {}", x, y),
+            };
+            let (x, y) = (self.number(), self.number());
+            let (command, name, args) = match self.below(16) {
+                0 => (Command::Start(x), "START", x.to_string()),
+                1 => (Command::RectStart(x, y), "RECTSTART", pair(self, x, y)),
+                2 => (Command::Turn(x, y), "TURN", pair(self, x, y)),
+                3 => (Command::Play(x, y), "PLAY", pair(self, x, y)),
+                4 => (Command::Takeback(x, y), "TAKEBACK", pair(self, x, y)),
+                5 => (Command::Begin, "BEGIN", String::new()),
+                6 => (Command::Board, "BOARD", String::new()),
+                7 => {
+                    let key = self.word();
+                    let value = (0..1 + self.below(3))
+                        .map(|_| self.word())
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let args = format!("{} {}", key, value);
+                    (Command::Info(key, value), "INFO", args)
+                }
+                8 => (Command::End, "END", String::new()),
+                9 => (Command::About, "ABOUT", String::new()),
+                10 => (Command::Restart, "RESTART", String::new()),
+                11 => (Command::Memory, "MEMORY", String::new()),
+                12 => (Command::Swap, "SWAP", String::new()),
+                13 => (Command::Suggest, "SUGGEST", String::new()),
+                14 => (Command::YxBoard, "YXBOARD", String::new()),
+                _ => (
+                    Command::Debug("BOARD".to_string()),
+                    "DEBUG",
+                    "board".to_string(),
+                ),
+            };
+            let line = format!(
+                "{}{} {}{}",
+                self.blanks(),
+                self.keyword(name),
+                args,
+                self.blanks()
+            );
+            (command, line)
+        }
+    }
+
+    #[test]
+    fn test_fuzzed_lines_never_panic() {
+        let mut fuzzer = Fuzzer(0x2545f4914f6cdd1d);
+        for _ in 0..20_000 {
+            let line = fuzzer.garbage();
+            let _ = parse_line(&line);
+            let _ = parse_board_line(&line);
+            let (_, command) = fuzzer.command();
+            let cut = command
+                .char_indices()
+                .nth(fuzzer.below(command.len() + 1))
+                .map_or(command.len(), |(i, _)| i);
+            let _ = parse_line(&command[..cut]);
+            let _ = parse_line(&format!("{}{}", &command[..cut], line));
+        }
+    }
+
+    #[test]
+    fn test_fuzzed_well_formed_commands_round_trip() {
+        let mut fuzzer = Fuzzer(0x9e3779b97f4a7c15);
+        for _ in 0..20_000 {
+            let (command, line) = fuzzer.command();
+            assert_eq!(parse_line(&line), command, "{:?}", line);
+        }
+    }
 }
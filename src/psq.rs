@@ -0,0 +1,74 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A game in Piskvork's PSQ record format: a `Piskvorky WxH` header, then
+/// one `x,y,ms` line per stone with 1-based coordinates and the time taken
+/// on that move.
+pub fn format_psq(width: usize, height: usize, moves: &[(usize, usize, u128)]) -> String {
+    let mut out = format!("Piskvorky {}x{}, 11:11, 0\n", width, height);
+    for &(x, y, ms) in moves {
+        out.push_str(&format!("{},{},{}\n", x + 1, y + 1, ms));
+    }
+    out
+}
+
+/// Writes `record` to a new `.psq` file in `dir`, named after the current
+/// time, the process id and a per-process counter. The file is created
+/// exclusively, moving on to the next counter if the name is taken, so
+/// engines sharing a directory never overwrite each other's games.
+pub fn write_psq(dir: &Path, record: &str) -> io::Result<PathBuf> {
+    static GAMES: AtomicUsize = AtomicUsize::new(0);
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    loop {
+        let game = GAMES.fetch_add(1, Ordering::Relaxed) + 1;
+        let name = format!("game-{}-{}-{}.psq", stamp, process::id(), game);
+        let path = dir.join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(record.as_bytes())?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_format_psq_is_one_based_with_times() {
+        let record = format_psq(20, 20, &[(9, 9, 0), (10, 9, 1234)]);
+        assert_eq!(record, "Piskvorky 20x20, 11:11, 0\n10,10,0\n11,10,1234\n");
+    }
+
+    #[test]
+    fn test_write_psq_creates_distinct_files() {
+        let dir = std::env::temp_dir();
+        let first = write_psq(&dir, "a").unwrap();
+        let second = write_psq(&dir, "b").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "a");
+        let _ = fs::remove_file(first);
+        let _ = fs::remove_file(second);
+    }
+
+    #[test]
+    fn test_write_psq_names_carry_the_process_id() {
+        let dir = std::env::temp_dir();
+        let path = write_psq(&dir, "c").unwrap();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.contains(&format!("-{}-", process::id())), "{}", name);
+        let _ = fs::remove_file(path);
+    }
+}
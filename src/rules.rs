@@ -0,0 +1,218 @@
+use crate::board::{Board, Cell, DIRECTIONS};
+
+/// Rule variant announced by the manager through `INFO rule`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RuleSet {
+    #[default]
+    Freestyle,
+    Standard,
+    Renju,
+}
+
+impl RuleSet {
+    /// Decodes the Gomocup `INFO rule` bitmask (1 = exactly five, 4 = renju).
+    pub fn from_info(value: &str) -> Option<Self> {
+        let bits = value.trim().parse::<u32>().ok()?;
+        Some(if bits & 4 != 0 {
+            RuleSet::Renju
+        } else if bits & 1 != 0 {
+            RuleSet::Standard
+        } else {
+            RuleSet::Freestyle
+        })
+    }
+}
+
+/// Renju prohibitions for black: a move is forbidden when it makes an
+/// overline, two fours, or two open threes, unless it also makes an exact
+/// five. Threes are judged by whether they can become a straight four,
+/// without recursively checking that the completing move is itself legal.
+pub fn is_forbidden_for_black(board: &Board, x: usize, y: usize, black: Cell) -> bool {
+    if !board.is_empty(x, y) {
+        return false;
+    }
+    let mut lines = DIRECTIONS.map(|dir| read_line(board, x, y, dir));
+    for line in &mut lines {
+        line[CENTER] = Some(black);
+    }
+
+    let runs = lines.map(|line| run_length(&line, black));
+    if runs.contains(&5) {
+        return false;
+    }
+    if runs.iter().any(|&run| run > 5) {
+        return true;
+    }
+
+    let mut fours = 0;
+    let mut threes = 0;
+    for line in &mut lines {
+        if makes_four(line, black) {
+            fours += 1;
+        } else if makes_open_three(line, black) {
+            threes += 1;
+        }
+    }
+    fours >= 2 || threes >= 2
+}
+
+/// How far either side of the move a line is read: far enough to see both
+/// ends of a straight four made with one more stone, and past a run of five.
+const LINE_REACH: usize = 5;
+/// Index of the move itself in a `Line`.
+const CENTER: usize = LINE_REACH;
+
+/// The cells of one line through the move, `None` where it leaves the
+/// board. Each check places its trial stones here rather than on the board.
+type Line = [Option<Cell>; 2 * LINE_REACH + 1];
+
+fn read_line(board: &Board, x: usize, y: usize, (dx, dy): (isize, isize)) -> Line {
+    let mut line = [None; 2 * LINE_REACH + 1];
+    for (i, cell) in line.iter_mut().enumerate() {
+        let k = i as isize - CENTER as isize;
+        let (nx, ny) = (x as isize + dx * k, y as isize + dy * k);
+        if nx >= 0 && ny >= 0 {
+            *cell = board.get_cell(nx as usize, ny as usize);
+        }
+    }
+    line
+}
+
+/// The cell `k` steps from the move, if the line was read that far.
+fn offset_cell(line: &Line, k: isize) -> Option<Cell> {
+    let i = CENTER as isize + k;
+    if i < 0 {
+        return None;
+    }
+    line.get(i as usize).copied().flatten()
+}
+
+/// Stones of `player` directly after the move in the `sign` direction.
+fn reach(line: &Line, sign: isize, player: Cell) -> isize {
+    (1..)
+        .take_while(|&k| offset_cell(line, sign * k) == Some(player))
+        .count() as isize
+}
+
+/// Length of the unbroken run of `player` through the move; anything
+/// longer than five may be cut short at the line's ends.
+fn run_length(line: &Line, player: Cell) -> usize {
+    (1 + reach(line, 1, player) + reach(line, -1, player)) as usize
+}
+
+/// Runs `check` with black tried on each empty cell near the move,
+/// reporting whether any of them passes.
+fn any_extension(line: &mut Line, black: Cell, check: impl Fn(&Line) -> bool) -> bool {
+    for k in (-4..=4isize).filter(|&k| k != 0) {
+        let i = (CENTER as isize + k) as usize;
+        if line[i] != Some(Cell::Empty) {
+            continue;
+        }
+        line[i] = Some(black);
+        let passes = check(line);
+        line[i] = Some(Cell::Empty);
+        if passes {
+            return true;
+        }
+    }
+    false
+}
+
+/// Whether one more stone on this line completes an exact five through the
+/// move.
+fn makes_four(line: &mut Line, black: Cell) -> bool {
+    any_extension(line, black, |line| run_length(line, black) == 5)
+}
+
+/// Whether one more stone on this line makes a straight four through the
+/// move: four in a row whose ends are both empty and extend to an exact five.
+fn makes_open_three(line: &mut Line, black: Cell) -> bool {
+    any_extension(line, black, |line| {
+        if run_length(line, black) != 4 {
+            return false;
+        }
+        [1, -1].iter().all(|&sign| {
+            let end = sign * (reach(line, sign, black) + 1);
+            offset_cell(line, end) == Some(Cell::Empty)
+                && offset_cell(line, end + sign) != Some(black)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_from_info() {
+        assert_eq!(RuleSet::from_info("0"), Some(RuleSet::Freestyle));
+        assert_eq!(RuleSet::from_info("1"), Some(RuleSet::Standard));
+        assert_eq!(RuleSet::from_info("4"), Some(RuleSet::Renju));
+        assert_eq!(RuleSet::from_info("5"), Some(RuleSet::Renju));
+        assert_eq!(RuleSet::from_info("renju"), None);
+    }
+
+    const BLACK: Cell = Cell::OpStone;
+
+    fn board_with(stones: &[(usize, usize)]) -> Board {
+        let mut board = Board::default();
+        for &(x, y) in stones {
+            board.set_cell(x, y, BLACK).unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn test_lines_stop_at_the_board_edge() {
+        let board = board_with(&[(0, 0), (1, 0), (3, 0), (4, 0), (5, 0)]);
+        assert!(is_forbidden_for_black(&board, 2, 0, BLACK));
+        let board = board_with(&[(15, 19), (16, 19), (17, 19), (18, 19)]);
+        assert!(!is_forbidden_for_black(&board, 19, 19, BLACK));
+    }
+
+    #[test]
+    fn test_double_three_is_forbidden() {
+        let board = board_with(&[(8, 10), (9, 10), (10, 8), (10, 9)]);
+        assert!(is_forbidden_for_black(&board, 10, 10, BLACK));
+    }
+
+    #[test]
+    fn test_split_double_three_is_forbidden() {
+        let board = board_with(&[(7, 10), (8, 10), (10, 8), (10, 9)]);
+        assert!(is_forbidden_for_black(&board, 10, 10, BLACK));
+    }
+
+    #[test]
+    fn test_blocked_three_does_not_count() {
+        let mut board = board_with(&[(8, 10), (9, 10), (10, 8), (10, 9)]);
+        board.set_cell(7, 10, Cell::MyStone).unwrap();
+        assert!(!is_forbidden_for_black(&board, 10, 10, BLACK));
+    }
+
+    #[test]
+    fn test_double_four_is_forbidden() {
+        let board = board_with(&[(7, 10), (8, 10), (9, 10), (10, 7), (10, 8), (10, 9)]);
+        let mut blocked = board;
+        blocked.set_cell(6, 10, Cell::MyStone).unwrap();
+        blocked.set_cell(10, 6, Cell::MyStone).unwrap();
+        assert!(is_forbidden_for_black(&blocked, 10, 10, BLACK));
+    }
+
+    #[test]
+    fn test_overline_is_forbidden() {
+        let board = board_with(&[(5, 10), (6, 10), (7, 10), (9, 10), (10, 10)]);
+        assert!(is_forbidden_for_black(&board, 8, 10, BLACK));
+    }
+
+    #[test]
+    fn test_exact_five_wins_despite_other_threats() {
+        let board = board_with(&[(6, 10), (7, 10), (8, 10), (9, 10), (10, 8), (10, 9)]);
+        assert!(!is_forbidden_for_black(&board, 10, 10, BLACK));
+    }
+
+    #[test]
+    fn test_single_three_is_allowed() {
+        let board = board_with(&[(8, 10), (9, 10)]);
+        assert!(!is_forbidden_for_black(&board, 10, 10, BLACK));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::board::{Board, Cell};
+use crate::config::GameConfig;
+use crate::game::GameState;
+
+/// A finished engine-vs-engine game. Moves alternate starting with the
+/// first player, opening stones included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfPlayGame {
+    pub moves: Vec<(usize, usize)>,
+    /// 0 for the first player, 1 for the second, `None` for a full board.
+    pub winner: Option<usize>,
+}
+
+/// Plays two engines built from `config` against each other on a
+/// `config.board_size` board, starting from `opening`, until one makes five
+/// or the board fills. An engine that
+/// answers with anything but a legal move forfeits.
+pub fn play(config: &GameConfig, opening: &[(usize, usize)]) -> SelfPlayGame {
+    let mut engines = [
+        GameState::with_config(config.clone()),
+        GameState::with_config(config.clone()),
+    ];
+    let mut referee = Board::new(config.board_size).unwrap_or_default();
+    let mut moves = Vec::new();
+    for engine in &mut engines {
+        engine.handle_start(referee.width());
+    }
+
+    for &(x, y) in opening {
+        let stone = stone_of(moves.len());
+        if !referee.is_empty(x, y) || referee.set_cell(x, y, stone).is_err() {
+            break;
+        }
+        moves.push((x, y));
+    }
+
+    let mut started = [false; 2];
+    loop {
+        let side = moves.len() % 2;
+        if referee.check_win(stone_of(side + 1), false) {
+            return SelfPlayGame {
+                moves,
+                winner: Some(1 - side),
+            };
+        }
+        if referee.is_full() {
+            return SelfPlayGame {
+                moves,
+                winner: None,
+            };
+        }
+
+        let reply = if started[side] {
+            let &(x, y) = moves.last().expect("the other side has moved");
+            engines[side].handle_turn(x, y)
+        } else {
+            started[side] = true;
+            send_position(&mut engines[side], &moves, side)
+        };
+
+        match parse_move(&reply).filter(|&(x, y)| referee.is_empty(x, y)) {
+            Some((x, y)) => {
+                let _ = referee.set_cell(x, y, stone_of(side));
+                moves.push((x, y));
+            }
+            None => {
+                return SelfPlayGame {
+                    moves,
+                    winner: Some(1 - side),
+                }
+            }
+        }
+    }
+}
+
+/// Hands an engine the whole game so far through `BOARD` and returns its
+/// reply; used for each side's first move.
+fn send_position(engine: &mut GameState, moves: &[(usize, usize)], side: usize) -> String {
+    if moves.is_empty() {
+        return engine.handle_begin();
+    }
+    let _ = engine.handle_board_start();
+    for (i, &(x, y)) in moves.iter().enumerate() {
+        let field = if i % 2 == side { 1 } else { 2 };
+        let _ = engine.handle_board_move(x, y, field);
+    }
+    engine.handle_board_done()
+}
+
+/// Referee stone for the player who makes the `ply`-th move.
+fn stone_of(ply: usize) -> Cell {
+    if ply % 2 == 0 {
+        Cell::MyStone
+    } else {
+        Cell::OpStone
+    }
+}
+
+/// The `x,y` on the last line of an engine reply.
+fn parse_move(reply: &str) -> Option<(usize, usize)> {
+    let (x, y) = reply.lines().last()?.split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+/// Two distinct opening stones near the centre picked from `seed`; seed 0
+/// starts from an empty board.
+pub fn seeded_opening(seed: u64) -> Vec<(usize, usize)> {
+    if seed == 0 {
+        return Vec::new();
+    }
+    let mut state = seed;
+    let mut next_cell = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let offset = (state % 25) as usize;
+        (8 + offset % 5, 8 + offset / 5)
+    };
+    let first = next_cell();
+    let second = std::iter::repeat_with(next_cell)
+        .find(|&cell| cell != first)
+        .unwrap_or((first.0 + 1, first.1));
+    vec![first, second]
+}
+
+/// Runs one self-play game from `config.seed`'s opening and renders it as
+/// the move list, one `x,y` per line, followed by the result.
+pub fn run(config: &GameConfig) -> String {
+    let game = play(config, &seeded_opening(config.seed));
+    let mut out: Vec<String> = game
+        .moves
+        .iter()
+        .map(|(x, y)| format!("{},{}", x, y))
+        .collect();
+    out.push(match game.winner {
+        Some(0) => "RESULT first".to_string(),
+        Some(_) => "RESULT second".to_string(),
+        None => "RESULT draw".to_string(),
+    });
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EngineKind;
+    use std::time::Duration;
+
+    #[test]
+    fn test_selfplay_game_terminates_with_legal_moves() {
+        let config = GameConfig::builder()
+            .timeout_turn(Duration::from_millis(30))
+            .time_margin(Duration::ZERO)
+            .max_depth(2)
+            .build();
+        let opening = seeded_opening(7);
+        let game = play(&config, &opening);
+
+        assert_eq!(&game.moves[..2], &opening[..]);
+        let mut board = Board::default();
+        for (i, &(x, y)) in game.moves.iter().enumerate() {
+            assert!(board.is_empty(x, y), "move {} at {},{} is taken", i, x, y);
+            board.set_cell(x, y, stone_of(i)).unwrap();
+        }
+        let winner = game.winner.expect("a short game should not fill the board");
+        assert_eq!(winner, (game.moves.len() - 1) % 2);
+        assert!(board.check_win(stone_of(winner), false));
+    }
+
+    #[test]
+    fn test_selfplay_uses_the_configured_board_size() {
+        let config = GameConfig::builder()
+            .board_size(15)
+            .engine(EngineKind::Greedy)
+            .seed(3)
+            .build();
+        let game = play(&config, &seeded_opening(config.seed));
+
+        assert!(game.winner.is_some() || game.moves.len() == 15 * 15);
+        assert!(game.moves.iter().all(|&(x, y)| x < 15 && y < 15));
+        let (x, y) = seeded_opening(3)[0];
+        assert!(run(&config).starts_with(&format!("{},{}\n", x, y)));
+    }
+
+    #[test]
+    fn test_seeded_opening_is_reproducible() {
+        assert!(seeded_opening(0).is_empty());
+        let opening = seeded_opening(42);
+        assert_eq!(opening, seeded_opening(42));
+        assert_eq!(opening.len(), 2);
+        assert_ne!(opening[0], opening[1]);
+    }
+
+    #[test]
+    fn test_parse_move_reads_last_line() {
+        assert_eq!(parse_move("MESSAGE resign-eval\n3,4"), Some((3, 4)));
+        assert_eq!(parse_move("ERROR no move"), None);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::config::GameConfig;
+use std::time::Duration;
+
+/// Thinking time used when the manager gave no limits at all.
+const DEFAULT_BUDGET: Duration = Duration::from_secs(5);
+/// Moves of our own a game is expected to last; the remaining match time is
+/// spread over whatever is left of it.
+const EXPECTED_OWN_MOVES: u32 = 24;
+/// Floor on the moves still expected, so a long game never bets the whole
+/// clock on a single move.
+const MIN_MOVES_LEFT: u32 = 8;
+
+/// Turns the manager's `timeout_turn`, `timeout_match` and `time_left` into
+/// a thinking budget for the next move.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeManager {
+    timeout_turn: Option<Duration>,
+    timeout_match: Option<Duration>,
+    time_left: Option<Duration>,
+    margin: Duration,
+}
+
+impl TimeManager {
+    pub fn new(config: &GameConfig, time_left: Option<Duration>) -> Self {
+        Self {
+            timeout_turn: config.timeout_turn,
+            timeout_match: config.timeout_match,
+            time_left,
+            margin: config.time_margin,
+        }
+    }
+
+    /// Budget for the next move once `stones` are on the board. A
+    /// `timeout_turn` of 0 means the turn itself is unlimited, so the budget
+    /// comes only from the match clock.
+    pub fn move_budget(&self, stones: usize) -> Duration {
+        let budget = match (self.turn_limit(), self.match_share(stones)) {
+            (Some(turn), Some(share)) => share.min(turn),
+            (Some(turn), None) => turn,
+            (None, Some(share)) => share,
+            (None, None) => DEFAULT_BUDGET,
+        };
+        budget.saturating_sub(self.margin)
+    }
+
+    fn turn_limit(&self) -> Option<Duration> {
+        self.timeout_turn.filter(|turn| !turn.is_zero())
+    }
+
+    fn match_share(&self, stones: usize) -> Option<Duration> {
+        let remaining = self
+            .time_left
+            .or(self.timeout_match.filter(|total| !total.is_zero()))?;
+        Some(remaining / moves_left(stones))
+    }
+}
+
+/// Our moves still expected in a game that already has `stones` played.
+fn moves_left(stones: usize) -> u32 {
+    let own_moves = u32::try_from(stones / 2).unwrap_or(u32::MAX);
+    EXPECTED_OWN_MOVES
+        .saturating_sub(own_moves)
+        .max(MIN_MOVES_LEFT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(turn: u64, total: u64, left: Option<u64>, margin: u64) -> TimeManager {
+        let config = GameConfig::builder()
+            .timeout_turn(Duration::from_millis(turn))
+            .timeout_match(Duration::from_millis(total))
+            .time_margin(Duration::from_millis(margin))
+            .build();
+        TimeManager::new(&config, left.map(Duration::from_millis))
+    }
+
+    #[test]
+    fn test_no_limits_uses_default_budget() {
+        let config = GameConfig::builder().time_margin(Duration::ZERO).build();
+        let manager = TimeManager::new(&config, None);
+        assert_eq!(manager.move_budget(0), DEFAULT_BUDGET);
+    }
+
+    #[test]
+    fn test_endgame_gets_more_time_per_move_than_opening() {
+        let manager = manager(0, 180_000, Some(60_000), 0);
+        let opening = manager.move_budget(0);
+        let endgame = manager.move_budget(60);
+        assert_eq!(opening, Duration::from_millis(2_500));
+        assert_eq!(endgame, Duration::from_millis(7_500));
+    }
+
+    #[test]
+    fn test_long_game_keeps_a_floor_of_moves_left() {
+        let manager = manager(0, 180_000, Some(8_000), 0);
+        assert_eq!(manager.move_budget(200), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_turn_limit_caps_match_share_minus_margin() {
+        assert_eq!(
+            manager(1_000, 180_000, None, 0).move_budget(0),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            manager(1_000, 180_000, None, 200).move_budget(0),
+            Duration::from_millis(800)
+        );
+    }
+}
@@ -1,23 +1,29 @@
-use crate::board::Cell;
+use crate::board::{Cell, MAX_BOARD_CELLS};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 
-const BOARD_SIZE: usize = 400;
 const NUM_STONE_TYPES: usize = 2;
 
 pub struct ZobristKeys {
-    stones: [[u64; NUM_STONE_TYPES]; BOARD_SIZE],
+    stones: [[u64; NUM_STONE_TYPES]; MAX_BOARD_CELLS],
     turn: u64,
 }
 
+impl Default for ZobristKeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ZobristKeys {
     pub fn new() -> Self {
         let mut keys = Self {
-            stones: [[0; NUM_STONE_TYPES]; BOARD_SIZE],
+            stones: [[0; NUM_STONE_TYPES]; MAX_BOARD_CELLS],
             turn: 0,
         };
 
         let mut state = 0x853c49e6748fea9bu64;
 
-        for pos in 0..BOARD_SIZE {
+        for pos in 0..MAX_BOARD_CELLS {
             for stone in 0..NUM_STONE_TYPES {
                 state = xorshift64(state);
                 keys.stones[pos][stone] = state;
@@ -46,8 +52,9 @@ impl ZobristKeys {
     }
 }
 
+/// One step of Marsaglia's xorshift generator; `state` must not be zero.
 #[inline]
-fn xorshift64(mut state: u64) -> u64 {
+pub(crate) fn xorshift64(mut state: u64) -> u64 {
     state ^= state << 13;
     state ^= state >> 7;
     state ^= state << 17;
@@ -62,82 +69,194 @@ pub enum TTFlag {
     UpperBound = 2,
 }
 
-#[derive(Clone, Copy)]
-#[repr(C)]
+/// A decoded table entry. Entries are copied out of the table, so a probe
+/// never holds on to memory another thread may be writing.
+#[derive(Clone, Copy, Debug)]
 pub struct TTEntry {
     pub key: u64,
     pub score: i32,
     pub best_move: Option<(u8, u8)>,
     pub depth: u8,
     pub flag: TTFlag,
+    /// Search that wrote the entry, modulo `GENERATIONS`; older ones are
+    /// the first to go.
+    pub generation: u8,
 }
 
-impl Default for TTEntry {
-    fn default() -> Self {
+/// Generations are stored in 7 bits and compared only for equality.
+const GENERATIONS: u8 = 1 << 7;
+/// Width of each coordinate of the stored best move.
+const MOVE_BITS: u32 = 7;
+const MOVE_MASK: u64 = (1 << MOVE_BITS) - 1;
+
+impl TTEntry {
+    /// Packs everything but the key: score in the low 32 bits, then depth,
+    /// flag, a has-move bit, the move's x and y, and the generation.
+    fn pack(&self) -> u64 {
+        let (has_move, x, y) = match self.best_move {
+            Some((x, y)) => (1, x as u64 & MOVE_MASK, y as u64 & MOVE_MASK),
+            None => (0, 0, 0),
+        };
+        (self.score as u32 as u64)
+            | (self.depth as u64) << 32
+            | (self.flag as u64) << 40
+            | has_move << 42
+            | x << 43
+            | y << (43 + MOVE_BITS)
+            | ((self.generation % GENERATIONS) as u64) << (43 + 2 * MOVE_BITS)
+    }
+
+    fn unpack(key: u64, data: u64) -> Self {
+        let flag = match (data >> 40) & 0b11 {
+            1 => TTFlag::LowerBound,
+            2 => TTFlag::UpperBound,
+            _ => TTFlag::Exact,
+        };
+        let best_move = ((data >> 42) & 1 == 1).then_some((
+            ((data >> 43) & MOVE_MASK) as u8,
+            ((data >> (43 + MOVE_BITS)) & MOVE_MASK) as u8,
+        ));
         Self {
-            key: 0,
-            score: 0,
-            best_move: None,
-            depth: 0,
-            flag: TTFlag::Exact,
+            key,
+            score: data as u32 as i32,
+            best_move,
+            depth: (data >> 32) as u8,
+            flag,
+            generation: (data >> (43 + 2 * MOVE_BITS)) as u8,
         }
     }
 }
 
-const TT_SIZE: usize = 1 << 22; // 4,194,304 entries (~67MB)
+/// One entry as two words: the packed data, and the key XORed with it. A
+/// read racing a write from another thread sees a mismatched pair and
+/// misses instead of returning another position's data.
+#[derive(Default)]
+struct Slot {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    fn load(&self) -> TTEntry {
+        let data = self.data.load(Ordering::Relaxed);
+        let check = self.check.load(Ordering::Relaxed);
+        TTEntry::unpack(check ^ data, data)
+    }
+
+    fn save(&self, entry: &TTEntry) {
+        let data = entry.pack();
+        self.check.store(entry.key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        self.check.store(0, Ordering::Relaxed);
+        self.data.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Two entries sharing an index: the first is kept for the deepest result of
+/// the current search, the second takes whatever was stored last.
+type Bucket = [Slot; 2];
 
+/// Shared by every thread searching the same game. Entries are written
+/// without locks, so a racing store can only cost a lost or missed entry.
 pub struct TranspositionTable {
-    entries: Vec<TTEntry>,
+    entries: Vec<Bucket>,
+    mask: usize,
+    generation: AtomicU8,
 }
 
 impl TranspositionTable {
-    pub fn new() -> Self {
+    /// A table using at most half of `bytes`, leaving the rest of the memory
+    /// budget to the board, evaluation tables and stack. The bucket count is
+    /// always a power of two so indexing can mask the hash.
+    pub fn with_capacity(bytes: usize) -> Self {
+        let budget = bytes / 2 / std::mem::size_of::<Bucket>();
+        let len = if budget.is_power_of_two() {
+            budget
+        } else {
+            (budget.next_power_of_two() / 2).max(1)
+        };
         Self {
-            entries: vec![TTEntry::default(); TT_SIZE],
+            entries: (0..len).map(|_| Bucket::default()).collect(),
+            mask: len - 1,
+            generation: AtomicU8::new(0),
         }
     }
 
     #[inline]
     fn index(&self, hash: u64) -> usize {
-        (hash as usize) & (TT_SIZE - 1)
+        (hash as usize) & self.mask
     }
 
-    pub fn probe(&self, hash: u64) -> Option<&TTEntry> {
-        let idx = self.index(hash);
-        let entry = &self.entries[idx];
-        if entry.key == hash {
-            Some(entry)
-        } else {
-            None
-        }
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Starts a new search, so entries stored so far count as stale.
+    pub fn new_search(&self) {
+        let next = (self.generation() + 1) % GENERATIONS;
+        self.generation.store(next, Ordering::Relaxed);
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TTEntry> {
+        self.entries[self.index(hash)]
+            .iter()
+            .map(Slot::load)
+            .find(|entry| entry.key == hash)
     }
 
     pub fn store(
-        &mut self,
+        &self,
         hash: u64,
         depth: u8,
         score: i32,
         flag: TTFlag,
         best_move: Option<(usize, usize)>,
     ) {
-        let idx = self.index(hash);
-        let existing = &self.entries[idx];
-
-        if existing.key == 0 || existing.depth <= depth {
-            self.entries[idx] = TTEntry {
-                key: hash,
-                score,
-                best_move: best_move.map(|(x, y)| (x as u8, y as u8)),
-                depth,
-                flag,
-            };
+        let generation = self.generation();
+        let entry = TTEntry {
+            key: hash,
+            score,
+            best_move: best_move.map(|(x, y)| (x as u8, y as u8)),
+            depth,
+            flag,
+            generation,
+        };
+        let [preferred_slot, recent_slot] = &self.entries[self.index(hash)];
+        let preferred = preferred_slot.load();
+
+        // A position already held is updated where it is, so the bucket
+        // never answers a probe with a stale copy of the same key.
+        if preferred.key == hash {
+            preferred_slot.save(&entry);
+            return;
+        }
+        let takes_preferred =
+            preferred.key == 0 || preferred.generation != generation || depth >= preferred.depth;
+        if takes_preferred {
+            // The displaced entry is still worth keeping until the next store;
+            // it also replaces any older copy of `hash` in the recent slot.
+            if preferred.key != 0 {
+                recent_slot.save(&preferred);
+            }
+            preferred_slot.save(&entry);
+        } else {
+            recent_slot.save(&entry);
         }
     }
 
-    pub fn clear(&mut self) {
-        for entry in &mut self.entries {
-            *entry = TTEntry::default();
+    /// Bytes held by the entry array.
+    pub fn memory_bytes(&self) -> usize {
+        self.entries.len() * std::mem::size_of::<Bucket>()
+    }
+
+    pub fn clear(&self) {
+        for slot in self.entries.iter().flatten() {
+            slot.reset();
         }
+        self.generation.store(0, Ordering::Relaxed);
     }
 }
 
@@ -145,6 +264,8 @@ impl TranspositionTable {
 mod tests {
     use super::*;
 
+    const TEST_TT_BYTES: usize = 1 << 20;
+
     #[test]
     fn test_zobrist_keys_deterministic() {
         let keys1 = ZobristKeys::new();
@@ -166,7 +287,7 @@ mod tests {
 
     #[test]
     fn test_tt_store_and_probe() {
-        let mut tt = TranspositionTable::new();
+        let tt = TranspositionTable::with_capacity(TEST_TT_BYTES);
         let hash = 0x123456789abcdef0u64;
 
         tt.store(hash, 5, 100, TTFlag::Exact, Some((10, 10)));
@@ -179,15 +300,64 @@ mod tests {
         assert_eq!(entry.best_move, Some((10, 10)));
     }
 
+    #[test]
+    fn test_tt_entries_round_trip_every_field() {
+        let tt = TranspositionTable::with_capacity(TEST_TT_BYTES);
+        for _ in 0..GENERATIONS + 3 {
+            tt.new_search();
+        }
+        tt.store(0xfeed, 255, -199_999, TTFlag::UpperBound, Some((79, 79)));
+        let entry = tt.probe(0xfeed).unwrap();
+        assert_eq!(entry.depth, 255);
+        assert_eq!(entry.score, -199_999);
+        assert_eq!(entry.flag, TTFlag::UpperBound);
+        assert_eq!(entry.best_move, Some((79, 79)));
+        assert_eq!(entry.generation, tt.generation());
+        assert_eq!(tt.generation(), 3);
+    }
+
+    #[test]
+    fn test_tt_torn_entry_misses() {
+        let tt = TranspositionTable::with_capacity(TEST_TT_BYTES);
+        tt.store(0xfeed, 4, 10, TTFlag::Exact, None);
+        let slot = &tt.entries[tt.index(0xfeed)][0];
+        slot.data.fetch_xor(1 << 32, Ordering::Relaxed);
+        assert!(tt.probe(0xfeed).is_none());
+    }
+
     #[test]
     fn test_tt_probe_miss() {
-        let tt = TranspositionTable::new();
+        let tt = TranspositionTable::with_capacity(TEST_TT_BYTES);
         assert!(tt.probe(0x123456789abcdef0u64).is_none());
     }
 
+    #[test]
+    fn test_tt_memory_bytes() {
+        // Half of a 1 MiB budget, which a power-of-two bucket count fills.
+        let tt = TranspositionTable::with_capacity(TEST_TT_BYTES);
+        assert_eq!(tt.memory_bytes(), 512 * 1024);
+
+        let budget = 3 * 1024 * 1024;
+        let tt = TranspositionTable::with_capacity(budget);
+        assert!(tt.memory_bytes() <= budget / 2);
+        assert!(tt.memory_bytes() >= budget / 4);
+    }
+
+    #[test]
+    fn test_tt_capacity_is_power_of_two_within_budget() {
+        for bytes in [0, 1, 100, 4096, 70_000_000, 70 * 1024 * 1024, 1 << 30] {
+            let tt = TranspositionTable::with_capacity(bytes);
+            let len = tt.entries.len();
+            assert!(len.is_power_of_two(), "{} bytes -> {} entries", bytes, len);
+            assert_eq!(tt.mask, len - 1);
+            assert!(len == 1 || tt.memory_bytes() <= bytes / 2);
+            assert!(tt.memory_bytes() * 2 + std::mem::size_of::<Bucket>() > bytes / 2);
+        }
+    }
+
     #[test]
     fn test_tt_depth_replacement() {
-        let mut tt = TranspositionTable::new();
+        let tt = TranspositionTable::with_capacity(TEST_TT_BYTES);
         let hash = 0x123456789abcdef0u64;
 
         tt.store(hash, 3, 50, TTFlag::LowerBound, None);
@@ -197,4 +367,69 @@ mod tests {
         assert_eq!(entry.depth, 5);
         assert_eq!(entry.score, 100);
     }
+
+    #[test]
+    fn test_tt_same_key_is_overwritten_in_place() {
+        let tt = TranspositionTable::with_capacity(TEST_TT_BYTES);
+        let (deep, other, _) = colliding_keys();
+
+        tt.store(deep, 8, 10, TTFlag::Exact, None);
+        tt.store(deep, 2, 20, TTFlag::UpperBound, None);
+        assert_eq!(tt.probe(deep).map(|e| (e.depth, e.score)), Some((2, 20)));
+
+        tt.store(other, 9, 30, TTFlag::Exact, None);
+        tt.store(deep, 1, 40, TTFlag::LowerBound, None);
+        tt.store(other, 1, 50, TTFlag::Exact, None);
+        let bucket = &tt.entries[tt.index(deep)];
+        for key in [deep, other] {
+            let copies = bucket.iter().filter(|slot| slot.load().key == key).count();
+            assert_eq!(copies, 1, "{:#x}", key);
+        }
+        assert_eq!(tt.probe(deep).map(|e| e.score), Some(40));
+        assert_eq!(tt.probe(other).map(|e| e.score), Some(50));
+    }
+
+    /// Two keys that land in the same bucket of a `TEST_TT_BYTES` table.
+    fn colliding_keys() -> (u64, u64, u64) {
+        let tt = TranspositionTable::with_capacity(TEST_TT_BYTES);
+        let stride = tt.mask as u64 + 1;
+        (7, 7 + stride, 7 + 2 * stride)
+    }
+
+    #[test]
+    fn test_tt_shallow_current_entry_survives_old_deep_one() {
+        let tt = TranspositionTable::with_capacity(TEST_TT_BYTES);
+        let (old, current, _) = colliding_keys();
+
+        tt.store(old, 12, 10, TTFlag::Exact, Some((1, 1)));
+        tt.new_search();
+        tt.store(current, 2, 20, TTFlag::Exact, Some((2, 2)));
+
+        assert_eq!(tt.probe(current).map(|e| e.depth), Some(2));
+        assert_eq!(tt.probe(old).map(|e| e.depth), Some(12));
+    }
+
+    #[test]
+    fn test_tt_deep_entry_of_current_search_is_kept() {
+        let tt = TranspositionTable::with_capacity(TEST_TT_BYTES);
+        let (deep, first, second) = colliding_keys();
+
+        tt.store(deep, 8, 10, TTFlag::Exact, None);
+        tt.store(first, 1, 20, TTFlag::Exact, None);
+        tt.store(second, 1, 30, TTFlag::Exact, None);
+
+        assert_eq!(tt.probe(deep).map(|e| e.score), Some(10));
+        assert!(tt.probe(first).is_none());
+        assert_eq!(tt.probe(second).map(|e| e.score), Some(30));
+    }
+
+    #[test]
+    fn test_tt_clear_resets_generation() {
+        let tt = TranspositionTable::with_capacity(TEST_TT_BYTES);
+        tt.new_search();
+        tt.store(99, 3, 1, TTFlag::Exact, None);
+        tt.clear();
+        assert!(tt.probe(99).is_none());
+        assert_eq!(tt.generation(), 0);
+    }
 }
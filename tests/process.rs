@@ -0,0 +1,42 @@
+//! Runs the built bot as the manager would, over pipes.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+#[test]
+fn closing_stdin_exits_cleanly() {
+    let mut bot = Command::new(env!("CARGO_BIN_EXE_pbrain-gomoku-ai"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = bot.stdin.take().unwrap();
+    let mut stdout = BufReader::new(bot.stdout.take().unwrap());
+
+    writeln!(stdin, "START 20").unwrap();
+    let mut reply = String::new();
+    stdout.read_line(&mut reply).unwrap();
+    assert_eq!(reply.trim(), "OK");
+
+    drop(stdin);
+    assert!(bot.wait().unwrap().success());
+}
+
+#[test]
+fn closed_stdout_exits_with_failure() {
+    let mut bot = Command::new(env!("CARGO_BIN_EXE_pbrain-gomoku-ai"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    drop(bot.stdout.take());
+    let mut stdin = bot.stdin.take().unwrap();
+
+    // The bot may already be gone by the second line.
+    let _ = writeln!(stdin, "START 20");
+    let _ = writeln!(stdin, "ABOUT");
+    drop(stdin);
+    assert!(!bot.wait().unwrap().success());
+}